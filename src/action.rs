@@ -0,0 +1,15 @@
+//! Things that can be asked for in more than one way, e.g. by a keypress or over remote control,
+//! so each gets carried out the same way whichever it came from.
+
+use crate::fs::settings::Param;
+
+pub enum Action {
+    PreviousPreset,
+    NextPreset,
+    /// Counting from 0.
+    Preset(usize),
+    Param(Param, f32),
+    Play,
+    Pause,
+    TogglePause,
+}
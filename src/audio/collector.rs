@@ -1,5 +1,6 @@
 use std::sync::Arc;
 use std::sync::Mutex;
+use std::time::Duration;
 
 use ringbuffer::RingBuffer;
 use rodio::ChannelCount;
@@ -9,43 +10,154 @@ use rodio::Source;
 
 use super::SAMPLES;
 
+/// Number of samples kept for each channel. More than gets analyzed at once, so the window can be
+/// moved back to what's actually playing. Must be a power of 2.
+const HISTORY: usize = 8 * SAMPLES;
+
 /// Collects a sliding window of samples into per-channel buffers.
 pub struct Collector {
     /// one for each of the channels in the source. Assumes that, upon initialization, the number
     /// of channels in source doesn't change.
-    channel_buffers: Vec<ringbuffer::ConstGenericRingBuffer<Sample, SAMPLES>>,
+    channel_buffers: Vec<ringbuffer::ConstGenericRingBuffer<Sample, HISTORY>>,
     /// A cached sample rate from the last time it updated
     cached_sample_rate: SampleRate,
+    /// Where in the music the samples counted in `frames` start from. Moves when seeking, or when
+    /// the sample rate changes.
+    clock_start: Duration,
+    /// How many samples of each channel have come in since `clock_start`.
+    frames: u64,
+    /// The only channel to analyze & play, if there's just one. Ignored if the music doesn't have
+    /// that many channels.
+    solo: Option<usize>,
 }
 
 impl Collector {
-    pub fn snapshot(&self, out: &mut [Sample; SAMPLES]) {
-        for buffer in self.channel_buffers.iter() {
+    /// Sums up the channels over a window of samples, ending at `position` in the music if it's
+    /// recent enough to still be kept, or at the most recent samples otherwise. Only the soloed
+    /// channel is counted, if there is one.
+    pub fn snapshot(&self, out: &mut [Sample; SAMPLES], position: Option<Duration>) {
+        let start = HISTORY - SAMPLES - self.frames_behind(position).unwrap_or(0);
+        let buffers = match self.soloed() {
+            Some(channel) => &self.channel_buffers[channel..=channel],
+            None => &self.channel_buffers[..],
+        };
+        for buffer in buffers {
             for i in 0..SAMPLES {
-                out[i] += buffer[i];
+                out[i] += buffer[start + i];
             }
         }
     }
 
+    /// How much louder the right channel is than the left over the same window as `snapshot()`,
+    /// from -1 (all left) to 1 (all right). Music that isn't stereo, or has a channel soloed, is
+    /// always balanced at 0.
+    pub fn balance(&self, position: Option<Duration>) -> f32 {
+        let [left, right] = match self.channel_buffers.as_slice() {
+            [left, right, ..] if self.soloed().is_none() => [left, right],
+            _ => return 0.0,
+        };
+        let start = HISTORY - SAMPLES - self.frames_behind(position).unwrap_or(0);
+        let energy = |buffer: &ringbuffer::ConstGenericRingBuffer<Sample, HISTORY>| {
+            (start..start + SAMPLES)
+                .map(|i| buffer[i] * buffer[i])
+                .sum::<f32>()
+        };
+        let (left, right) = (energy(left), energy(right));
+        if left + right > f32::EPSILON {
+            (right - left) / (right + left)
+        } else {
+            0.0
+        }
+    }
+
+    /// How many samples before the most recent one `position` is, if that's somewhere it makes
+    /// sense to analyze from.
+    fn frames_behind(&self, position: Option<Duration>) -> Option<usize> {
+        let behind = self.position().checked_sub(position?)?;
+        let frames = (behind.as_secs_f64() * f64::from(self.cached_sample_rate)) as usize;
+        (frames <= HISTORY - SAMPLES).then_some(frames)
+    }
+
+    /// Where in the music the most recent sample is.
+    fn position(&self) -> Duration {
+        self.clock_start
+            + Duration::from_secs_f64(
+                self.frames as f64 / f64::from(self.cached_sample_rate.max(1)),
+            )
+    }
+
     pub fn sample_rate(&self) -> SampleRate {
         self.cached_sample_rate
     }
 
+    /// How many channels the music has, or 0 if none of it has been played yet.
+    pub fn channels(&self) -> usize {
+        self.channel_buffers.len()
+    }
+
+    /// Analyzes & plays only one channel (counting from 0), or all of them with `None`. The soloed
+    /// channel gets played on every speaker, so it can be heard whichever one it was meant for.
+    pub fn set_solo(&mut self, channel: Option<usize>) {
+        self.solo = channel;
+    }
+
+    /// The channel being soloed, if there is one & the music has it.
+    fn soloed(&self) -> Option<usize> {
+        self.solo
+            .filter(|&channel| channel < self.channel_buffers.len())
+    }
+
     pub fn new<S: Source + Send>(source: S) -> (Arc<Mutex<Self>>, impl Source + Send) {
         let collector = Arc::new(Mutex::new(Self {
             channel_buffers: Vec::new(),
             cached_sample_rate: 0,
+            clock_start: Duration::ZERO,
+            frames: 0,
+            solo: None,
         }));
+        let source = Self::queue(&collector, source, Duration::ZERO);
+        (collector, source)
+    }
+
+    /// Collects from another source that gets played straight after the ones before it, like the
+    /// next time around a loop. Carries on from the samples already collected rather than starting
+    /// over, as long as the channels & sample rate stay the same. `start` is where in the music
+    /// this source begins, counting from the start of the first one.
+    pub fn queue<S: Source + Send>(
+        collector: &Arc<Mutex<Self>>,
+        source: S,
+        start: Duration,
+    ) -> impl Source + Send + use<S> {
         let c1 = collector.clone();
         let c2 = collector.clone();
-        let source = Inspectable::new(
+        let c3 = collector.clone();
+        Inspectable::new(
             source,
             move |sample, channel_index| {
                 let mut this = c1.lock().unwrap();
                 this.channel_buffers[usize::from(channel_index)].enqueue(sample);
+                if channel_index == 0 {
+                    this.frames += 1;
+                }
+                // The soloed channel's newest sample, which for the channels before it is from
+                // the frame before. Being a sample late doesn't make any difference to hear.
+                match this.soloed() {
+                    Some(channel) => this.channel_buffers[channel]
+                        .back()
+                        .copied()
+                        .unwrap_or(sample),
+                    None => sample,
+                }
             },
             move |num_channels, sample_rate| {
                 let mut this = c2.lock().unwrap();
+                if this.channel_buffers.len() == usize::from(num_channels)
+                    && this.cached_sample_rate == sample_rate
+                {
+                    return;
+                }
+                this.clock_start = this.position();
+                this.frames = 0;
                 this.channel_buffers = (0..num_channels)
                     .map(|_| {
                         let mut out = ringbuffer::ConstGenericRingBuffer::new();
@@ -55,24 +167,30 @@ impl Collector {
                     .collect();
                 this.cached_sample_rate = sample_rate;
             },
-        );
-
-        (collector, source)
+            move |position| {
+                let mut this = c3.lock().unwrap();
+                this.clock_start = start + position;
+                this.frames = 0;
+            },
+        )
     }
 }
 
-struct Inspectable<I, F1, F2>
+struct Inspectable<I, F1, F2, F3>
 where
     I: Source,
-    F1: FnMut(Sample, ChannelCount),
+    F1: FnMut(Sample, ChannelCount) -> Sample,
     F2: FnMut(ChannelCount, SampleRate),
+    F3: FnMut(Duration),
 {
     inner: I,
-    /// Called with (sample, channel_index)
+    /// Called with (sample, channel_index), & returns the sample to play instead
     sample_inspector: F1,
     /// Called with (num_channels, sample_rate) every time the .channels() or .sample_rate()
     /// functions change
     channels_inspector: F2,
+    /// Called with the new position every time the source seeks successfully
+    seek_inspector: F3,
 
     next_channel_index: rodio::ChannelCount,
     cached_current_span_len: Option<usize>,
@@ -80,17 +198,19 @@ where
     cached_sample_rate: rodio::SampleRate,
 }
 
-impl<I, F1, F2> Inspectable<I, F1, F2>
+impl<I, F1, F2, F3> Inspectable<I, F1, F2, F3>
 where
     I: Source,
-    F1: FnMut(Sample, ChannelCount),
+    F1: FnMut(Sample, ChannelCount) -> Sample,
     F2: FnMut(ChannelCount, SampleRate),
+    F3: FnMut(Duration),
 {
-    pub fn new(inner: I, sample_inspector: F1, channels_inspector: F2) -> Self {
+    pub fn new(inner: I, sample_inspector: F1, channels_inspector: F2, seek_inspector: F3) -> Self {
         Self {
             inner,
             sample_inspector,
             channels_inspector,
+            seek_inspector,
             next_channel_index: 0,
             cached_current_span_len: Some(0), // Make it so the next .next() call updates this
             cached_channels: 0,
@@ -99,11 +219,12 @@ where
     }
 }
 
-impl<I, F1, F2> Iterator for Inspectable<I, F1, F2>
+impl<I, F1, F2, F3> Iterator for Inspectable<I, F1, F2, F3>
 where
     I: Source,
-    F1: FnMut(Sample, ChannelCount),
+    F1: FnMut(Sample, ChannelCount) -> Sample,
     F2: FnMut(ChannelCount, SampleRate),
+    F3: FnMut(Duration),
 {
     type Item = Sample;
 
@@ -135,20 +256,19 @@ where
         }
 
         // output of a source is specified to always be interleaved
-        let out = self.inner.next();
-        if let Some(v) = out {
-            (self.sample_inspector)(v, self.next_channel_index);
-            self.next_channel_index = (self.next_channel_index + 1) % self.cached_channels;
-        }
-        out
+        let out = self.inner.next()?;
+        let out = (self.sample_inspector)(out, self.next_channel_index);
+        self.next_channel_index = (self.next_channel_index + 1) % self.cached_channels;
+        Some(out)
     }
 }
 
-impl<I, F1, F2> Source for Inspectable<I, F1, F2>
+impl<I, F1, F2, F3> Source for Inspectable<I, F1, F2, F3>
 where
     I: Source,
-    F1: FnMut(Sample, ChannelCount),
+    F1: FnMut(Sample, ChannelCount) -> Sample,
     F2: FnMut(ChannelCount, SampleRate),
+    F3: FnMut(Duration),
 {
     #[inline(always)]
     fn current_span_len(&self) -> Option<usize> {
@@ -172,6 +292,8 @@ where
 
     #[inline(always)]
     fn try_seek(&mut self, pos: std::time::Duration) -> Result<(), rodio::source::SeekError> {
-        self.inner.try_seek(pos)
+        self.inner.try_seek(pos)?;
+        (self.seek_inspector)(pos);
+        Ok(())
     }
 }
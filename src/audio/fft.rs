@@ -1,14 +1,17 @@
+use std::sync::{Arc, Mutex};
+
 use rodio::{Sample, SampleRate};
 
 use super::SAMPLES;
 
-struct FrequencyRange {
-    lo: f32,
-    hi: f32,
+#[derive(Copy, Clone)]
+pub struct FrequencyRange {
+    pub lo: f32,
+    pub hi: f32,
 }
 
 /// Defined frequency ranges that we want to plot graphically. All defined in terms of Hz.
-const FREQUENCY_RANGES: &[FrequencyRange] = {
+pub const DEFAULT_FREQUENCY_RANGES: [FrequencyRange; 5] = {
     const fn fr(lo: f32, hi: f32) -> FrequencyRange {
         FrequencyRange { lo, hi }
     }
@@ -19,13 +22,20 @@ const FREQUENCY_RANGES: &[FrequencyRange] = {
     const MIDS: FrequencyRange = fr(500.0, 2_000.0);
     const HIGHS: FrequencyRange = fr(2_000.0, 6_000.0);
 
-    &[SUB_BASS, BASS, LOW_MIDS, MIDS, HIGHS]
+    [SUB_BASS, BASS, LOW_MIDS, MIDS, HIGHS]
 };
-pub const NUM_BINS: usize = FREQUENCY_RANGES.len();
-
-/// Given a list of samples, compute the FFT & bucket the results into pre-determined frequency
-/// ranges.
-pub fn fft_buckets(samples: &mut [Sample; SAMPLES], sample_rate: SampleRate) -> Vec<f32> {
+pub const NUM_BINS: usize = DEFAULT_FREQUENCY_RANGES.len();
+
+/// The frequency ranges currently in use, shared between the worker bucketing samples into them
+/// & whoever is editing them.
+pub type SharedRanges = Arc<Mutex<[FrequencyRange; NUM_BINS]>>;
+
+/// Given a list of samples, compute the FFT & bucket the results into the given frequency ranges.
+pub fn fft_buckets(
+    samples: &mut [Sample; SAMPLES],
+    sample_rate: SampleRate,
+    ranges: &[FrequencyRange],
+) -> Vec<f32> {
     let spectrum = microfft::real::rfft_4096(samples);
     // since the real-valued coefficient at the Nyquist frequency is packed into the
     // imaginary part of the DC bin, it must be cleared before computing the amplitudes
@@ -35,7 +45,7 @@ pub fn fft_buckets(samples: &mut [Sample; SAMPLES], sample_rate: SampleRate) ->
     // How much frequency does each bucket produce?
     let resolution = sample_rate as f32 / (SAMPLES / 2) as f32;
 
-    FREQUENCY_RANGES
+    ranges
         .iter()
         .map(|r| {
             let index_lo = (r.lo / resolution).floor() as usize;
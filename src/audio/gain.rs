@@ -0,0 +1,125 @@
+//! Automatic gain control across the whole spectrum, so quiet & loud passages move the simulation
+//! by similar amounts. Follows the overall level of the bins with an envelope, and scales all of
+//! them by the same amount to bring that level to `TARGET_LEVEL`.
+
+use std::time::Duration;
+
+/// The overall level (mean of all bins) we try to bring the music to. Around where moderately
+/// loud music sits without any gain, so presets tuned without this behave similarly with it.
+const TARGET_LEVEL: f32 = 10.0;
+
+#[derive(Copy, Clone, Debug)]
+pub struct Config {
+    /// How quickly the gain comes down when the music gets louder.
+    pub attack: Duration,
+    /// How quickly the gain goes up when the music gets quieter.
+    pub release: Duration,
+    /// The most the bins can be amplified by, so silence doesn't get blown up into noise.
+    pub max_gain: f32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            attack: Duration::from_millis(100),
+            release: Duration::from_secs(2),
+            max_gain: 8.0,
+        }
+    }
+}
+
+pub struct AutoGain {
+    config: Config,
+    /// Slow-moving estimate of the overall level. `None` until we've seen some music.
+    envelope: Option<f32>,
+}
+
+impl AutoGain {
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            envelope: None,
+        }
+    }
+
+    /// Scales all the bins by the current gain, after updating the level estimate with them.
+    /// `elapsed` is how long it's been since the previous call.
+    pub fn apply(&mut self, bins: &mut [f32], elapsed: Duration) {
+        if bins.is_empty() {
+            return;
+        }
+        let level = bins.iter().sum::<f32>() / bins.len() as f32;
+        let envelope = match self.envelope {
+            Some(envelope) => {
+                let time_constant = if level > envelope {
+                    self.config.attack
+                } else {
+                    self.config.release
+                };
+                // Exponential smoothing that doesn't depend on how often we're called
+                let alpha = if time_constant.is_zero() {
+                    1.0
+                } else {
+                    1.0 - f32::exp(-elapsed.as_secs_f32() / time_constant.as_secs_f32())
+                };
+                envelope + (level - envelope) * alpha
+            }
+            None => level,
+        };
+        self.envelope = Some(envelope);
+
+        let gain = if envelope > f32::EPSILON {
+            (TARGET_LEVEL / envelope).min(self.config.max_gain)
+        } else {
+            self.config.max_gain
+        };
+        for bin in bins.iter_mut() {
+            *bin *= gain;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs the same spectrum through a fresh `AutoGain` over and over, for a few seconds' worth
+    /// of frames, and gives back what comes out of the last one.
+    fn settle(bins: &[f32]) -> Vec<f32> {
+        let mut gain = AutoGain::new(Config::default());
+        let mut out = bins.to_vec();
+        for _ in 0..300 {
+            out.copy_from_slice(bins);
+            gain.apply(&mut out, Duration::from_millis(16));
+        }
+        out
+    }
+
+    #[test]
+    fn loud_and_quiet_converge() {
+        let spectrum: Vec<f32> = (0..16).map(|i| 1.0 + (i % 5) as f32 * 4.0).collect();
+        let loud: Vec<f32> = spectrum.iter().map(|bin| bin * 3.0).collect();
+        let quiet: Vec<f32> = spectrum.iter().map(|bin| bin * 0.3).collect();
+
+        let loud = settle(&loud);
+        let quiet = settle(&quiet);
+        for (loud, quiet) in loud.iter().zip(&quiet) {
+            assert!(
+                (loud - quiet).abs() <= 0.01 * loud,
+                "loud {loud} & quiet {quiet} didn't converge"
+            );
+        }
+        let level = loud.iter().sum::<f32>() / loud.len() as f32;
+        assert!((level - TARGET_LEVEL).abs() < 0.1, "level {level}");
+    }
+
+    #[test]
+    fn silence_stays_under_max_gain() {
+        let mut gain = AutoGain::new(Config::default());
+        let mut bins = [0.01; 8];
+        gain.apply(&mut bins, Duration::from_millis(16));
+        for bin in bins {
+            assert!(bin <= 0.01 * Config::default().max_gain + f32::EPSILON);
+        }
+    }
+}
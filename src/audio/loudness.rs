@@ -0,0 +1,216 @@
+//! Evens out how loudly different tracks were mastered, so switching songs doesn't change how
+//! much the simulation reacts. Keeps a running estimate of the whole track's loudness, roughly
+//! the way ITU-R BS.1770 measures it, and scales the samples to bring it to a target.
+//!
+//! Unlike `gain`, this is meant to settle on one number per track rather than follow the music
+//! around, so quiet & loud passages within a song still look quiet & loud.
+//!
+//! It's only an approximation: the channels have already been mixed together by the time we see
+//! the samples, each FFT window counts as one block, and there's no relative gate.
+
+use rodio::{Sample, SampleRate};
+
+/// Blocks quieter than this (in LUFS) are silence, and don't count towards the track's loudness.
+const ABSOLUTE_GATE: f32 = -70.0;
+/// The most the samples get turned up or down by, in dB, so a track that's mostly silence
+/// doesn't get turned all the way up.
+const MAX_ADJUSTMENT: f32 = 24.0;
+
+pub struct Normalizer {
+    /// The loudness to bring tracks to, in LUFS.
+    target: f32,
+    /// Running total of the mean square of every block that made it past the gate.
+    total: f64,
+    blocks: u64,
+    /// The K-weighting filter, designed for the sample rate it was last used with.
+    filter: Option<(SampleRate, KWeighting)>,
+}
+
+impl Normalizer {
+    pub fn new(target: f32) -> Self {
+        Self {
+            target,
+            total: 0.0,
+            blocks: 0,
+            filter: None,
+        }
+    }
+
+    /// Adds these samples to the track's loudness estimate, then scales them to bring the track to
+    /// the target loudness.
+    pub fn apply(&mut self, samples: &mut [Sample], sample_rate: SampleRate) {
+        if sample_rate == 0 || samples.is_empty() {
+            return;
+        }
+        if !matches!(&self.filter, Some((rate, _)) if *rate == sample_rate) {
+            self.filter = Some((sample_rate, KWeighting::new(sample_rate)));
+        }
+        let (_, filter) = self.filter.as_ref().expect("filter was just set");
+
+        let mean_square = filter.mean_square(samples);
+        if loudness(mean_square) > ABSOLUTE_GATE {
+            self.total += mean_square;
+            self.blocks += 1;
+        }
+        if self.blocks == 0 {
+            // Nothing but silence so far
+            return;
+        }
+
+        let integrated = loudness(self.total / self.blocks as f64);
+        let adjustment = (self.target - integrated).clamp(-MAX_ADJUSTMENT, MAX_ADJUSTMENT);
+        let gain = 10f32.powf(adjustment / 20.0);
+        for sample in samples.iter_mut() {
+            *sample *= gain;
+        }
+    }
+}
+
+/// How loud a block with this mean square is, in LUFS.
+fn loudness(mean_square: f64) -> f32 {
+    (-0.691 + 10.0 * mean_square.max(f64::MIN_POSITIVE).log10()) as f32
+}
+
+/// The BS.1770 pre-filter: a high shelf to account for the head, then a high pass to ignore the
+/// lowest frequencies.
+struct KWeighting {
+    shelf: Biquad,
+    high_pass: Biquad,
+}
+
+impl KWeighting {
+    /// Works out the filter coefficients for any sample rate, from the analog prototypes the
+    /// standard's 48kHz coefficients come from.
+    fn new(sample_rate: SampleRate) -> Self {
+        let sample_rate = f64::from(sample_rate);
+
+        let shelf = {
+            const GAIN_DB: f64 = 3.999_843_853_973_347;
+            const Q: f64 = 0.707_175_236_955_419_3;
+            const CENTER: f64 = 1_681.974_450_955_532;
+            let k = (std::f64::consts::PI * CENTER / sample_rate).tan();
+            let vh = 10f64.powf(GAIN_DB / 20.0);
+            let vb = vh.powf(0.499_666_774_154_541_6);
+            let a0 = 1.0 + k / Q + k * k;
+            Biquad {
+                b: [
+                    (vh + vb * k / Q + k * k) / a0,
+                    2.0 * (k * k - vh) / a0,
+                    (vh - vb * k / Q + k * k) / a0,
+                ],
+                a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / Q + k * k) / a0],
+            }
+        };
+
+        let high_pass = {
+            const Q: f64 = 0.500_327_037_325_395_3;
+            const CENTER: f64 = 38.135_470_876_139_82;
+            let k = (std::f64::consts::PI * CENTER / sample_rate).tan();
+            let a0 = 1.0 + k / Q + k * k;
+            Biquad {
+                b: [1.0, -2.0, 1.0],
+                a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / Q + k * k) / a0],
+            }
+        };
+
+        Self { shelf, high_pass }
+    }
+
+    /// Mean square of the samples after filtering them. The filters start from rest each time,
+    /// since the blocks we get overlap & skip around rather than following on from each other.
+    fn mean_square(&self, samples: &[Sample]) -> f64 {
+        let mut shelf = BiquadState::default();
+        let mut high_pass = BiquadState::default();
+        let sum: f64 = samples
+            .iter()
+            .map(|sample| {
+                let filtered =
+                    high_pass.next(&self.high_pass, shelf.next(&self.shelf, f64::from(*sample)));
+                filtered * filtered
+            })
+            .sum();
+        sum / samples.len() as f64
+    }
+}
+
+struct Biquad {
+    /// Feed-forward coefficients
+    b: [f64; 3],
+    /// Feedback coefficients, with a0 normalized to 1
+    a: [f64; 2],
+}
+
+#[derive(Default)]
+struct BiquadState {
+    x: [f64; 2],
+    y: [f64; 2],
+}
+
+impl BiquadState {
+    fn next(&mut self, biquad: &Biquad, x: f64) -> f64 {
+        let y = biquad.b[0] * x + biquad.b[1] * self.x[0] + biquad.b[2] * self.x[1]
+            - biquad.a[0] * self.y[0]
+            - biquad.a[1] * self.y[1];
+        self.x = [x, self.x[0]];
+        self.y = [y, self.y[0]];
+        y
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::fft::fft_buckets;
+    use crate::audio::{DEFAULT_FREQUENCY_RANGES, SAMPLES};
+
+    const SAMPLE_RATE: SampleRate = 44_100;
+
+    /// A chord of a few tones, at some overall volume.
+    fn track(volume: f32) -> [Sample; SAMPLES] {
+        std::array::from_fn(|i| {
+            let t = i as f32 / SAMPLE_RATE as f32;
+            let tau = std::f32::consts::TAU;
+            volume
+                * (0.5 * (tau * 110.0 * t).sin()
+                    + 0.3 * (tau * 440.0 * t).sin()
+                    + 0.2 * (tau * 3_000.0 * t).sin())
+        })
+    }
+
+    /// The bins for a track once it's been normalized for a while.
+    fn normalized_bins(volume: f32) -> Vec<f32> {
+        let mut normalizer = Normalizer::new(-14.0);
+        for _ in 0..9 {
+            normalizer.apply(&mut track(volume), SAMPLE_RATE);
+        }
+        let mut samples = track(volume);
+        normalizer.apply(&mut samples, SAMPLE_RATE);
+        fft_buckets(&mut samples, SAMPLE_RATE, &DEFAULT_FREQUENCY_RANGES)
+    }
+
+    #[test]
+    fn loud_and_quiet_tracks_match() {
+        let loud = normalized_bins(0.8);
+        let quiet = normalized_bins(0.08);
+        for (loud, quiet) in loud.iter().zip(&quiet) {
+            assert!(
+                (loud - quiet).abs() <= 0.01 * loud.max(1.0),
+                "loud {loud:?} & quiet {quiet:?} don't match"
+            );
+        }
+        // Without normalizing, they were 20dB apart
+        let mut loud_raw = track(0.8);
+        let mut quiet_raw = track(0.08);
+        let loud_raw = fft_buckets(&mut loud_raw, SAMPLE_RATE, &DEFAULT_FREQUENCY_RANGES);
+        let quiet_raw = fft_buckets(&mut quiet_raw, SAMPLE_RATE, &DEFAULT_FREQUENCY_RANGES);
+        assert!(loud_raw.iter().sum::<f32>() > 5.0 * quiet_raw.iter().sum::<f32>());
+    }
+
+    #[test]
+    fn silence_is_left_alone() {
+        let mut normalizer = Normalizer::new(-14.0);
+        let mut samples = [0.0; SAMPLES];
+        normalizer.apply(&mut samples, SAMPLE_RATE);
+        assert!(samples.iter().all(|&sample| sample == 0.0));
+    }
+}
@@ -1,8 +1,15 @@
 pub mod collector;
 mod fft;
+pub mod gain;
+pub mod loudness;
+pub mod position;
 pub mod worker;
 
 /// Number of samples in the buffer. Must be a power of 2.
 pub const SAMPLES: usize = 4096;
+/// Extensions of the music files we can decode. Depends on which of rodio's features are enabled
+/// in Cargo.toml, so keep the two in sync.
+pub const FORMATS: &[&str] = &["flac", "mp3", "ogg", "wav"];
 /// Total number of frequency ranges we generate
 pub use fft::NUM_BINS;
+pub use fft::{DEFAULT_FREQUENCY_RANGES, FrequencyRange, SharedRanges};
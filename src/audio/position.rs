@@ -0,0 +1,66 @@
+//! Glides the position indicator along between the updates rodio gives us, which on some decoders
+//! only come every so often, so it doesn't step from one to the next.
+
+use web_time::{Duration, Instant};
+
+/// If the position reported jumps by more than this from what we're showing, it was a seek (or the
+/// music looped), so we go straight there instead of gliding.
+const SNAP_DISTANCE: Duration = Duration::from_millis(500);
+
+pub struct SmoothedPosition {
+    /// The last position reported, & when we first saw it.
+    reported: Option<(Duration, Instant)>,
+    /// What we showed last time.
+    shown: Duration,
+    /// How far past the last position reported we're willing to guess. Zero turns smoothing off.
+    max_ahead: Duration,
+}
+
+impl SmoothedPosition {
+    pub fn new(max_ahead: Duration) -> Self {
+        Self {
+            reported: None,
+            shown: Duration::ZERO,
+            max_ahead,
+        }
+    }
+
+    /// Goes straight to the next position reported, for right after seeking.
+    pub fn snap(&mut self) {
+        self.reported = None;
+    }
+
+    /// Where to show the music as being, given where rodio says it is. Only guesses ahead while
+    /// `playing`, at `speed` times real time.
+    pub fn update(
+        &mut self,
+        reported: Duration,
+        playing: bool,
+        speed: f32,
+        now: Instant,
+    ) -> Duration {
+        let since = match self.reported {
+            Some((last, _))
+                if last != reported && reported.abs_diff(self.shown) > SNAP_DISTANCE =>
+            {
+                None
+            }
+            Some((last, since)) if last == reported => Some(since),
+            Some(_) => Some(now),
+            None => None,
+        };
+        let Some(since) = since.filter(|_| playing && !self.max_ahead.is_zero()) else {
+            self.reported = Some((reported, now));
+            self.shown = reported;
+            return reported;
+        };
+        self.reported = Some((reported, since));
+        let ahead = now
+            .duration_since(since)
+            .mul_f32(speed.max(0.0))
+            .min(self.max_ahead);
+        // Small corrections back only hold it still for a bit, rather than making it twitch
+        self.shown = self.shown.max(reported + ahead);
+        self.shown
+    }
+}
@@ -1,16 +1,39 @@
 use std::sync::{Arc, Mutex, mpsc};
 
+use web_time::{Duration, Instant};
+
 use crate::audio::collector::Collector;
-use crate::audio::fft::fft_buckets;
+use crate::audio::fft::{SharedRanges, fft_buckets};
+use crate::audio::gain::{self, AutoGain};
+use crate::audio::loudness::Normalizer;
 use crate::audio::{NUM_BINS, SAMPLES};
 
+/// Asks the worker for another batch of work, for the music being heard at the given position if
+/// that's known.
+pub type Sender = mpsc::SyncSender<Option<Duration>>;
+/// The most recent bins, shared with whatever displays them.
+pub type SharedBins = Arc<Mutex<Vec<f32>>>;
+
 pub struct Worker {
-    /// Waits on this to start the next batch of work
-    rx: mpsc::Receiver<()>,
+    /// Waits on this to start the next batch of work, which says where in the music is being heard
+    /// right now if that's known
+    rx: mpsc::Receiver<Option<Duration>>,
     /// The samples collector that we are reading from
     collector: Arc<Mutex<Collector>>,
     /// The canonical most recent batch of frequency bins to display
-    bins: Arc<Mutex<Vec<f32>>>,
+    bins: SharedBins,
+    /// The same bins before auto-gain evened them out, for comparing against
+    raw_bins: SharedBins,
+    /// The stereo balance of the same samples, see `Collector::balance()`
+    balance: Arc<Mutex<f32>>,
+    /// Which frequencies go into each bin, which can change while running
+    ranges: SharedRanges,
+    /// Evens out the samples between loud & quiet tracks, if enabled
+    loudness: Option<Normalizer>,
+    /// Evens out the bins between quiet & loud passages, if enabled
+    gain: Option<AutoGain>,
+    /// When the previous batch of work was done
+    last_snapshot: Option<Instant>,
 }
 
 impl Worker {
@@ -18,27 +41,41 @@ impl Worker {
     /// simply need to attempt to put a value, and discard if the queue is full.
     pub fn new(
         collector: Arc<Mutex<Collector>>,
-    ) -> (mpsc::SyncSender<()>, Arc<Mutex<Vec<f32>>>, Self) {
+        ranges: SharedRanges,
+        gain: Option<gain::Config>,
+        loudness_target: Option<f32>,
+    ) -> (Sender, SharedBins, SharedBins, Arc<Mutex<f32>>, Self) {
         let (tx, rx) = mpsc::sync_channel(1);
         let bins = Arc::new(Mutex::new([0.0; NUM_BINS].into()));
+        let raw_bins = Arc::new(Mutex::new([0.0; NUM_BINS].into()));
+        let balance = Arc::new(Mutex::new(0.0));
         (
             tx,
             bins.clone(),
+            raw_bins.clone(),
+            balance.clone(),
             Self {
                 rx,
                 collector,
                 bins,
+                raw_bins,
+                balance,
+                ranges,
+                loudness: loudness_target.map(Normalizer::new),
+                gain: gain.map(AutoGain::new),
+                last_snapshot: None,
             },
         )
     }
 }
 
-/// Notifies the worker on the other size of tx that there is more work to be done.
-pub fn submit_work(tx: &mpsc::SyncSender<()>) {
-    match tx.try_send(()) {
+/// Notifies the worker on the other size of tx that there is more work to be done, for the music
+/// being heard at `position` if that's known.
+pub fn submit_work(tx: &Sender, position: Option<Duration>) {
+    match tx.try_send(position) {
         Ok(()) => {}
-        Err(mpsc::TrySendError::Full(())) => {}
-        Err(mpsc::TrySendError::Disconnected(())) => {
+        Err(mpsc::TrySendError::Full(_)) => {}
+        Err(mpsc::TrySendError::Disconnected(_)) => {
             panic!("worker stopped unexpectedly");
         }
     }
@@ -46,24 +83,39 @@ pub fn submit_work(tx: &mpsc::SyncSender<()>) {
 
 impl Worker {
     /// Main loop of the worker where is processes all incoming work. Should be run in its own
-    /// thread.
-    pub fn work(self) {
-        loop {
-            self.rx.recv().expect("sender closed unexpectedly");
-            self.snapshot_fft_buckets();
+    /// thread. Returns once the sender is dropped.
+    pub fn work(mut self) {
+        while let Ok(position) = self.rx.recv() {
+            self.snapshot_fft_buckets(position);
         }
     }
 
-    /// Given samples collected from an audio source, take a snapshot of the most recent samples
-    /// & bucket the results into pre-determined frequency ranges.
-    fn snapshot_fft_buckets(&self) {
+    /// Given samples collected from an audio source, take a snapshot of the samples being heard
+    /// at `position` (or the most recent ones, if that's not known) & bucket the results into the
+    /// current frequency ranges.
+    fn snapshot_fft_buckets(&mut self, position: Option<Duration>) {
         let mut samples = [0.0f32; SAMPLES];
-        let sample_rate = {
+        let (sample_rate, balance) = {
             let collector = self.collector.lock().unwrap();
-            collector.snapshot(&mut samples);
-            collector.sample_rate()
+            collector.snapshot(&mut samples, position);
+            (collector.sample_rate(), collector.balance(position))
         };
-        let new_bins = fft_buckets(&mut samples, sample_rate);
+        *self.balance.lock().unwrap() = balance;
+        if let Some(loudness) = &mut self.loudness {
+            loudness.apply(&mut samples, sample_rate);
+        }
+        let ranges = *self.ranges.lock().unwrap();
+        let mut new_bins = fft_buckets(&mut samples, sample_rate, &ranges);
+        self.raw_bins.lock().unwrap().clone_from(&new_bins);
+        let now = Instant::now();
+        if let Some(gain) = &mut self.gain {
+            let elapsed = self
+                .last_snapshot
+                .map(|last| now.duration_since(last))
+                .unwrap_or_default();
+            gain.apply(&mut new_bins, elapsed);
+        }
+        self.last_snapshot = Some(now);
         {
             let mut bins = self.bins.lock().unwrap();
             *bins = new_bins;
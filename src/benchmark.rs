@@ -0,0 +1,151 @@
+//! Measures how many particles this GPU can keep up with, without opening a window. The number of
+//! particles moving is doubled step by step up to how many there are, timing a few seconds of
+//! frames at each, until they can't be drawn at the target frame rate anymore.
+
+use std::path::PathBuf;
+
+use web_time::{Duration, Instant};
+use winit::dpi::PhysicalSize;
+
+use crate::constants::SIMULATION_NUM_PARTICLES;
+use crate::graphics;
+
+/// The frame rate to keep up by default.
+pub const DEFAULT_TARGET_FPS: f32 = 60.0;
+/// How many times fewer particles than there are the first step starts with.
+const FIRST_STEP_DIVISOR: usize = 32;
+/// How long each step gets timed for.
+const STEP_TIME: Duration = Duration::from_secs(3);
+/// Frames run before timing each step, so it doesn't include any warming up.
+const WARMUP_FRAMES: u32 = 10;
+/// What to render into, about the size of a small window.
+const SIZE: PhysicalSize<u32> = PhysicalSize::new(1280, 800);
+const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+/// Returns whether the benchmark could be run at all.
+pub fn run(settings_filename: PathBuf, backends: wgpu::Backends, target_fps: f32) -> bool {
+    let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
+        backends,
+        ..Default::default()
+    });
+    let adapter = match pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+        power_preference: wgpu::PowerPreference::HighPerformance,
+        force_fallback_adapter: false,
+        compatible_surface: None,
+    })) {
+        Ok(adapter) => adapter,
+        Err(e) => {
+            eprintln!("Error finding a graphics adapter: {e}");
+            return false;
+        }
+    };
+    let info = adapter.get_info();
+    println!(
+        "Benchmarking {} on {} ({} {})",
+        info.backend, info.name, info.driver, info.driver_info
+    );
+    if !crate::supports_required_limits(&adapter) {
+        return false;
+    }
+    let (device, queue) =
+        match pollster::block_on(adapter.request_device(&crate::device_descriptor(&adapter))) {
+            Ok(device) => device,
+            Err(e) => {
+                eprintln!("Error opening the adapter: {e}");
+                return false;
+            }
+        };
+
+    let mut pipeline = graphics::Pipeline::new(
+        &device,
+        &queue,
+        SIZE,
+        FORMAT,
+        graphics::Edges::Wrap,
+        None,
+        Some(Default::default()),
+    );
+    pipeline.read_settings_file(&queue, settings_filename);
+    pipeline.resize(&device, &queue, SIZE);
+    let target = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("benchmark_texture"),
+        size: wgpu::Extent3d {
+            width: SIZE.width,
+            height: SIZE.height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        view_formats: &[],
+    });
+
+    let target_frame_time = Duration::from_secs_f32(1.0 / target_fps);
+    let mut best = None;
+    let mut count = SIMULATION_NUM_PARTICLES / FIRST_STEP_DIVISOR;
+    loop {
+        pipeline.set_active_particles(count);
+        let mut frame = || frame(&mut pipeline, &device, &queue, &target);
+        let mut frames = 0;
+        let result = (0..WARMUP_FRAMES).try_for_each(|_| frame()).and_then(|()| {
+            let started = Instant::now();
+            while started.elapsed() < STEP_TIME {
+                frame()?;
+                frames += 1;
+            }
+            Ok(started.elapsed())
+        });
+        let elapsed = match result {
+            Ok(elapsed) => elapsed,
+            Err(e) => {
+                eprintln!("Error waiting for the GPU: {e}");
+                return false;
+            }
+        };
+        let frame_time = elapsed / frames.max(1);
+        println!(
+            "{count:>9} particles: {:.2} ms a frame ({:.0} fps)",
+            frame_time.as_secs_f64() * 1000.0,
+            f64::from(frames) / elapsed.as_secs_f64(),
+        );
+        if frame_time > target_frame_time {
+            break;
+        }
+        best = Some(count);
+        if count == SIMULATION_NUM_PARTICLES {
+            break;
+        }
+        count = (count * 2).min(SIMULATION_NUM_PARTICLES);
+    }
+
+    match best {
+        Some(SIMULATION_NUM_PARTICLES) => println!(
+            "All {SIMULATION_NUM_PARTICLES} particles keep up {target_fps} fps on {}",
+            info.name
+        ),
+        Some(best) => println!(
+            "At most about {best} particles keep up {target_fps} fps on {}, out of the \
+             {SIMULATION_NUM_PARTICLES} there are. Lowering SIMULATION_NUM_PARTICLES in \
+             src/constants.rs to that should run smoothly.",
+            info.name
+        ),
+        None => println!(
+            "Even {count} particles can't keep up {target_fps} fps on {}",
+            info.name
+        ),
+    }
+    true
+}
+
+/// Runs one frame & waits for the GPU to finish it, so each one is timed in full.
+fn frame(
+    pipeline: &mut graphics::Pipeline,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    target: &wgpu::Texture,
+) -> Result<(), wgpu::PollError> {
+    pipeline.render(device, queue, target, FORMAT, None);
+    device.poll(wgpu::PollType::Wait).map(|_| ())
+}
@@ -0,0 +1,138 @@
+//! Runs the whole pipeline for a few frames without a window, on a software adapter if there is
+//! one, to catch GPU-side mistakes (bind group layouts that don't match the shaders, buffers that
+//! are the wrong size, dispatches that are too big) without needing a real GPU or a screen.
+
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use winit::dpi::PhysicalSize;
+
+use crate::graphics;
+
+/// How many frames to run. Software adapters are slow, and most mistakes show up on the first.
+const FRAMES: u32 = 3;
+/// What to render into, about the size of a small window.
+const SIZE: PhysicalSize<u32> = PhysicalSize::new(1280, 800);
+const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+/// Returns whether everything worked. Having no software adapter to check with counts as working,
+/// since there's nothing wrong with the program.
+pub fn run(settings_filename: PathBuf) -> bool {
+    let Some(adapter) = software_adapter() else {
+        return true;
+    };
+    run_frames(&adapter, Some(settings_filename))
+}
+
+fn software_adapter() -> Option<wgpu::Adapter> {
+    let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
+    match pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+        power_preference: wgpu::PowerPreference::default(),
+        force_fallback_adapter: true,
+        compatible_surface: None,
+    })) {
+        Ok(adapter) => Some(adapter),
+        Err(e) => {
+            println!("No software adapter to check with ({e}), skipping");
+            None
+        }
+    }
+}
+
+/// Runs the frames with the presets in `settings_filename`, or the default ones if there isn't
+/// one, & returns whether everything worked.
+fn run_frames(adapter: &wgpu::Adapter, settings_filename: Option<PathBuf>) -> bool {
+    let info = adapter.get_info();
+    println!("Checking with {} on {}", info.backend, info.name);
+    if !crate::supports_required_limits(adapter) {
+        return false;
+    }
+    let (device, queue) =
+        match pollster::block_on(adapter.request_device(&crate::device_descriptor(adapter))) {
+            Ok(device) => device,
+            Err(e) => {
+                eprintln!("Error opening the adapter: {e}");
+                return false;
+            }
+        };
+    // Validation errors would normally panic, so collect them instead to report at the end
+    let errors = Arc::new(Mutex::new(Vec::new()));
+    device.on_uncaptured_error({
+        let errors = errors.clone();
+        Box::new(move |e| errors.lock().unwrap().push(e.to_string()))
+    });
+
+    let mut pipeline = graphics::Pipeline::new(
+        &device,
+        &queue,
+        SIZE,
+        FORMAT,
+        graphics::Edges::Wrap,
+        None,
+        Some(Default::default()),
+    );
+    if let Some(settings_filename) = settings_filename {
+        pipeline.read_settings_file(&queue, settings_filename);
+    }
+    pipeline.resize(&device, &queue, SIZE);
+    let target = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("check_texture"),
+        size: wgpu::Extent3d {
+            width: SIZE.width,
+            height: SIZE.height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    });
+
+    let mut frame = None;
+    for i in 0..FRAMES {
+        if i + 1 == FRAMES {
+            pipeline.request_capture();
+        }
+        frame = pipeline.render(&device, &queue, &target, FORMAT, None);
+    }
+
+    let errors = std::mem::take(&mut *errors.lock().unwrap());
+    for e in &errors {
+        eprintln!("GPU error: {e}");
+    }
+    let Some(frame) = frame else {
+        eprintln!("The last frame was never read back");
+        return false;
+    };
+    // Everything below the header is the simulation, which shouldn't be one flat color after
+    // particles have been moving around in it
+    let rows = frame.rgba.chunks(frame.width as usize * 4);
+    let mut pixels = rows
+        .skip(crate::constants::HEADER_HEIGHT as usize)
+        .flat_map(|row| row.chunks(4));
+    let first = pixels.next();
+    if pixels.all(|pixel| Some(pixel) == first) {
+        eprintln!("The simulation didn't draw anything");
+        return false;
+    }
+    if !errors.is_empty() {
+        return false;
+    }
+    println!("Ran {FRAMES} frames without any problems");
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_presets_run_on_a_software_adapter() {
+        let Some(adapter) = software_adapter() else {
+            return;
+        };
+        assert!(run_frames(&adapter, None));
+    }
+}
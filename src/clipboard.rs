@@ -0,0 +1,26 @@
+//! Copies screenshots to the system clipboard, where there is one.
+
+use std::sync::mpsc;
+
+use crate::graphics::Image;
+use crate::platform::{Current, Platform};
+
+pub struct Clipboard {
+    tx: mpsc::Sender<Image>,
+}
+
+impl Clipboard {
+    /// Connects to the system clipboard, if there is one.
+    pub fn open() -> Result<Self, String> {
+        Ok(Self {
+            tx: Current::open_clipboard()?,
+        })
+    }
+
+    /// Copies the image to the clipboard on its own thread, so the event loop isn't held up
+    /// converting it.
+    pub fn copy(&self, image: Image) {
+        // The thread only stops if connecting failed, in which case we never got this far
+        let _ = self.tx.send(image);
+    }
+}
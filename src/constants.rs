@@ -1,16 +1,75 @@
+use std::time::Duration;
+
 use crate::audio::NUM_BINS;
 use crate::shaders::compute_shader::Constants;
 use crate::shaders::compute_shader::PointSettings;
+use crate::shaders::compute_shader::WORK_GROUP_SIZE;
 
 pub const HEADER_HEIGHT: u32 = 60;
 pub const FFT_BIN_WIDTH: u32 = 60;
 pub const FFT_WIDTH: u32 = FFT_BIN_WIDTH * NUM_BINS as u32;
 pub const PLAYBACK_WIDTH: u32 = 300;
+pub const SPARKLINE_WIDTH: u32 = 200;
 
 pub const SIMULATION_WIDTH: u32 = 1280;
 pub const SIMULATION_HEIGHT: u32 = 736;
-pub const SIMULATION_WORK_GROUP_SIZE: u32 = 16;
+/// Set in the shader, so dispatches can't disagree with it.
+pub const SIMULATION_WORK_GROUP_SIZE: u32 = WORK_GROUP_SIZE;
 pub const SIMULATION_NUM_PARTICLES: usize = 512 * 512 * 22;
+/// Upper limit on how many times the trail can be diffused in a single frame, since each pass
+/// is a full-screen dispatch.
+pub const MAX_DIFFUSION_PASSES: u32 = 8;
+/// Upper limit on how far the trail is blurred along each axis in one diffusion pass, since every
+/// pixel reads `(2 * x + 1) * (2 * y + 1)` of its neighbors.
+pub const MAX_DIFFUSION_RADIUS: u32 = 4;
+/// Upper limit on how many sensors each particle can have, since every particle samples the
+/// trail once per sensor each frame.
+pub const MAX_SENSORS: u32 = 9;
+/// Slowest & fastest particles can be made to move, relative to what the point settings say.
+pub const MOVE_SPEED_RANGE: (f32, f32) = (0.1, 10.0);
+/// How much each keypress speeds particles up or slows them down by.
+pub const MOVE_SPEED_STEP: f32 = 1.1;
+/// Weakest & strongest particles can be made to turn towards the trail, relative to what the
+/// point settings say.
+pub const TURN_STRENGTH_RANGE: (f32, f32) = (0.1, 10.0);
+/// How much each keypress strengthens or weakens turning by.
+pub const TURN_STRENGTH_STEP: f32 = 1.1;
+/// Least & most the music's effect on the parameters can be scaled by, from none to 4 times as
+/// much as the bands are set up for.
+pub const REACTIVITY_RANGE: (f32, f32) = (0.0, 4.0);
+/// How much each keypress turns the music's effect up or down by.
+pub const REACTIVITY_STEP: f32 = 0.1;
+/// Darkest & brightest the exposure of the trail can be set to, where 1 leaves it as it is.
+pub const EXPOSURE_RANGE: (f32, f32) = (0.1, 20.0);
+/// How much each keypress brightens or darkens the exposure by.
+pub const EXPOSURE_STEP: f32 = 1.25;
+/// Least & most the gamma of the trail can be set to, where 1 leaves it as it is.
+pub const GAMMA_RANGE: (f32, f32) = (0.25, 4.0);
+/// How much each keypress raises or lowers the gamma by.
+pub const GAMMA_STEP: f32 = 1.1;
+/// Least & most trail each particle can be made to lay down, where 0 leaves none at all.
+pub const DEPOSIT_FACTOR_RANGE: (f32, f32) = (0.0, 0.1);
+/// How much each keypress raises or lowers the trail laid down by.
+pub const DEPOSIT_FACTOR_STEP: f32 = 1.25;
+/// Lowering the deposit below this turns it off, & raising it from off starts here.
+pub const MIN_DEPOSIT_FACTOR: f32 = 0.0002;
+/// Least & most strongly particles can be made to steer away from crowded pixels, where 0 doesn't
+/// at all.
+pub const REPULSION_RANGE: (f32, f32) = (0.0, 2.0);
+/// How much each keypress raises or lowers the repulsion by.
+pub const REPULSION_STEP: f32 = 0.1;
+/// How long a frame `CONSTANTS.decay_factor` is the decay for, 60 frames a second. Frames that take
+/// more or less time decay the trail more or less, so it fades at the same speed whatever the
+/// frame rate.
+pub const DECAY_FRAME_TIME: Duration = Duration::from_micros(16_667);
+/// Frames that take longer than this, e.g. after a stall, only decay the trail by this much so it
+/// isn't wiped out.
+pub const MAX_DECAY_FRAME_TIME: Duration = Duration::from_millis(100);
+
+/// How many steps particles live for by default before respawning somewhere random.
+pub const DEFAULT_PARTICLE_LIFETIME: u32 = 1000;
+/// Longer lifetimes get lost in the precision the shader keeps each particle's age at.
+pub const MAX_PARTICLE_LIFETIME: u32 = 10_000;
 
 pub const CONSTANTS: Constants = Constants {
     width: SIMULATION_WIDTH,
@@ -18,8 +77,27 @@ pub const CONSTANTS: Constants = Constants {
     reset_value: 0,
     deposit_factor: 0.003,
     decay_factor: 0.75,
+    count_scale: 10.0,
+    count_blend: 1.0,
+    wrap_edges: 1,
+    sensor_count: 3,
+    sensor_layout: 0,
+    move_speed: 1.0,
+    trail_colors: 0,
+    diffusion_radius_x: 1,
+    diffusion_radius_y: 1,
+    turn_strength: 1.0,
+    stereo_bias: 0.0,
+    heatmap: 0,
+    respawn_rate: 1.0 / DEFAULT_PARTICLE_LIFETIME as f32,
+    repulsion: 0.0,
 };
 
+/// Default color the trail is multiplied by, leaves it untouched.
+pub const DEFAULT_TINT: [f32; 3] = [1.0, 1.0, 1.0];
+/// Default color behind the simulation, plain black.
+pub const DEFAULT_BACKGROUND: [f32; 4] = [0.0, 0.0, 0.0, 1.0];
+
 pub const DEFAULT_INCREMENT_SETTINGS: PointSettings = PointSettings {
     default_scaling_factor: 0.01,
     sd_base: 0.01,
@@ -448,3 +526,101 @@ pub const DEFAULT_POINT_SETTINGS: &[PointSettings; 24] = &[
         default_scaling_factor: 8.60,
     }, // from bleuje
 ];
+
+/// A point setting that doesn't change anything, for building the FFT routings out of.
+const NO_CHANGE: PointSettings = PointSettings {
+    default_scaling_factor: 0.0,
+    sd_base: 0.0,
+    sd_exponent: 0.0,
+    sd_amplitude: 0.0,
+    sa_base: 0.0,
+    sa_exponent: 0.0,
+    sa_amplitude: 0.0,
+    ra_base: 0.0,
+    ra_exponent: 0.0,
+    ra_amplitude: 0.0,
+    md_base: 0.0,
+    md_exponent: 0.0,
+    md_amplitude: 0.0,
+    sensor_bias_1: 0.0,
+    sensor_bias_2: 0.0,
+};
+
+/// Ready-made ways for the music to drive the parameters, each with what every FFT bin does from
+/// the lowest frequencies to the highest. Higher bins are usually quieter, so they're given more
+/// to do for the same effect.
+pub const FFT_ROUTINGS: &[(&str, [PointSettings; NUM_BINS])] = &[
+    (
+        "bass drives move distance",
+        [
+            PointSettings {
+                md_base: 0.02,
+                ..NO_CHANGE
+            },
+            PointSettings {
+                md_base: 0.01,
+                ..NO_CHANGE
+            },
+            NO_CHANGE,
+            NO_CHANGE,
+            NO_CHANGE,
+        ],
+    ),
+    (
+        "highs drive rotation angle",
+        [
+            NO_CHANGE,
+            NO_CHANGE,
+            NO_CHANGE,
+            PointSettings {
+                ra_base: 0.01,
+                ..NO_CHANGE
+            },
+            PointSettings {
+                ra_base: 0.04,
+                ..NO_CHANGE
+            },
+        ],
+    ),
+    (
+        "mids drive sensor angle",
+        [
+            NO_CHANGE,
+            NO_CHANGE,
+            PointSettings {
+                sa_base: 0.01,
+                ..NO_CHANGE
+            },
+            PointSettings {
+                sa_base: 0.02,
+                ..NO_CHANGE
+            },
+            NO_CHANGE,
+        ],
+    ),
+    (
+        "whole mix drives sensor distance",
+        [
+            PointSettings {
+                sd_base: 0.05,
+                ..NO_CHANGE
+            },
+            PointSettings {
+                sd_base: 0.05,
+                ..NO_CHANGE
+            },
+            PointSettings {
+                sd_base: 0.1,
+                ..NO_CHANGE
+            },
+            PointSettings {
+                sd_base: 0.1,
+                ..NO_CHANGE
+            },
+            PointSettings {
+                sd_base: 0.2,
+                ..NO_CHANGE
+            },
+        ],
+    ),
+];
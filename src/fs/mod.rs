@@ -4,32 +4,126 @@
 use std::path::PathBuf;
 
 use bytemuck::Zeroable;
+use web_time::{Duration, Instant};
 use winit::keyboard::KeyCode;
 
 use crate::fs::settings::BinIndex;
 use crate::fs::settings::DisplaySettings;
+use crate::fs::settings::Framing;
 use crate::fs::settings::Param;
+use crate::fs::settings::Randomizer;
 use crate::fs::settings::Settings;
+use crate::graphics::Theme;
+use crate::platform::{Current, Platform};
 use crate::{constants, shaders::compute_shader};
 
 pub mod point_settings;
 pub mod settings;
 
-fn write_settings(mut w: impl std::io::Write, settings: &[Settings]) -> std::io::Result<()> {
+/// Which version of the file format we write. Bump this whenever old files need upgrading to be
+/// read, and add how to upgrade them to `read_settings`.
+///
+/// * 0: A bare array of presets.
+/// * 1: An object with the version & the array of presets.
+const SETTINGS_VERSION: u32 = 1;
+
+/// How long F9 waits to be pressed again before deleting the current preset.
+const DELETE_CONFIRM_TIME: Duration = Duration::from_secs(3);
+
+/// What's in a settings file, as of `SETTINGS_VERSION`.
+#[derive(facet::Facet)]
+struct SettingsFile {
+    version: u32,
+    /// The colors of the header text, shared by every preset.
+    #[facet(default)]
+    theme: Theme,
+    /// How the simulation is fitted into the window, shared by every preset.
+    #[facet(default)]
+    framing: Framing,
+    /// How randomizing picks parameters, shared by every preset.
+    #[facet(default)]
+    randomizer: Randomizer,
+    /// The most presets F1 can duplicate up to, or 0 for as many as you like.
+    #[facet(default)]
+    max_presets: usize,
+    presets: Vec<Settings>,
+}
+
+fn write_settings(
+    mut w: impl std::io::Write,
+    settings: &[Settings],
+    theme: &Theme,
+    framing: Framing,
+    randomizer: &Randomizer,
+    max_presets: usize,
+) -> std::io::Result<()> {
+    let file = SettingsFile {
+        version: SETTINGS_VERSION,
+        theme: theme.clone(),
+        framing,
+        randomizer: randomizer.clone(),
+        max_presets,
+        presets: settings.to_vec(),
+    };
     let mut buf = Vec::<u8>::with_capacity(std::mem::size_of_val(settings));
-    facet_json::to_writer(&settings, &mut buf)
+    facet_json::to_writer(&file, &mut buf)
         .map_err(|err| std::io::Error::other(format!("{:?}", err)))?;
     w.write_all(&buf)
 }
 
-fn read_settings(mut r: impl std::io::Read) -> std::io::Result<Vec<Settings>> {
+/// Reads settings written by any version so far, upgrading them to the current format. They get
+/// written back in the current format the next time they're saved. Files without any presets are
+/// rejected, since there'd be nothing to run.
+fn read_settings(mut r: impl std::io::Read) -> std::io::Result<SettingsFile> {
     let mut buf = Vec::<u8>::new();
     r.read_to_end(&mut buf)?;
-    facet_json::from_slice(&buf).map_err(|err| std::io::Error::other(format!("{}", err)))
+    let parse_error = |err| std::io::Error::other(format!("{}", err));
+
+    // Version 0 files are just the array, from before there was a version to check
+    let file = if buf.trim_ascii_start().starts_with(b"[") {
+        SettingsFile {
+            version: SETTINGS_VERSION,
+            theme: Theme::default(),
+            framing: Framing::default(),
+            randomizer: Randomizer::default(),
+            max_presets: 0,
+            presets: facet_json::from_slice(&buf).map_err(parse_error)?,
+        }
+    } else {
+        facet_json::from_slice(&buf).map_err(parse_error)?
+    };
+    if file.version > SETTINGS_VERSION {
+        return Err(std::io::Error::other(format!(
+            "settings file is version {}, but only up to version {SETTINGS_VERSION} can be read",
+            file.version
+        )));
+    }
+    if file.presets.is_empty() {
+        return Err(std::io::Error::other(
+            "settings file has no presets, it needs at least one",
+        ));
+    }
+    Ok(file)
+}
+
+/// One of the two places settings can be stashed, to compare them against each other.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Slot {
+    A,
+    B,
+}
+
+impl Slot {
+    fn other(self) -> Self {
+        match self {
+            Self::A => Self::B,
+            Self::B => Self::A,
+        }
+    }
 }
 
 /// These are the collection of all settings that can be loaded into memory at once. Only
-/// `presets` is ever persisted to disk.
+/// `presets`, `theme`, `framing`, `randomizer`, & `max_presets` are ever persisted to disk.
 pub struct AllSettings {
     /// Where we should persist our settings to disk.
     pub filename: Option<PathBuf>,
@@ -43,9 +137,31 @@ pub struct AllSettings {
     index: usize,
     /// Whether `settings != presets[index]`, cached for performance.
     dirty: bool,
+    /// What the simulation is running with while edits are being staged, so they only take effect
+    /// once applied. `None` when editing live, where the simulation just uses `settings`.
+    live: Option<Settings>,
+    /// Whether `settings` has staged edits that haven't been applied to `live` yet.
+    pending: bool,
+    /// Which of `constants::FFT_ROUTINGS` gets applied next.
+    next_routing: usize,
+    /// The colors of the header text.
+    theme: Theme,
+    /// How the simulation is fitted into the window.
+    framing: Framing,
+    /// How randomizing picks parameters.
+    randomizer: Randomizer,
+    /// The most presets there can be before F1 refuses to add any more, or 0 for no limit.
+    max_presets: usize,
+    /// Settings stashed in the A & B slots, if any, separate from the presets.
+    slots: [Option<Settings>; 2],
+    /// Which slot was switched to last.
+    compared: Option<Slot>,
+    /// Which preset F9 was pressed to delete, & when, while it's waiting to be pressed again.
+    delete_armed: Option<(usize, Instant)>,
 }
 
 impl AllSettings {
+    /// `presets` MUST NOT be empty, which `read_settings` makes sure of for files.
     fn from_presets(presets: Vec<Settings>) -> Self {
         Self {
             filename: None,
@@ -53,6 +169,16 @@ impl AllSettings {
             presets,
             index: 0,
             dirty: false,
+            live: None,
+            pending: false,
+            next_routing: 0,
+            theme: Theme::default(),
+            framing: Framing::default(),
+            randomizer: Randomizer::default(),
+            max_presets: 0,
+            slots: [None, None],
+            compared: None,
+            delete_armed: None,
         }
     }
 
@@ -65,15 +191,34 @@ impl AllSettings {
             }
         };
 
-        let file = std::fs::File::create(filename)?;
-        write_settings(file, &self.presets)
+        let file = Current::create_file(filename)?;
+        write_settings(
+            file,
+            &self.presets,
+            &self.theme,
+            self.framing,
+            &self.randomizer,
+            self.max_presets,
+        )
     }
 
-    fn read(path: PathBuf) -> std::io::Result<Self> {
-        let file = std::fs::File::open(&path)?;
-        let presets = read_settings(file)?;
+    pub fn read(path: PathBuf) -> std::io::Result<Self> {
+        let file = Current::open_file(&path)?;
+        let SettingsFile {
+            theme,
+            framing,
+            randomizer,
+            max_presets,
+            presets,
+            ..
+        } = read_settings(file)?;
 
-        Ok(Self::from_presets(presets).with_filename(path))
+        let mut settings = Self::from_presets(presets).with_filename(path);
+        settings.theme = theme.validated();
+        settings.framing = framing;
+        settings.randomizer = randomizer.validated();
+        settings.max_presets = max_presets;
+        Ok(settings)
     }
 
     pub fn read_or_default(path: PathBuf) -> Self {
@@ -96,26 +241,224 @@ impl Default for AllSettings {
         Self::from_presets(
             constants::DEFAULT_POINT_SETTINGS
                 .iter()
-                .map(|settings| Settings {
-                    base: DisplaySettings {
-                        current: (*settings).into(),
-                        increment: constants::DEFAULT_INCREMENT_SETTINGS.into(),
-                    },
-                    fft: std::array::repeat(DisplaySettings {
-                        current: compute_shader::PointSettings::zeroed().into(),
-                        increment: constants::DEFAULT_INCREMENT_SETTINGS.into(),
-                    }),
-                })
+                .map(default_preset)
                 .collect(),
         )
     }
 }
 
+/// A preset running one of the default point settings, with everything else left as default.
+fn default_preset(settings: &compute_shader::PointSettings) -> Settings {
+    Settings {
+        base: DisplaySettings {
+            current: (*settings).into(),
+            increment: constants::DEFAULT_INCREMENT_SETTINGS.into(),
+        },
+        fft: std::array::repeat(DisplaySettings {
+            current: compute_shader::PointSettings::zeroed().into(),
+            increment: constants::DEFAULT_INCREMENT_SETTINGS.into(),
+        }),
+        tint: constants::DEFAULT_TINT,
+        background: constants::DEFAULT_BACKGROUND,
+        steps: Default::default(),
+        sensors: Default::default(),
+        diffusion: Default::default(),
+        move_speed: 1.0,
+        turn_strength: 1.0,
+        trail_colors: Default::default(),
+        fft_mix: Default::default(),
+        reactivity: 1.0,
+        smoothing: Default::default(),
+        exposure: 1.0,
+        gamma: 1.0,
+        stereo_lean: 0.0,
+        deposit_factor: constants::CONSTANTS.deposit_factor,
+        repulsion: 0.0,
+    }
+}
+
 impl AllSettings {
     pub fn get_settings(&self) -> &Settings {
         &self.settings
     }
 
+    /// The settings the simulation should run with, which lag behind `get_settings()` while edits
+    /// are being staged.
+    pub fn get_live_settings(&self) -> &Settings {
+        self.live.as_ref().unwrap_or(&self.settings)
+    }
+
+    /// Whether edits are being held back until they're applied, rather than taking effect live.
+    pub fn is_staging(&self) -> bool {
+        self.live.is_some()
+    }
+
+    /// Whether there are staged edits that haven't been applied yet.
+    pub fn get_pending(&self) -> bool {
+        self.pending
+    }
+
+    /// Switches between staging edits & applying them live. Any staged edits take effect when
+    /// switching back to live.
+    pub fn toggle_staging(&mut self) {
+        self.live = match self.live {
+            Some(_) => None,
+            None => Some(self.settings.clone()),
+        };
+        self.pending = false;
+    }
+
+    /// Makes the simulation run with the current settings, if edits were being staged.
+    fn apply(&mut self) {
+        if let Some(live) = &mut self.live {
+            *live = self.settings.clone();
+        }
+        self.pending = false;
+    }
+
+    /// Marks the current settings as changed, both from the preset & from what's live.
+    fn edited(&mut self) {
+        self.dirty = true;
+        self.pending = self.live.is_some();
+    }
+
+    pub fn randomizer(&self) -> &Randomizer {
+        &self.randomizer
+    }
+
+    pub fn theme(&self) -> &Theme {
+        &self.theme
+    }
+
+    pub fn framing(&self) -> Framing {
+        self.framing
+    }
+
+    /// Switches how the simulation is fitted into the window, saving it straight away. Unlike
+    /// edits to a preset, there's nothing to hold this back for.
+    pub fn toggle_framing(&mut self) {
+        self.framing = self.framing.next();
+        if let Err(e) = self.write() {
+            eprintln!("Error saving file: {e}");
+        }
+    }
+
+    /// Makes particles turn towards the trail more or less strongly, by a step.
+    pub fn nudge_turn_strength(&mut self, stronger: bool) {
+        let step = if stronger {
+            constants::TURN_STRENGTH_STEP
+        } else {
+            constants::TURN_STRENGTH_STEP.recip()
+        };
+        let (min, max) = constants::TURN_STRENGTH_RANGE;
+        self.settings.turn_strength = (self.settings.turn_strength * step).clamp(min, max);
+        self.edited();
+    }
+
+    /// Turns how much the music moves the parameters up or down a step.
+    pub fn nudge_reactivity(&mut self, up: bool) {
+        let step = if up {
+            constants::REACTIVITY_STEP
+        } else {
+            -constants::REACTIVITY_STEP
+        };
+        let (min, max) = constants::REACTIVITY_RANGE;
+        // Rounded so steps land back on exact tenths, e.g. 0 rather than something tiny
+        let reactivity = ((self.settings.reactivity + step) * 10.0).round() / 10.0;
+        self.settings.reactivity = reactivity.clamp(min, max);
+        self.edited();
+        println!(
+            "Reacting to music at {:.0}%",
+            self.settings.reactivity * 100.0
+        );
+    }
+
+    /// Brightens or darkens the faint parts of the trail by a step.
+    pub fn nudge_exposure(&mut self, up: bool) {
+        let step = if up {
+            constants::EXPOSURE_STEP
+        } else {
+            constants::EXPOSURE_STEP.recip()
+        };
+        let (min, max) = constants::EXPOSURE_RANGE;
+        self.settings.exposure = (self.settings.exposure * step).clamp(min, max);
+        self.edited();
+        println!("Exposure at {:.2}", self.settings.exposure);
+    }
+
+    /// Raises or lowers the gamma the trail is rendered with by a step.
+    pub fn nudge_gamma(&mut self, up: bool) {
+        let step = if up {
+            constants::GAMMA_STEP
+        } else {
+            constants::GAMMA_STEP.recip()
+        };
+        let (min, max) = constants::GAMMA_RANGE;
+        self.settings.gamma = (self.settings.gamma * step).clamp(min, max);
+        self.edited();
+        println!("Gamma at {:.2}", self.settings.gamma);
+    }
+
+    /// Raises or lowers how much trail particles lay down by a step, turning it off below the
+    /// smallest step.
+    pub fn nudge_deposit_factor(&mut self, up: bool) {
+        let deposit_factor = if up {
+            (self.settings.deposit_factor * constants::DEPOSIT_FACTOR_STEP)
+                .max(constants::MIN_DEPOSIT_FACTOR)
+        } else {
+            self.settings.deposit_factor / constants::DEPOSIT_FACTOR_STEP
+        };
+        let (min, max) = constants::DEPOSIT_FACTOR_RANGE;
+        self.settings.deposit_factor = if deposit_factor < constants::MIN_DEPOSIT_FACTOR {
+            0.0
+        } else {
+            deposit_factor.clamp(min, max)
+        };
+        self.edited();
+        println!("Depositing trail at {:.4}", self.settings.deposit_factor);
+    }
+
+    /// Makes particles steer away from crowded pixels more or less strongly, by a step.
+    pub fn nudge_repulsion(&mut self, up: bool) {
+        let step = if up {
+            constants::REPULSION_STEP
+        } else {
+            -constants::REPULSION_STEP
+        };
+        let (min, max) = constants::REPULSION_RANGE;
+        // Rounded so steps land back on exact tenths, e.g. 0 rather than something tiny
+        let repulsion = ((self.settings.repulsion + step) * 10.0).round() / 10.0;
+        self.settings.repulsion = repulsion.clamp(min, max);
+        self.edited();
+        println!("Repelling particles at {:.1}", self.settings.repulsion);
+    }
+
+    /// Stashes the current settings in a slot, replacing whatever was there.
+    pub fn store_slot(&mut self, slot: Slot) {
+        self.slots[slot as usize] = Some(self.settings.clone());
+        self.compared = Some(slot);
+        println!("Stored the current settings in {slot:?}");
+    }
+
+    /// Switches to the other slot's settings from the one switched to last, without touching the
+    /// preset. If only one slot has anything in it, switches to that.
+    pub fn switch_slot(&mut self) {
+        let preferred = self.compared.map_or(Slot::A, Slot::other);
+        let Some(slot) = [preferred, preferred.other()]
+            .into_iter()
+            .find(|slot| self.slots[*slot as usize].is_some())
+        else {
+            println!("Nothing stored to compare yet");
+            return;
+        };
+        if let Some(settings) = &self.slots[slot as usize] {
+            self.settings = settings.clone();
+        }
+        self.compared = Some(slot);
+        self.edited();
+        println!("Comparing: showing {slot:?}");
+    }
+
     pub fn get_index(&self) -> usize {
         self.index
     }
@@ -124,54 +467,148 @@ impl AllSettings {
         self.dirty
     }
 
+    pub fn presets(&self) -> &[Settings] {
+        &self.presets
+    }
+
+    pub fn num_presets(&self) -> usize {
+        self.presets.len()
+    }
+
+    /// Keeps `index` pointing at a preset, putting back a default one if the list has somehow
+    /// been emptied, so nothing that goes by `index` can be out of range.
+    fn ensure_valid_index(&mut self) {
+        if self.presets.is_empty() {
+            eprintln!("No presets left, this shouldn't happen");
+            eprintln!("Falling back to a default preset...");
+            self.presets
+                .push(default_preset(&constants::DEFAULT_POINT_SETTINGS[0]));
+        }
+        self.index = self.index.min(self.presets.len() - 1);
+    }
+
+    /// Whether F9 has been pressed once to delete the current preset, & can be pressed again to
+    /// go through with it.
+    pub fn is_delete_armed(&self) -> bool {
+        self.delete_armed.is_some_and(|(index, armed)| {
+            index == self.index && armed.elapsed() < DELETE_CONFIRM_TIME
+        })
+    }
+
+    /// Calls off deleting the current preset, & says whether it was about to be.
+    pub fn disarm_delete(&mut self) -> bool {
+        self.delete_armed.take().is_some()
+    }
+
+    /// Calls off deleting the current preset if F9 wasn't pressed again in time, & says whether
+    /// it was.
+    pub fn check_delete_timeout(&mut self) -> bool {
+        self.delete_armed.is_some() && !self.is_delete_armed() && self.disarm_delete()
+    }
+
     /// Handles all the keypresses that have to do with manipulating setting presets.
     /// Returns true if the key was handled.
     pub fn handle_keypress(&mut self, key: KeyCode) -> bool {
+        self.ensure_valid_index();
         match key {
-            KeyCode::BracketLeft => {
-                // Go to previous preset
-                let next_index = if self.index == 0 {
-                    self.presets.len() - 1
-                } else {
-                    self.index - 1
-                };
-                self.set_index(next_index);
-            }
-            KeyCode::BracketRight => {
-                // Go to next preset
-                let next_index = if self.index == self.presets.len() - 1 {
-                    0
-                } else {
-                    self.index + 1
-                };
-                self.set_index(next_index);
+            KeyCode::Enter if self.pending => {
+                // Apply staged edits, leaving saving for the next press
+                self.apply();
             }
             KeyCode::Enter => {
                 // Save settings to current preset
                 self.presets[self.index] = self.settings.clone();
                 self.save_settings();
             }
+            KeyCode::F1 if self.max_presets != 0 && self.presets.len() >= self.max_presets => {
+                eprintln!(
+                    "Already at the most presets allowed ({}), not adding another",
+                    self.max_presets
+                );
+                eprintln!(
+                    "Delete one with F9 first, or raise `max_presets` in the settings file..."
+                );
+            }
             KeyCode::F1 => {
                 // Create new preset after the current one, duplicating the current settings
                 self.index += 1;
                 self.presets.insert(self.index, self.settings.clone());
+                self.apply();
                 self.save_settings();
             }
             KeyCode::F5 => {
                 // Reset current settings to default for the preset
                 self.settings = self.presets[self.index].clone();
                 self.dirty = false;
+                self.apply();
+            }
+            KeyCode::F9 if self.presets.len() > 1 && !self.is_delete_armed() => {
+                // There's no undoing a delete, so wait for another press to be sure
+                self.delete_armed = Some((self.index, Instant::now()));
+                println!("Press F9 again to delete preset {}", self.index + 1);
             }
             KeyCode::F9 if self.presets.len() > 1 => {
                 // Delete the current preset, if we can
+                self.delete_armed = None;
                 self.presets.remove(self.index);
                 self.index = std::cmp::min(self.index, self.presets.len() - 1);
                 self.set_index(self.index);
             }
             KeyCode::Slash => {
                 // Randomize current settings
-                self.settings = Settings::random();
-                self.dirty = true;
+                self.settings = Settings::random(&self.randomizer);
+                self.edited();
+            }
+            KeyCode::KeyN => {
+                // Add a sensor, wrapping back around to 1
+                let sensors = &mut self.settings.sensors;
+                sensors.count = sensors.count % constants::MAX_SENSORS + 1;
+                self.edited();
+            }
+            KeyCode::KeyM => {
+                self.settings.sensors.layout = self.settings.sensors.layout.next();
+                self.edited();
+            }
+            KeyCode::NumpadMultiply | KeyCode::NumpadDivide => {
+                // Blur further along one axis, wrapping back around to not blurring along it
+                let diffusion = &mut self.settings.diffusion;
+                let radius = if key == KeyCode::NumpadMultiply {
+                    &mut diffusion.radius_x
+                } else {
+                    &mut diffusion.radius_y
+                };
+                *radius = (*radius + 1) % (constants::MAX_DIFFUSION_RADIUS + 1);
+                self.edited();
+            }
+            KeyCode::Equal => {
+                // Route the music to the parameters in one of the ready-made ways, going through
+                // them in turn
+                let (name, routing) = constants::FFT_ROUTINGS[self.next_routing];
+                println!("Routing the music so the {name}");
+                self.settings.fft = routing.map(|settings| DisplaySettings {
+                    current: settings.into(),
+                    increment: constants::DEFAULT_INCREMENT_SETTINGS.into(),
+                });
+                self.next_routing = (self.next_routing + 1) % constants::FFT_ROUTINGS.len();
+                self.edited();
+            }
+            KeyCode::NumpadAdd => {
+                self.settings.fft_mix = self.settings.fft_mix.next();
+                self.edited();
+            }
+            KeyCode::Insert => {
+                self.settings.trail_colors = self.settings.trail_colors.next();
+                self.edited();
+            }
+            KeyCode::KeyJ | KeyCode::KeyL => {
+                let step = if key == KeyCode::KeyJ {
+                    constants::MOVE_SPEED_STEP.recip()
+                } else {
+                    constants::MOVE_SPEED_STEP
+                };
+                let (min, max) = constants::MOVE_SPEED_RANGE;
+                self.settings.move_speed = (self.settings.move_speed * step).clamp(min, max);
+                self.edited();
             }
             _ => return false,
         };
@@ -187,27 +624,83 @@ impl AllSettings {
         }
     }
 
+    /// Swaps the current preset with the one before or after it, keeping it selected, & saves the
+    /// new order. Unlike going through the presets, this stops at either end instead of wrapping.
+    pub fn move_preset(&mut self, later: bool) {
+        self.ensure_valid_index();
+        let other = if later {
+            Some(self.index + 1).filter(|&other| other < self.presets.len())
+        } else {
+            self.index.checked_sub(1)
+        };
+        let Some(other) = other else {
+            return;
+        };
+        self.presets.swap(self.index, other);
+        self.index = other;
+        // Only the order changed, so any edits to the current settings are still unsaved
+        if let Err(e) = self.write() {
+            eprintln!("Error saving file: {e}");
+        }
+    }
+
+    /// Goes to the previous preset, wrapping around to the end.
+    pub fn previous_preset(&mut self) {
+        self.ensure_valid_index();
+        let next_index = if self.index == 0 {
+            self.presets.len() - 1
+        } else {
+            self.index - 1
+        };
+        self.set_index(next_index);
+    }
+
+    /// Goes to the next preset, wrapping around to the start.
+    pub fn next_preset(&mut self) {
+        self.ensure_valid_index();
+        let next_index = if self.index == self.presets.len() - 1 {
+            0
+        } else {
+            self.index + 1
+        };
+        self.set_index(next_index);
+    }
+
     pub fn set_index(&mut self, index: usize) {
+        self.ensure_valid_index();
         if index >= self.presets.len() {
             return;
         }
         self.index = index;
         self.settings = self.presets[self.index].clone();
         self.dirty = false;
+        self.apply();
     }
 
     pub fn handle_base_keypress(&mut self, param: Param, key: KeyCode) -> bool {
         let out = param.apply(&mut self.settings.base, key);
         if out {
-            self.dirty = true;
+            self.edited();
         }
         out
     }
 
+    /// Sets a base parameter outright, rather than nudging it.
+    pub fn set_base(&mut self, param: Param, value: f32) {
+        param.set(&mut self.settings.base.current, value);
+        self.edited();
+    }
+
+    /// Flips which way an FFT bin pushes all the parameters.
+    pub fn flip_bin_sign(&mut self, index: BinIndex) {
+        self.settings.fft[index.0].flip_sign();
+        self.edited();
+    }
+
     pub fn handle_fft_keypress(&mut self, param: Param, index: BinIndex, key: KeyCode) -> bool {
         let out = param.apply(&mut self.settings.fft[index.0], key);
         if out {
-            self.dirty = true;
+            self.edited();
         }
         out
     }
@@ -11,10 +11,17 @@ macro_rules! point_settings {
         )* }
 
         impl $name {
-            pub fn random_base() -> Self {
+            /// Each setting's name in the shader, alongside its value.
+            fn shader_fields(&self) -> Vec<(&'static str, f32)> {
+                vec![$(
+                    (stringify!($from), self.$to),
+                )*]
+            }
+
+            pub fn random_base(randomizer: &crate::fs::settings::Randomizer) -> Self {
                 let mut rng = rand::rng();
                 Self { $(
-                    $to: crate::fs::settings::sample_base_setting(&mut rng),
+                    $to: crate::fs::settings::sample_base_setting(&mut rng, randomizer),
                 )* }
             }
         }
@@ -44,6 +51,24 @@ macro_rules! point_settings {
             }
         }
 
+        impl std::ops::Neg for $name {
+            type Output = $name;
+            fn neg(self) -> Self::Output {
+                Self::Output { $(
+                    $to: -self.$to,
+                )* }
+            }
+        }
+
+        impl std::ops::Mul<$name> for $name {
+            type Output = $name;
+            fn mul(self, rhs: $name) -> Self::Output {
+                Self::Output { $(
+                    $to: self.$to * rhs.$to,
+                )* }
+            }
+        }
+
         impl std::ops::Mul<f32> for $name {
             type Output = $name;
             fn mul(self, rhs: f32) -> Self::Output {
@@ -58,19 +83,47 @@ macro_rules! point_settings {
 point_settings! {
     pub struct PointSettings {
         sd_base                -> sd0,
-        sd_amplitude           -> sda,
         sd_exponent            -> sde,
+        sd_amplitude           -> sda,
         sa_base                -> sa0,
-        sa_amplitude           -> saa,
         sa_exponent            -> sae,
+        sa_amplitude           -> saa,
         ra_base                -> ra0,
-        ra_amplitude           -> raa,
         ra_exponent            -> rae,
+        ra_amplitude           -> raa,
         md_base                -> md0,
-        md_amplitude           -> mda,
         md_exponent            -> mde,
-        default_scaling_factor -> dsf,
+        md_amplitude           -> mda,
         sensor_bias_1          -> sb1,
         sensor_bias_2          -> sb2,
+        default_scaling_factor -> dsf,
+    }
+}
+
+impl PointSettings {
+    /// Formats these settings as a `PointSettings` literal, laid out the same way as the ones in
+    /// `DEFAULT_POINT_SETTINGS`, for pasting into `constants.rs`.
+    pub fn to_rust_literal(&self) -> String {
+        /// Rounds to this many significant figures, but always with something after the decimal
+        /// point so it stays an `f32` literal.
+        fn literal(value: f32, figures: usize) -> String {
+            // Avoids printing -0.000
+            let value = if value == 0.0 { 0.0 } else { value };
+            let whole_digits = (value.abs().log10().floor() as i32 + 1).max(1) as usize;
+            let decimals = figures.saturating_sub(whole_digits).max(1);
+            format!("{value:.decimals$}")
+        }
+        let mut out = String::from("    PointSettings {\n");
+        for (name, value) in self.shader_fields() {
+            // The scaling factors are all whole-ish numbers, so get one figure fewer
+            let figures = if name == "default_scaling_factor" {
+                3
+            } else {
+                4
+            };
+            out += &format!("        {name}: {},\n", literal(value, figures));
+        }
+        out += "    },";
+        out
     }
 }
@@ -15,6 +15,14 @@ pub struct DisplaySettings {
     pub increment: PointSettings,
 }
 
+impl DisplaySettings {
+    /// Makes every setting push the opposite way it did before. For an FFT bin, this means a
+    /// louder band takes away from the base settings instead of adding to them.
+    pub fn flip_sign(&mut self) {
+        self.current = -self.current.clone();
+    }
+}
+
 /// These are the overall settings used to calculate the exact `PointSettings` fed into the
 /// simulation in a given tick.
 #[derive(Debug, Clone, facet::Facet)]
@@ -23,42 +31,343 @@ pub struct Settings {
     pub base: DisplaySettings,
     /// How much to add to each base point, scaled by the amount in each FFT bin.
     pub fft: [DisplaySettings; NUM_BINS],
+    /// Linear RGB color the trail gets multiplied by when rendered.
+    #[facet(default = constants::DEFAULT_TINT)]
+    pub tint: [f32; 3],
+    /// Linear RGBA color that shows through wherever the trail is dark, and behind the header.
+    /// The alpha is how strongly it shows through.
+    #[facet(default = constants::DEFAULT_BACKGROUND)]
+    pub background: [f32; 4],
+    /// How many times each of the simulation's passes gets run.
+    #[facet(default)]
+    pub steps: Steps,
+    /// Where each particle senses the trail around it.
+    #[facet(default)]
+    pub sensors: Sensors,
+    /// How far the trail blurs along each axis.
+    #[facet(default)]
+    pub diffusion: Diffusion,
+    /// How far particles move each step, relative to what the point settings say. Kept within
+    /// `constants::MOVE_SPEED_RANGE`.
+    #[facet(default = 1.0)]
+    pub move_speed: f32,
+    /// How strongly particles turn towards the trail they sense, relative to what the point
+    /// settings say. Kept within `constants::TURN_STRENGTH_RANGE`.
+    #[facet(default = 1.0)]
+    pub turn_strength: f32,
+    /// How particles decide which color of trail to leave behind.
+    #[facet(default)]
+    pub trail_colors: TrailColors,
+    /// How the FFT bins' settings get combined with the base settings.
+    #[facet(default)]
+    pub fft_mix: FftMix,
+    /// How much all the FFT bins' settings get scaled by before being combined, like a master
+    /// fader for the music. Kept within `constants::REACTIVITY_RANGE`.
+    #[facet(default = 1.0)]
+    pub reactivity: f32,
+    /// How quickly each FFT bin follows the music, in the same order as `fft`.
+    #[facet(default)]
+    pub smoothing: [BinSmoothing; NUM_BINS],
+    /// How much faint parts of the trail get brightened when rendered, without clipping the
+    /// brightest. Kept within `constants::EXPOSURE_RANGE`.
+    #[facet(default = 1.0)]
+    pub exposure: f32,
+    /// The gamma the trail gets rendered with, after exposure. Kept within
+    /// `constants::GAMMA_RANGE`.
+    #[facet(default = 1.0)]
+    pub gamma: f32,
+    /// How much the simulation leans with the music's stereo balance, from 0 (not at all) to 1
+    /// (particles on the quiet side stop moving & the loud side move twice as fast when the music
+    /// is all on one side).
+    #[facet(default)]
+    pub stereo_lean: f32,
+    /// How much trail each particle lays down where it is every step. Higher makes denser,
+    /// brighter networks, & 0 leaves no trail. Kept within `constants::DEPOSIT_FACTOR_RANGE`.
+    #[facet(default = constants::CONSTANTS.deposit_factor)]
+    pub deposit_factor: f32,
+    /// How strongly particles steer away from pixels other particles are crowding onto, which
+    /// spreads dense blobs out into more even networks. 0 doesn't at all. Kept within
+    /// `constants::REPULSION_RANGE`.
+    #[facet(default)]
+    pub repulsion: f32,
+}
+
+/// How quickly an FFT bin follows the music, so e.g. the bass can be snappy while the highs drift.
+/// Both times are how long it takes to get most of the way (about 63%) to a new level, in
+/// milliseconds. 0 follows straight away, which is the default.
+#[derive(Debug, Copy, Clone, Default, facet::Facet)]
+pub struct BinSmoothing {
+    /// For when the band gets louder.
+    pub attack_ms: f32,
+    /// For when the band gets quieter.
+    pub release_ms: f32,
+}
+
+/// How many times each of the simulation's passes gets run.
+#[derive(Debug, Clone, facet::Facet)]
+pub struct Steps {
+    /// Only deposit trail once every this many frames. 1 deposits every frame.
+    pub deposit_interval: u32,
+    /// How many times to diffuse the trail every frame. Higher numbers blur more. Capped at
+    /// `constants::MAX_DIFFUSION_PASSES`.
+    pub diffusion_passes: u32,
+}
+
+impl Default for Steps {
+    fn default() -> Self {
+        Self {
+            deposit_interval: 1,
+            diffusion_passes: 1,
+        }
+    }
+}
+
+/// How far the trail blurs along each axis every diffusion pass.
+#[derive(Debug, Clone, facet::Facet)]
+pub struct Diffusion {
+    /// How many pixels to either side get blurred together horizontally. Capped at
+    /// `constants::MAX_DIFFUSION_RADIUS`, and 0 doesn't blur horizontally at all.
+    pub radius_x: u32,
+    /// The same, vertically.
+    pub radius_y: u32,
+}
+
+impl Default for Diffusion {
+    fn default() -> Self {
+        Self {
+            radius_x: 1,
+            radius_y: 1,
+        }
+    }
+}
+
+/// Where each particle senses the trail around it.
+#[derive(Debug, Clone, facet::Facet)]
+pub struct Sensors {
+    /// How many sensors each particle has. Capped at `constants::MAX_SENSORS`.
+    pub count: u32,
+    pub layout: SensorLayout,
+}
+
+/// How a particle's sensors are spread out around the way it's heading.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, facet::Facet)]
+#[repr(u8)]
+pub enum SensorLayout {
+    /// Spread evenly from one side of the sensor angle to the other, with the middle sensor (if
+    /// there is one) straight ahead. 3 sensors like this is the classic physarum model.
+    Fan,
+    /// Spread evenly all the way around, starting straight ahead. Ignores the sensor angle.
+    Ring,
+}
+
+/// How the FFT bins' settings get combined with the base settings, once each is scaled by how loud
+/// its bin is.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, facet::Facet)]
+#[repr(u8)]
+pub enum FftMix {
+    /// Bins get added on top of the base settings.
+    #[default]
+    Add,
+    /// Bins scale the base settings, e.g. 0.5 makes a setting half again as big. Keeps the
+    /// character of the base preset, since settings that are 0 stay 0.
+    Multiply,
+}
+
+impl FftMix {
+    pub fn next(self) -> Self {
+        match self {
+            Self::Add => Self::Multiply,
+            Self::Multiply => Self::Add,
+        }
+    }
+}
+
+/// How the simulation is fitted into the window, when they're different shapes.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, facet::Facet)]
+#[repr(u8)]
+pub enum Framing {
+    /// Fill the whole window, cropping off the edges of the simulation that don't fit.
+    #[default]
+    Cover,
+    /// Show the whole simulation, with bars either side where it doesn't fill the window.
+    Fit,
+}
+
+impl Framing {
+    pub fn next(self) -> Self {
+        match self {
+            Self::Cover => Self::Fit,
+            Self::Fit => Self::Cover,
+        }
+    }
+}
+
+/// How particles decide which color of trail to leave behind. Each particle deposits into one of
+/// the red, green, or blue channels, so colors show where different particles have been.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, facet::Facet)]
+#[repr(u8)]
+pub enum TrailColors {
+    /// Every particle leaves the same trail, which gets tinted.
+    #[default]
+    Mono,
+    /// By which way the particle is heading, split into thirds.
+    Heading,
+    /// By which particle it is, so a third of them always leave each color.
+    Particle,
+    /// By which way the particle is heading, smoothly around the color wheel. Unlike the others,
+    /// each particle's deposit is split between channels.
+    Hue,
+}
+
+impl TrailColors {
+    pub fn next(self) -> Self {
+        match self {
+            Self::Mono => Self::Heading,
+            Self::Heading => Self::Particle,
+            Self::Particle => Self::Hue,
+            Self::Hue => Self::Mono,
+        }
+    }
+
+    /// How the rule is told apart in the shader.
+    pub fn index(self) -> u32 {
+        match self {
+            Self::Mono => 0,
+            Self::Heading => 1,
+            Self::Particle => 2,
+            Self::Hue => 3,
+        }
+    }
+}
+
+impl Default for Sensors {
+    fn default() -> Self {
+        Self {
+            count: 3,
+            layout: SensorLayout::Fan,
+        }
+    }
+}
+
+impl SensorLayout {
+    pub fn next(self) -> Self {
+        match self {
+            Self::Fan => Self::Ring,
+            Self::Ring => Self::Fan,
+        }
+    }
+
+    /// How the layout is told apart in the shader.
+    pub fn index(self) -> u32 {
+        match self {
+            Self::Fan => 0,
+            Self::Ring => 1,
+        }
+    }
 }
 
 /// Creates an entirely random set of settings. Based on my own work.
 impl Settings {
-    pub fn random() -> Self {
+    pub fn random(randomizer: &Randomizer) -> Self {
         Self {
             base: DisplaySettings {
-                current: PointSettings::random_base(),
+                current: PointSettings::random_base(randomizer),
                 increment: constants::DEFAULT_INCREMENT_SETTINGS.into(),
             },
             fft: std::array::repeat(DisplaySettings {
                 current: compute_shader::PointSettings::zeroed().into(),
                 increment: constants::DEFAULT_INCREMENT_SETTINGS.into(),
             }),
+            tint: constants::DEFAULT_TINT,
+            background: constants::DEFAULT_BACKGROUND,
+            steps: Steps::default(),
+            sensors: Sensors::default(),
+            diffusion: Diffusion::default(),
+            move_speed: 1.0,
+            turn_strength: 1.0,
+            trail_colors: TrailColors::default(),
+            fft_mix: FftMix::default(),
+            reactivity: 1.0,
+            smoothing: Default::default(),
+            exposure: 1.0,
+            gamma: 1.0,
+            stereo_lean: 0.0,
+            deposit_factor: constants::CONSTANTS.deposit_factor,
+            repulsion: 0.0,
         }
     }
 }
 
-/// Uses a custom probability CDF to get a point. Tuned for "pretty good" results, often requires
-/// putting paramters into out-of-range places.
-pub(super) fn sample_base_setting(rng: &mut impl rand::Rng) -> f32 {
-    let decision: f32 = rng.random_range(0.0..1.0);
-    if decision < 0.3 {
-        0.0
-    } else if decision < 0.93 {
-        // exponential distribution with λ = 1
-        // CDF = λe^-λx => inverse CDF = -ln(c/λ)/λ
+/// How randomizing picks each parameter: it's either 0, positive, or negative, with the positive &
+/// negative values each following an exponential distribution pushed a little away from 0.
+#[derive(Debug, Clone, facet::Facet)]
+pub struct Randomizer {
+    /// How likely a parameter is to be exactly 0, turning it off.
+    #[facet(default = 0.3)]
+    pub zero_chance: f32,
+    /// How likely a parameter is to be negative. Anything not 0 or negative is positive.
+    #[facet(default = 0.07)]
+    pub negative_chance: f32,
+    /// How quickly positive values get less likely as they get bigger. They average out to
+    /// (1 + ln λ)/λ, so higher numbers give calmer results. At least 1, so they stay positive.
+    #[facet(default = 1.0)]
+    pub positive_lambda: f32,
+    /// The same, for negative values.
+    #[facet(default = 2.0)]
+    pub negative_lambda: f32,
+}
+
+impl Default for Randomizer {
+    fn default() -> Self {
+        Self {
+            zero_chance: 0.3,
+            negative_chance: 0.07,
+            positive_lambda: 1.0,
+            negative_lambda: 2.0,
+        }
+    }
+}
+
+impl Randomizer {
+    /// Puts back the usual distribution if this one can't be sampled from.
+    pub fn validated(self) -> Self {
+        let chances = [self.zero_chance, self.negative_chance];
+        let lambdas = [self.positive_lambda, self.negative_lambda];
+        if chances.iter().all(|chance| (0.0..=1.0).contains(chance))
+            && chances.iter().sum::<f32>() <= 1.0
+            && lambdas
+                .iter()
+                .all(|lambda| lambda.is_finite() && *lambda >= 1.0)
+        {
+            self
+        } else {
+            eprintln!(
+                "Randomizer chances need to be between 0 & 1 without adding up to more than 1, & its lambdas at least 1"
+            );
+            eprintln!("Falling back to the default randomizer...");
+            Self::default()
+        }
+    }
+}
+
+/// Uses a custom probability CDF to get a point, shaped by `randomizer`. Tuned for "pretty good"
+/// results by default, often requires putting parameters into out-of-range places.
+pub(super) fn sample_base_setting(rng: &mut impl rand::Rng, randomizer: &Randomizer) -> f32 {
+    // Exponential-ish distribution: inverse CDF = -ln(c/λ)/λ, which is the usual -ln(c)/λ pushed
+    // ln(λ)/λ further from 0.
+    fn sample(rng: &mut impl rand::Rng, lambda: f32) -> f32 {
         // sample a value in range (0, 1], so we don't have to worry about log(0)
-        const LAMBDA: f32 = 1.0;
         let cdf: f32 = -rng.random_range(-1.0..0.0);
-        -f32::ln(cdf / LAMBDA) / LAMBDA
+        -f32::ln(cdf / lambda) / lambda
+    }
+
+    let decision: f32 = rng.random_range(0.0..1.0);
+    if decision < randomizer.zero_chance {
+        0.0
+    } else if decision < 1.0 - randomizer.negative_chance {
+        sample(rng, randomizer.positive_lambda)
     } else {
-        // exponential distrubiton with λ = 2, for the negative numbers
-        const LAMBDA: f32 = 2.0;
-        let cdf: f32 = -rng.random_range(-1.0..0.0);
-        f32::ln(cdf / LAMBDA) / LAMBDA
+        -sample(rng, randomizer.negative_lambda)
     }
 }
 
@@ -96,6 +405,20 @@ macro_rules! param_enum {
                 )* }
             }
 
+            /// The value of this parameter in some settings.
+            pub fn get(&self, settings: &PointSettings) -> f32 {
+                match self { $(
+                    $name::$case => settings.$param,
+                )* }
+            }
+
+            /// Sets this parameter in some settings.
+            pub fn set(&self, settings: &mut PointSettings, value: f32) {
+                match self { $(
+                    $name::$case => settings.$param = value,
+                )* }
+            }
+
             pub fn activate(key: KeyCode) -> Option<Self> {
                 match key { $(
                     KeyCode::$key => Some($name::$case),
@@ -103,6 +426,15 @@ macro_rules! param_enum {
                     _ => None
                 }
             }
+
+            /// Looks up a parameter by the name it has in the settings file, like `sd0`.
+            pub fn from_name(name: &str) -> Option<Self> {
+                match name { $(
+                    stringify!($param) => Some($name::$case),
+                )*
+                    _ => None
+                }
+            }
         }
     }
 }
@@ -159,3 +491,61 @@ bin_indices!(
         4 = KeyP,
     }
 );
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+
+    /// Samples a lot of values, giving back how many were 0 & negative, & the averages of the
+    /// positive & negative ones.
+    fn summarize(randomizer: &Randomizer) -> (f32, f32, f32, f32) {
+        const SAMPLES: usize = 100_000;
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+        let (mut zeros, mut positives, mut negatives) = (0, Vec::new(), Vec::new());
+        for _ in 0..SAMPLES {
+            let value = sample_base_setting(&mut rng, randomizer);
+            if value == 0.0 {
+                zeros += 1;
+            } else if value > 0.0 {
+                positives.push(value);
+            } else {
+                negatives.push(value);
+            }
+        }
+        let mean = |values: &[f32]| values.iter().sum::<f32>() / values.len() as f32;
+        (
+            zeros as f32 / SAMPLES as f32,
+            negatives.len() as f32 / SAMPLES as f32,
+            mean(&positives),
+            mean(&negatives),
+        )
+    }
+
+    #[test]
+    fn default_randomizer_keeps_the_original_distribution() {
+        let (zeros, negatives, positive_mean, negative_mean) = summarize(&Randomizer::default());
+        assert!((zeros - 0.3).abs() < 0.01, "{zeros}");
+        assert!((negatives - 0.07).abs() < 0.01, "{negatives}");
+        assert!((positive_mean - 1.0).abs() < 0.02, "{positive_mean}");
+        // -(1 + ln 2)/2
+        assert!((negative_mean + 0.8466).abs() < 0.02, "{negative_mean}");
+    }
+
+    #[test]
+    fn randomizer_follows_its_settings() {
+        let randomizer = Randomizer {
+            zero_chance: 0.5,
+            negative_chance: 0.2,
+            positive_lambda: 4.0,
+            negative_lambda: 1.0,
+        };
+        let (zeros, negatives, positive_mean, negative_mean) = summarize(&randomizer);
+        assert!((zeros - 0.5).abs() < 0.01, "{zeros}");
+        assert!((negatives - 0.2).abs() < 0.01, "{negatives}");
+        // (1 + ln 4)/4
+        assert!((positive_mean - 0.5966).abs() < 0.02, "{positive_mean}");
+        assert!((negative_mean + 1.0).abs() < 0.02, "{negative_mean}");
+    }
+}
@@ -0,0 +1,193 @@
+//! Records a few seconds of the screen as an animated GIF, for sharing short loops. Frames are
+//! read back at a reduced rate & size while recording, then encoded on their own thread once it
+//! stops.
+//!
+//! Colors are matched to a fixed palette with ordered dithering rather than one picked for each
+//! clip, which is quick & simple at the cost of a little grain.
+
+use std::io::Write;
+use std::path::Path;
+
+use web_time::{Duration, Instant};
+
+use crate::graphics::Image;
+use crate::platform::{Current, Platform};
+
+/// How long a recording lasts, unless it's stopped early.
+pub const DURATION: Duration = Duration::from_secs(5);
+/// How often frames get read back while recording, about 15 a second. Each one waits on the GPU,
+/// so this is well below the display's frame rate.
+const FRAME_INTERVAL: Duration = Duration::from_millis(66);
+/// Frames get scaled down by a whole number until they're at most this wide.
+const MAX_WIDTH: u32 = 480;
+/// How many levels of red, green, & blue are in the palette, for 252 colors. Green gets an extra
+/// one since that's what eyes are most sensitive to.
+const LEVELS: [u32; 3] = [6, 7, 6];
+/// 4x4 Bayer matrix, for spreading out the error from matching colors to the palette.
+const DITHER: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+pub struct Recording {
+    started: Instant,
+    frames: Vec<Frame>,
+}
+
+struct Frame {
+    width: u32,
+    height: u32,
+    /// 8-bit RGBA, already scaled down.
+    rgba: Vec<u8>,
+    taken: Instant,
+}
+
+impl Recording {
+    pub fn start(now: Instant) -> Self {
+        Self {
+            started: now,
+            frames: Vec::new(),
+        }
+    }
+
+    /// Whether it's time to read back another frame.
+    pub fn wants_frame(&self, now: Instant) -> bool {
+        self.frames
+            .last()
+            .is_none_or(|frame| now.duration_since(frame.taken) >= FRAME_INTERVAL)
+    }
+
+    /// Whether the recording has gone on for as long as it should.
+    pub fn is_finished(&self, now: Instant) -> bool {
+        now.duration_since(self.started) >= DURATION
+    }
+
+    /// Scales down a frame & keeps it. Frames that come out a different size to the first one,
+    /// e.g. from the window being resized, are left out since a GIF is all one size.
+    pub fn add_frame(&mut self, image: &Image, now: Instant) {
+        let frame = downscale(image, now);
+        if let Some(first) = self.frames.first()
+            && (first.width, first.height) != (frame.width, frame.height)
+        {
+            return;
+        }
+        self.frames.push(frame);
+    }
+
+    /// Encodes the recording into the current directory on its own thread, so the event loop
+    /// isn't held up by it.
+    pub fn save(self) {
+        if self.frames.is_empty() {
+            eprintln!("No frames were recorded, not saving a GIF");
+            return;
+        }
+        let path = crate::screenshot::timestamped_path("gif");
+        Current::spawn(move || match write_gif(&path, &self.frames) {
+            Ok(()) => println!("Saved GIF to {}", path.display()),
+            Err(e) => eprintln!("Error saving GIF to {}: {e}", path.display()),
+        });
+    }
+}
+
+/// Averages blocks of pixels together, so the frame is at most `MAX_WIDTH` wide.
+fn downscale(image: &Image, taken: Instant) -> Frame {
+    let factor = image.width.div_ceil(MAX_WIDTH).max(1);
+    let width = (image.width / factor).max(1);
+    let height = (image.height / factor).max(1);
+    let mut rgba = Vec::with_capacity((width * height * 4) as usize);
+    for y in 0..height {
+        for x in 0..width {
+            let mut sum = [0u32; 4];
+            let mut count = 0;
+            for source_y in (y * factor..(y + 1) * factor).take_while(|&y| y < image.height) {
+                for source_x in (x * factor..(x + 1) * factor).take_while(|&x| x < image.width) {
+                    let offset = ((source_y * image.width + source_x) * 4) as usize;
+                    for (total, channel) in sum.iter_mut().zip(&image.rgba[offset..offset + 4]) {
+                        *total += u32::from(*channel);
+                    }
+                    count += 1;
+                }
+            }
+            rgba.extend(sum.map(|total| (total / count.max(1)) as u8));
+        }
+    }
+    Frame {
+        width,
+        height,
+        rgba,
+        taken,
+    }
+}
+
+fn write_gif(path: &Path, frames: &[Frame]) -> std::io::Result<()> {
+    let file = std::io::BufWriter::new(Current::create_file(path)?);
+    let (width, height) = (frames[0].width as u16, frames[0].height as u16);
+    let mut encoder =
+        ::gif::Encoder::new(file, width, height, &palette()).map_err(std::io::Error::other)?;
+    encoder
+        .set_repeat(::gif::Repeat::Infinite)
+        .map_err(std::io::Error::other)?;
+
+    let start = frames[0].taken;
+    // Delays are in hundredths of a second. Working them out from the total time so far keeps
+    // rounding from adding up over the clip.
+    let centiseconds = |taken: Instant| (taken.duration_since(start).as_millis() / 10) as u64;
+    for (index, frame) in frames.iter().enumerate() {
+        let next = frames
+            .get(index + 1)
+            .map_or(frame.taken + FRAME_INTERVAL, |next| next.taken);
+        // Most viewers treat anything less than 2 as much slower, confusingly
+        let delay = (centiseconds(next) - centiseconds(frame.taken)).clamp(2, u64::from(u16::MAX));
+
+        let mut gif_frame = ::gif::Frame::from_indexed_pixels(width, height, quantize(frame), None);
+        gif_frame.delay = delay as u16;
+        gif_frame.dispose = ::gif::DisposalMethod::Keep;
+        encoder
+            .write_frame(&gif_frame)
+            .map_err(std::io::Error::other)?;
+    }
+
+    encoder.into_inner().map_err(std::io::Error::other)?.flush()
+}
+
+/// An evenly spaced cube of colors, padded out to 256 with black.
+fn palette() -> Vec<u8> {
+    let [reds, greens, blues] = LEVELS;
+    let level = |value: u32, levels: u32| (value * 255 / (levels - 1)) as u8;
+    let mut palette = Vec::with_capacity(256 * 3);
+    for r in 0..reds {
+        for g in 0..greens {
+            for b in 0..blues {
+                palette.extend([level(r, reds), level(g, greens), level(b, blues)]);
+            }
+        }
+    }
+    palette.resize(256 * 3, 0);
+    palette
+}
+
+/// Matches each pixel to the closest color in the palette, dithered so smooth gradients don't
+/// turn into bands.
+fn quantize(frame: &Frame) -> Vec<u8> {
+    let [reds, greens, blues] = LEVELS;
+    frame
+        .rgba
+        .as_chunks::<4>()
+        .0
+        .iter()
+        .enumerate()
+        .map(|(index, pixel)| {
+            let (x, y) = (index % frame.width as usize, index / frame.width as usize);
+            let threshold = (f32::from(DITHER[y % 4][x % 4]) + 0.5) / 16.0 - 0.5;
+            let level = |channel: u8, levels: u32| {
+                let max = (levels - 1) as f32;
+                (f32::from(channel) / 255.0 * max + threshold)
+                    .round()
+                    .clamp(0.0, max) as u32
+            };
+            let (r, g, b) = (
+                level(pixel[0], reds),
+                level(pixel[1], greens),
+                level(pixel[2], blues),
+            );
+            ((r * greens + g) * blues + b) as u8
+        })
+        .collect()
+}
@@ -0,0 +1,63 @@
+//! Spots beats in the music from sudden jumps in the FFT bins, so they can set off bursts of fresh
+//! particles.
+//!
+//! How much the bins jumped by (the spectral flux) is compared to how much they've been jumping
+//! recently, so this works the same whether the music is loud or quiet.
+
+use web_time::{Duration, Instant};
+
+use crate::audio::NUM_BINS;
+
+/// How far back the recent average of the flux reaches.
+const AVERAGE_TIME: Duration = Duration::from_secs(1);
+/// How many times the recent average the flux needs to be to count as a beat. Most frames the
+/// bins barely move, which keeps the average well below the flux of any beat.
+const THRESHOLD: f32 = 3.0;
+/// How long the strongest recent beat takes to be forgotten, which others are measured against.
+const PEAK_TIME: Duration = Duration::from_secs(8);
+/// The shortest time between beats, so one hit doesn't count more than once.
+const MIN_INTERVAL: Duration = Duration::from_millis(150);
+
+#[derive(Default)]
+pub struct Beats {
+    previous: [f32; NUM_BINS],
+    /// The recent average of the flux.
+    average: f32,
+    /// The flux of the strongest recent beat, fading away over time.
+    peak: f32,
+    /// When the first bins came in. There's nothing to compare to until a while after this.
+    started: Option<Instant>,
+    last_update: Option<Instant>,
+    last_beat: Option<Instant>,
+}
+
+impl Beats {
+    /// Takes in the newest bins, & says how strong the beat is (between 0 & 1) if they're one.
+    pub fn update(&mut self, bins: &[f32; NUM_BINS], now: Instant) -> Option<f32> {
+        let flux: f32 = bins
+            .iter()
+            .zip(&self.previous)
+            .map(|(bin, previous)| (bin - previous).max(0.0))
+            .sum();
+        self.previous = *bins;
+        let ratio = flux / self.average.max(f32::EPSILON);
+
+        let elapsed = self.last_update.map_or(Duration::ZERO, |last| now - last);
+        self.last_update = Some(now);
+        let started = *self.started.get_or_insert(now);
+        let fade = |time: Duration| (-elapsed.as_secs_f32() / time.as_secs_f32()).exp();
+        self.average += (flux - self.average) * (1.0 - fade(AVERAGE_TIME));
+        self.peak *= fade(PEAK_TIME);
+
+        let ready = now.duration_since(started) >= AVERAGE_TIME
+            && self
+                .last_beat
+                .is_none_or(|last| now.duration_since(last) >= MIN_INTERVAL);
+        if flux <= 0.0 || ratio < THRESHOLD || !ready {
+            return None;
+        }
+        self.last_beat = Some(now);
+        self.peak = self.peak.max(flux);
+        Some(flux / self.peak)
+    }
+}
@@ -0,0 +1,151 @@
+//! Lets regions of the simulation be painted with the mouse, so they follow different settings
+//! from the rest of it. Painted regions use the settings that were captured with the brush, and
+//! blend into the surrounding settings around their edges.
+//!
+//! How much each part of the simulation is painted is kept in a coarse grid on the GPU, with one
+//! cell per `FIELD_CELL_SIZE` square of pixels. The move & deposit shaders look up their settings
+//! from it, and the brush shader paints into it.
+
+use crate::constants::*;
+use crate::shaders::compute_shader;
+use crate::shaders::compute_shader::{FIELD_CELL_SIZE, PointSettings};
+
+/// How far the brush reaches, in simulation pixels.
+const RADIUS: f32 = 48.0;
+/// How much gets painted in the middle of the brush each frame it's held down. Painting over the
+/// same spot for a few frames fully paints it.
+const STRENGTH: f32 = 0.15;
+
+const FIELD_WIDTH: u32 = SIMULATION_WIDTH.div_ceil(FIELD_CELL_SIZE);
+const FIELD_HEIGHT: u32 = SIMULATION_HEIGHT.div_ceil(FIELD_CELL_SIZE);
+
+/// What holding the mouse down does.
+#[derive(Copy, Clone)]
+pub enum Stroke {
+    /// Makes regions follow the brush's settings.
+    Paint,
+    /// Makes regions go back to the normal settings.
+    Erase,
+}
+
+/// The buffers the simulation's shaders read from.
+pub struct Buffers {
+    pub settings: wgpu::Buffer,
+    pub uniforms: wgpu::Buffer,
+    pub field: wgpu::Buffer,
+}
+
+pub struct Brush {
+    /// What the mouse is doing, if it's held down.
+    stroke: Option<Stroke>,
+    /// Where the brush is being applied this frame, in simulation pixels, if anywhere.
+    position: Option<glam::Vec2>,
+    /// Whether any settings have been captured for painting with yet.
+    has_settings: bool,
+
+    buffers: Buffers,
+    pipeline: wgpu::ComputePipeline,
+}
+
+impl Brush {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let buffer = |name: &str, size: u64, usage: wgpu::BufferUsages| {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(&format!("brush_{name}_buffer")),
+                size,
+                usage,
+                mapped_at_creation: false,
+            })
+        };
+        let settings = buffer(
+            "settings",
+            size_of::<PointSettings>() as u64,
+            wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        );
+        // Filled in during each set_settings()
+        let uniforms = buffer(
+            "uniforms",
+            size_of::<compute_shader::Brush>() as u64,
+            wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        );
+        // Filled in during each prepare()
+        let field = buffer(
+            "field",
+            u64::from(FIELD_WIDTH * FIELD_HEIGHT) * size_of::<f32>() as u64,
+            wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        );
+        // Starts out zeroed, so nothing is painted
+
+        Self {
+            stroke: None,
+            position: None,
+            has_settings: false,
+            buffers: Buffers {
+                settings,
+                uniforms,
+                field,
+            },
+            pipeline: compute_shader::compute::create_cs_brush_pipeline(device),
+        }
+    }
+
+    pub fn buffers(&self) -> &Buffers {
+        &self.buffers
+    }
+
+    pub fn has_settings(&self) -> bool {
+        self.has_settings
+    }
+
+    /// Sets the settings that painted regions follow. Regions that were already painted switch
+    /// over to them too.
+    pub fn set_settings(&mut self, queue: &wgpu::Queue, settings: &PointSettings) {
+        queue.write_buffer(&self.buffers.settings, 0, bytemuck::bytes_of(settings));
+        self.has_settings = true;
+    }
+
+    pub fn set_stroke(&mut self, stroke: Option<Stroke>) {
+        self.stroke = stroke;
+    }
+
+    /// Goes back to nothing being painted.
+    pub fn clear(&mut self, queue: &wgpu::Queue) {
+        let zeros = vec![0f32; (FIELD_WIDTH * FIELD_HEIGHT) as usize];
+        queue.write_buffer(
+            &self.buffers.field,
+            0,
+            bytemuck::cast_slice(zeros.as_slice()),
+        );
+    }
+
+    /// Gets the brush ready to be applied at `position` this frame, if the mouse is held down.
+    pub fn prepare(&mut self, queue: &wgpu::Queue, position: Option<glam::Vec2>) {
+        self.position = position.filter(|_| self.stroke.is_some());
+        let (Some(stroke), Some(center)) = (self.stroke, self.position) else {
+            return;
+        };
+        let uniforms = compute_shader::Brush {
+            center,
+            radius: RADIUS,
+            strength: match stroke {
+                Stroke::Paint => STRENGTH,
+                Stroke::Erase => -STRENGTH,
+            },
+        };
+        queue.write_buffer(&self.buffers.uniforms, 0, bytemuck::bytes_of(&uniforms));
+    }
+
+    /// Applies the brush, if it's being used. Expects the simulation's bind groups to already be
+    /// set.
+    pub fn compute_pass(&self, compute_pass: &mut wgpu::ComputePass) {
+        if self.position.is_none() {
+            return;
+        }
+        compute_pass.set_pipeline(&self.pipeline);
+        compute_pass.dispatch_workgroups(
+            FIELD_WIDTH.div_ceil(SIMULATION_WORK_GROUP_SIZE),
+            FIELD_HEIGHT.div_ceil(SIMULATION_WORK_GROUP_SIZE),
+            1,
+        );
+    }
+}
@@ -51,6 +51,11 @@ impl From<Uniforms> for rect_render_shader::Uniforms {
             offset,
             lower_bound,
             upper_bound,
+            // Set separately, if at all
+            quarter_turns: 0,
+            _padding: 0,
+            image_scale: glam::Vec2::ZERO,
+            image_offset: glam::Vec2::ZERO,
         }
     }
 }
@@ -74,6 +79,7 @@ pub struct SourceRect {
     pub height: f32,
 }
 
+#[derive(Clone, Copy)]
 pub struct DestinationRect {
     pub x: f32,
     pub y: f32,
@@ -81,6 +87,53 @@ pub struct DestinationRect {
     pub height: f32,
 }
 
+/// A shape for everything to be drawn in, e.g. 16x9, for when that matters more than filling the
+/// window.
+#[derive(Clone, Copy)]
+pub struct Aspect {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl std::str::FromStr for Aspect {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let expected = || format!("expected something like 1920x1080, got {s}");
+        let (width, height) = s.split_once(['x', 'X']).ok_or_else(expected)?;
+        match (width.trim().parse(), height.trim().parse()) {
+            (Ok(width), Ok(height)) if width > 0 && height > 0 => Ok(Self { width, height }),
+            _ => Err(expected()),
+        }
+    }
+}
+
+impl DestinationRect {
+    /// The area of the screen everything gets drawn in: the whole of it, or the biggest rectangle
+    /// of this aspect that fits in the middle, leaving bars either side. Rounded to whole pixels
+    /// so the edges of the bars are clean.
+    pub fn letterbox(size: PhysicalSize<u32>, aspect: Option<Aspect>) -> Self {
+        let (screen_width, screen_height) = (size.width as f32, size.height as f32);
+        let (width, height) = match aspect {
+            None => (screen_width, screen_height),
+            Some(aspect) => {
+                let ratio = aspect.width as f32 / aspect.height as f32;
+                if screen_width / screen_height.max(1.0) > ratio {
+                    ((screen_height * ratio).round(), screen_height)
+                } else {
+                    (screen_width, (screen_width / ratio).round())
+                }
+            }
+        };
+        Self {
+            x: ((screen_width - width) / 2.0).floor(),
+            y: ((screen_height - height) / 2.0).floor(),
+            width,
+            height,
+        }
+    }
+}
+
 impl Uniforms {
     /// Takes a "source" rectangle (just width/height) and returns a set of parameters that will
     /// blit it onto the screen at a "destination" rectangle (x/y/width/height).
@@ -103,6 +156,16 @@ impl Uniforms {
     ///     discard;
     /// }
     /// ```
+    ///
+    /// As a worked example, a 100x100 source drawn over the whole of a 200x100 screen gives:
+    /// * `Cover`: scale `(0.02, -0.04)`, offset `(-1, 2)`. The source is doubled in size to fill
+    ///   the screen's width, and the top & bottom quarters of it are cut off.
+    /// * `Fit`: scale `(0.01, -0.02)`, offset `(-0.5, 1)`. The source stays the same size, centered
+    ///   horizontally between x = 50 and x = 150.
+    ///
+    /// In both cases the bounds are the whole screen, `(0, 0)` to `(200, 100)`, and the y
+    /// components are negated because clip space points up while source pixels point down. Any
+    /// source or destination with no area gives all zeroes, which draws nothing.
     pub fn source_to_screen(
         screen: ScreenRect,
         source: SourceRect,
@@ -266,3 +329,160 @@ impl Uniforms {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn screen(width: f32, height: f32) -> ScreenRect {
+        ScreenRect { width, height }
+    }
+
+    fn source(width: f32, height: f32) -> SourceRect {
+        SourceRect { width, height }
+    }
+
+    fn destination(x: f32, y: f32, width: f32, height: f32) -> DestinationRect {
+        DestinationRect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    fn assert_uniforms(uniforms: Uniforms, scale: [f32; 2], offset: [f32; 2], bounds: [f32; 4]) {
+        let close = |a: glam::Vec2, b: [f32; 2]| a.abs_diff_eq(glam::Vec2::from(b), 1e-6);
+        assert!(close(uniforms.scale, scale), "{uniforms:?}");
+        assert!(close(uniforms.offset, offset), "{uniforms:?}");
+        assert!(
+            close(uniforms.lower_bound, [bounds[0], bounds[1]]),
+            "{uniforms:?}"
+        );
+        assert!(
+            close(uniforms.upper_bound, [bounds[2], bounds[3]]),
+            "{uniforms:?}"
+        );
+    }
+
+    /// Where a source pixel ends up on the screen, going through clip space like the GPU does.
+    fn to_screen(uniforms: &Uniforms, screen: &ScreenRect, pixel: glam::Vec2) -> glam::Vec2 {
+        let clip = pixel * uniforms.scale + uniforms.offset;
+        let size = glam::vec2(screen.width, screen.height);
+        (clip * glam::vec2(1.0, -1.0) + 1.0) * 0.5 * size
+    }
+
+    #[test]
+    fn cover_fills_the_destination() {
+        let uniforms = Uniforms::source_to_screen(
+            screen(200.0, 100.0),
+            source(100.0, 100.0),
+            destination(0.0, 0.0, 200.0, 100.0),
+            Mode::Cover,
+        );
+        assert_uniforms(
+            uniforms,
+            [0.02, -0.04],
+            [-1.0, 2.0],
+            [0.0, 0.0, 200.0, 100.0],
+        );
+    }
+
+    #[test]
+    fn fit_stays_inside_the_destination() {
+        let uniforms = Uniforms::source_to_screen(
+            screen(200.0, 100.0),
+            source(100.0, 100.0),
+            destination(0.0, 0.0, 200.0, 100.0),
+            Mode::Fit,
+        );
+        assert_uniforms(
+            uniforms,
+            [0.01, -0.02],
+            [-0.5, 1.0],
+            [0.0, 0.0, 200.0, 100.0],
+        );
+    }
+
+    #[test]
+    fn fit_and_cover_pick_opposite_axes() {
+        // Taller than the destination, so fit scales by height & cover by width
+        let fit = Uniforms::source_to_screen(
+            screen(100.0, 100.0),
+            source(50.0, 200.0),
+            destination(0.0, 0.0, 100.0, 100.0),
+            Mode::Fit,
+        );
+        let cover = Uniforms::source_to_screen(
+            screen(100.0, 100.0),
+            source(50.0, 200.0),
+            destination(0.0, 0.0, 100.0, 100.0),
+            Mode::Cover,
+        );
+        assert_uniforms(fit, [0.01, -0.01], [-0.25, 1.0], [0.0, 0.0, 100.0, 100.0]);
+        assert_uniforms(cover, [0.04, -0.04], [-1.0, 4.0], [0.0, 0.0, 100.0, 100.0]);
+    }
+
+    #[test]
+    fn top_left_stays_top_left() {
+        // The y-flip means the first row of the source is drawn at the top of the screen, not the
+        // bottom
+        let uniforms = Uniforms::source_to_screen(
+            screen(300.0, 200.0),
+            source(100.0, 50.0),
+            destination(50.0, 20.0, 200.0, 100.0),
+            Mode::Fit,
+        );
+        let screen = screen(300.0, 200.0);
+        let top_left = to_screen(&uniforms, &screen, glam::vec2(0.0, 0.0));
+        let bottom_right = to_screen(&uniforms, &screen, glam::vec2(100.0, 50.0));
+        assert!(
+            top_left.abs_diff_eq(glam::vec2(50.0, 20.0), 1e-4),
+            "{top_left}"
+        );
+        assert!(
+            bottom_right.abs_diff_eq(glam::vec2(250.0, 120.0), 1e-4),
+            "{bottom_right}"
+        );
+    }
+
+    #[test]
+    fn bounds_follow_the_destination() {
+        let uniforms = Uniforms::source_to_screen(
+            screen(1920.0, 1080.0),
+            source(640.0, 480.0),
+            destination(240.0, 60.0, 1440.0, 1020.0),
+            Mode::Cover,
+        );
+        assert_eq!(uniforms.lower_bound, glam::vec2(240.0, 60.0));
+        assert_eq!(uniforms.upper_bound, glam::vec2(1680.0, 1080.0));
+    }
+
+    #[test]
+    fn nothing_to_draw_gives_zeroes() {
+        let zeroed = |uniforms: Uniforms| {
+            uniforms.scale == glam::Vec2::ZERO
+                && uniforms.offset == glam::Vec2::ZERO
+                && uniforms.lower_bound == glam::Vec2::ZERO
+                && uniforms.upper_bound == glam::Vec2::ZERO
+        };
+        assert!(zeroed(Uniforms::source_to_screen(
+            screen(200.0, 100.0),
+            source(0.0, 100.0),
+            destination(0.0, 0.0, 200.0, 100.0),
+            Mode::Cover,
+        )));
+        assert!(zeroed(Uniforms::source_to_screen(
+            screen(200.0, 100.0),
+            source(100.0, 0.0),
+            destination(0.0, 0.0, 200.0, 100.0),
+            Mode::Fit,
+        )));
+        assert!(zeroed(Uniforms::source_to_screen(
+            screen(200.0, 100.0),
+            source(100.0, 100.0),
+            destination(0.0, 0.0, 0.0, 100.0),
+            Mode::Fit,
+        )));
+    }
+}
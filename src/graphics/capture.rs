@@ -0,0 +1,145 @@
+//! Reads finished frames back off the screen, so they can be saved. Reading back waits on the GPU,
+//! so this is only for the occasional frame rather than every one.
+
+/// A frame read back from the screen.
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    /// 8-bit RGBA, row by row from the top, with no padding between rows.
+    pub rgba: Vec<u8>,
+}
+
+/// Whether the channels of a surface format need swapping to get RGBA, or `None` if we don't know
+/// how to read it at all.
+fn swap_red_blue(format: wgpu::TextureFormat) -> Option<bool> {
+    match format.remove_srgb_suffix() {
+        wgpu::TextureFormat::Rgba8Unorm => Some(false),
+        wgpu::TextureFormat::Bgra8Unorm => Some(true),
+        _ => None,
+    }
+}
+
+pub struct Capture {
+    /// Whether the next frame should be read back.
+    requested: bool,
+    /// The copy of the current frame, once it's been recorded.
+    pending: Option<Pending>,
+}
+
+struct Pending {
+    buffer: wgpu::Buffer,
+    width: u32,
+    height: u32,
+    /// Bytes between rows in `buffer`, padded to what copies require.
+    row_stride: u32,
+    swap_red_blue: bool,
+}
+
+impl Capture {
+    pub fn new() -> Self {
+        Self {
+            requested: false,
+            pending: None,
+        }
+    }
+
+    /// Reads back the next frame that gets rendered.
+    pub fn request(&mut self) {
+        self.requested = true;
+    }
+
+    /// Records a copy of the finished frame, if one was requested.
+    pub fn copy_frame(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        texture: &wgpu::Texture,
+    ) {
+        if !std::mem::take(&mut self.requested) {
+            return;
+        }
+        if !texture.usage().contains(wgpu::TextureUsages::COPY_SRC) {
+            eprintln!("Can't read frames back from this display, so can't take a screenshot");
+            return;
+        }
+        let Some(swap_red_blue) = swap_red_blue(texture.format()) else {
+            eprintln!(
+                "Don't know how to read frames in {:?}, so can't take a screenshot",
+                texture.format()
+            );
+            return;
+        };
+
+        let size = texture.size();
+        let row_stride = (size.width * 4).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("capture_buffer"),
+            size: u64::from(row_stride * size.height),
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::TexelCopyBufferInfo {
+                buffer: &buffer,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(row_stride),
+                    rows_per_image: None,
+                },
+            },
+            size,
+        );
+        self.pending = Some(Pending {
+            buffer,
+            width: size.width,
+            height: size.height,
+            row_stride,
+            swap_red_blue,
+        });
+    }
+
+    /// Waits for the copied frame & returns it, if there is one. Call after submitting.
+    pub fn read(&mut self, device: &wgpu::Device) -> Option<Image> {
+        let pending = self.pending.take()?;
+        let slice = pending.buffer.slice(..);
+        let (tx, rx) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = tx.send(result);
+        });
+        if let Err(e) = device.poll(wgpu::PollType::Wait) {
+            eprintln!("Error waiting for screenshot: {e}");
+            return None;
+        }
+        match rx.recv() {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => {
+                eprintln!("Error reading screenshot: {e}");
+                return None;
+            }
+            Err(_) => {
+                eprintln!("Screenshot was never read back");
+                return None;
+            }
+        }
+
+        let mut rgba = Vec::with_capacity((pending.width * pending.height * 4) as usize);
+        {
+            let data = slice.get_mapped_range();
+            for row in data.chunks(pending.row_stride as usize) {
+                rgba.extend_from_slice(&row[..(pending.width * 4) as usize]);
+            }
+        }
+        pending.buffer.unmap();
+        if pending.swap_red_blue {
+            for pixel in rgba.as_chunks_mut::<4>().0 {
+                pixel.swap(0, 2);
+            }
+        }
+        Some(Image {
+            width: pending.width,
+            height: pending.height,
+            rgba,
+        })
+    }
+}
@@ -0,0 +1,239 @@
+//! This module lets previous frames linger on screen, fading out behind the current one. Unlike
+//! the trail decay, this works on the whole finished frame, including the text & visualizers.
+//!
+//! When it's on, each frame is drawn into a scene texture instead of straight onto the screen.
+//! The scene is then blended into an accumulated image, and that image gets copied to the screen.
+
+use winit::dpi::PhysicalSize;
+
+use crate::shaders::feedback_shader as shader;
+
+/// How much the feedback amount changes with each keypress.
+pub const FEEDBACK_STEP: f32 = 0.05;
+/// The highest feedback amount allowed. At 1, nothing new would ever show up.
+pub const MAX_FEEDBACK: f32 = 0.95;
+
+/// Kept in higher precision than the screen, so a slow fade can get all the way to black instead
+/// of getting stuck on a dim color.
+const ACCUMULATED_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+pub struct Pipeline {
+    /// How much of the previous frames are kept each frame, from 0 (none, feedback is off) to
+    /// `MAX_FEEDBACK`.
+    amount: f32,
+    /// Whether the accumulated image has anything worth keeping in it.
+    primed: bool,
+
+    surface_format: wgpu::TextureFormat,
+    sampler: wgpu::Sampler,
+    /// Only present once we know how big the screen is.
+    targets: Option<Targets>,
+
+    composite_pipeline: wgpu::RenderPipeline,
+    blit_pipeline: wgpu::RenderPipeline,
+}
+
+/// Textures that have to match the size of the screen.
+struct Targets {
+    scene_view: wgpu::TextureView,
+    accumulated_view: wgpu::TextureView,
+    /// Reads from the scene texture
+    scene_bind_group: shader::bind_groups::BindGroup0,
+    /// Reads from the accumulated texture
+    accumulated_bind_group: shader::bind_groups::BindGroup0,
+}
+
+impl Pipeline {
+    pub fn new(device: &wgpu::Device, surface_format: wgpu::TextureFormat) -> Self {
+        let module = shader::create_shader_module(device);
+        let layout = shader::create_pipeline_layout(device);
+        let pipeline = |label: &str, target: wgpu::ColorTargetState| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&layout),
+                vertex: shader::vertex_state(&module, &shader::vs_entry()),
+                fragment: Some(shader::fragment_state(
+                    &module,
+                    &shader::fs_entry([Some(target)]),
+                )),
+                primitive: Default::default(),
+                depth_stencil: Default::default(),
+                multisample: Default::default(),
+                multiview: Default::default(),
+                cache: Default::default(),
+            })
+        };
+
+        // accumulated = scene * (1 - amount) + accumulated * amount, with the amount set as the
+        // blend constant
+        let fade = wgpu::BlendComponent {
+            src_factor: wgpu::BlendFactor::OneMinusConstant,
+            dst_factor: wgpu::BlendFactor::Constant,
+            operation: wgpu::BlendOperation::Add,
+        };
+        let composite_pipeline = pipeline(
+            "feedback composite pipeline",
+            wgpu::ColorTargetState {
+                format: ACCUMULATED_FORMAT,
+                blend: Some(wgpu::BlendState {
+                    color: fade,
+                    alpha: fade,
+                }),
+                write_mask: wgpu::ColorWrites::ALL,
+            },
+        );
+        let blit_pipeline = pipeline(
+            "feedback blit pipeline",
+            wgpu::ColorTargetState {
+                format: surface_format,
+                blend: None,
+                write_mask: wgpu::ColorWrites::ALL,
+            },
+        );
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("feedback_sampler"),
+            // Everything is the same size as the screen, so there's nothing to filter
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        Self {
+            amount: 0.0,
+            primed: false,
+            surface_format,
+            sampler,
+            targets: None,
+            composite_pipeline,
+            blit_pipeline,
+        }
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, new_size: PhysicalSize<u32>) {
+        let texture = |label: &str, format: wgpu::TextureFormat| {
+            device
+                .create_texture(&wgpu::TextureDescriptor {
+                    label: Some(&format!("{label}_texture")),
+                    size: wgpu::Extent3d {
+                        width: new_size.width.max(1),
+                        height: new_size.height.max(1),
+                        depth_or_array_layers: 1,
+                    },
+                    format,
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                        | wgpu::TextureUsages::TEXTURE_BINDING,
+                    view_formats: &[],
+                })
+                .create_view(&Default::default())
+        };
+        let scene_view = texture("feedback_scene", self.surface_format);
+        let accumulated_view = texture("feedback_accumulated", ACCUMULATED_FORMAT);
+
+        let bind_group = |view: &wgpu::TextureView| {
+            shader::bind_groups::BindGroup0::from_bindings(
+                device,
+                shader::bind_groups::BindGroupLayout0 {
+                    ourSampler: &self.sampler,
+                    ourTexture: view,
+                },
+            )
+        };
+        self.targets = Some(Targets {
+            scene_bind_group: bind_group(&scene_view),
+            accumulated_bind_group: bind_group(&accumulated_view),
+            scene_view,
+            accumulated_view,
+        });
+        // The old image is gone
+        self.primed = false;
+    }
+
+    pub fn amount(&self) -> f32 {
+        self.amount
+    }
+
+    pub fn set_amount(&mut self, amount: f32) {
+        self.amount = amount.clamp(0.0, MAX_FEEDBACK);
+        if self.amount == 0.0 {
+            // Don't show whatever was left over next time feedback gets turned on
+            self.primed = false;
+        }
+    }
+
+    /// Where the current frame should be drawn. When feedback is off, that's the screen itself.
+    pub fn scene_view<'a>(&'a self, screen: &'a wgpu::TextureView) -> &'a wgpu::TextureView {
+        match &self.targets {
+            Some(targets) if self.amount > 0.0 => &targets.scene_view,
+            _ => screen,
+        }
+    }
+
+    /// Blends the frame drawn into `scene_view()` with the previous ones, and puts the result on
+    /// the screen. Does nothing when feedback is off, since the frame is already there.
+    pub fn composite(&mut self, encoder: &mut wgpu::CommandEncoder, screen: &wgpu::TextureView) {
+        let targets = match &self.targets {
+            Some(targets) if self.amount > 0.0 => targets,
+            _ => return,
+        };
+
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("feedback_composite_pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &targets.accumulated_view,
+                    depth_slice: None,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: if self.primed {
+                            wgpu::LoadOp::Load
+                        } else {
+                            wgpu::LoadOp::Clear(wgpu::Color::BLACK)
+                        },
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            render_pass.set_pipeline(&self.composite_pipeline);
+            let amount = f64::from(self.amount);
+            render_pass.set_blend_constant(wgpu::Color {
+                r: amount,
+                g: amount,
+                b: amount,
+                a: amount,
+            });
+            targets.scene_bind_group.set(&mut render_pass);
+            render_pass.draw(0..3, 0..1);
+        }
+
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("feedback_blit_pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: screen,
+                    depth_slice: None,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        // Covered entirely by the accumulated image
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            render_pass.set_pipeline(&self.blit_pipeline);
+            targets.accumulated_bind_group.set(&mut render_pass);
+            render_pass.draw(0..3, 0..1);
+        }
+
+        self.primed = true;
+    }
+}
@@ -12,6 +12,36 @@ use crate::{
     shaders::{pipelines, tris_render_shader as render_shader},
 };
 
+/// How big the visualizer's circles & lines are, in header pixels. Each circle sits its radius
+/// above the bottom of the header.
+#[derive(Debug, Clone, Copy)]
+pub struct VisualizerSizes {
+    pub circle_radius: f32,
+    /// How thick each circle's ring is, inwards from its radius.
+    pub ring_width: f32,
+    /// How thick the lines joining the circles are.
+    pub line_width: f32,
+}
+
+impl Default for VisualizerSizes {
+    fn default() -> Self {
+        Self {
+            circle_radius: 10.0,
+            ring_width: 2.0,
+            line_width: 1.0,
+        }
+    }
+}
+
+impl VisualizerSizes {
+    /// The biggest circles can be while still fitting in their bins & the header.
+    pub const MAX_RADIUS: f32 = (if FFT_BIN_WIDTH < HEADER_HEIGHT {
+        FFT_BIN_WIDTH
+    } else {
+        HEADER_HEIGHT
+    } / 2) as f32;
+}
+
 pub struct Pipeline {
     render_uniforms_buffer: wgpu::Buffer,
 
@@ -24,8 +54,17 @@ pub struct Pipeline {
     // The offsets to apply to the geometry. It contains things type glam::Vec2, and has length
     // NUM_BINS.
     offset_buffer: wgpu::Buffer,
+    /// How far the circles can rise before going over the top of the header.
+    max_rise: f32,
 
     render_bind_group: render_shader::bind_groups::BindGroup0,
+
+    /// Offsets for a faint copy of the geometry, following the bins before any auto-gain or
+    /// smoothing.
+    raw_offset_buffer: wgpu::Buffer,
+    raw_bind_group: render_shader::bind_groups::BindGroup0,
+    /// Whether the faint copy gets drawn.
+    show_raw: bool,
 }
 
 impl Pipeline {
@@ -33,6 +72,7 @@ impl Pipeline {
         device: &wgpu::Device,
         queue: &wgpu::Queue,
         surface_format: wgpu::TextureFormat,
+        sizes: VisualizerSizes,
     ) -> Self {
         pipelines::initialize(device, surface_format);
 
@@ -41,28 +81,31 @@ impl Pipeline {
             device,
             queue,
             "fft vertex buffer",
-            (0..NUM_BINS).flat_map(|i| -> Box<dyn Iterator<Item = render_shader::Vertex>> {
-                const W: f32 = FFT_BIN_WIDTH as f32;
-                const H: f32 = HEADER_HEIGHT as f32;
-
-                let i = i as u32;
-                let x = i as f32;
-
-                // add circle in this bin
-                let center = glam::vec2(W * x + W / 2.0, H - 10.0);
-                let circle = make_circle(center, 8.0, 10.0);
-                let circle = circle.to_vertices(i);
-
-                if i > 0 {
-                    // add line from previous circle
-                    let h = i - 1;
-                    let prev_center = center - glam::vec2(W, 0.0);
-                    let line = make_line(prev_center, center, 1.0);
-                    Box::new(circle.chain(line.to_vertices((h, i))))
-                } else {
-                    Box::new(circle)
-                }
-            }),
+            (0..NUM_BINS).flat_map(
+                move |i| -> Box<dyn Iterator<Item = render_shader::Vertex>> {
+                    const W: f32 = FFT_BIN_WIDTH as f32;
+                    const H: f32 = HEADER_HEIGHT as f32;
+
+                    let i = i as u32;
+                    let x = i as f32;
+
+                    // add circle in this bin
+                    let radius = sizes.circle_radius;
+                    let center = glam::vec2(W * x + W / 2.0, H - radius);
+                    let circle = make_circle(center, (radius - sizes.ring_width).max(0.0), radius);
+                    let circle = circle.to_vertices(i);
+
+                    if i > 0 {
+                        // add line from previous circle
+                        let h = i - 1;
+                        let prev_center = center - glam::vec2(W, 0.0);
+                        let line = make_line(prev_center, center, sizes.line_width);
+                        Box::new(circle.chain(line.to_vertices((h, i))))
+                    } else {
+                        Box::new(circle)
+                    }
+                },
+            ),
         );
 
         // The previous geometry created exactly `NUM_BINS` indexes that we need to
@@ -100,17 +143,62 @@ impl Pipeline {
             },
         );
 
+        let raw_color_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("fft raw color buffer"),
+            size: (size_of::<glam::Vec4>() * NUM_BINS) as u64,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let raw_color_data: Vec<glam::Vec4> = (0..NUM_BINS)
+            .map(|index| (bin_color(index).truncate() * RAW_BRIGHTNESS).extend(1.0))
+            .collect();
+        queue.write_buffer(
+            &raw_color_buffer,
+            0,
+            bytemuck::cast_slice(&raw_color_data[..]),
+        );
+        let raw_offset_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("fft raw offset buffer"),
+            size: (size_of::<glam::Vec2>() * NUM_BINS) as u64,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let raw_bind_group = render_shader::bind_groups::BindGroup0::from_bindings(
+            device,
+            render_shader::bind_groups::BindGroupLayout0 {
+                colors: raw_color_buffer.as_entire_buffer_binding(),
+                offsets: raw_offset_buffer.as_entire_buffer_binding(),
+                uni: render_uniforms_buffer.as_entire_buffer_binding(),
+            },
+        );
+
         Self {
             render_uniforms_buffer,
             vertex_buffer,
             color_buffer,
             offset_buffer,
+            max_rise: (HEADER_HEIGHT as f32 - 2.0 * sizes.circle_radius).max(0.0),
             render_bind_group,
+            raw_offset_buffer,
+            raw_bind_group,
+            show_raw: false,
         }
     }
 
-    pub fn resize(&mut self, queue: &wgpu::Queue, new_size: PhysicalSize<u32>) {
-        let render_uniforms = Self::calculate_uniforms(new_size);
+    /// Starts or stops drawing the raw bins, returning whether they're drawn now.
+    pub fn toggle_raw(&mut self) -> bool {
+        self.show_raw = !self.show_raw;
+        self.show_raw
+    }
+
+    pub fn resize(
+        &mut self,
+        queue: &wgpu::Queue,
+        new_size: PhysicalSize<u32>,
+        frame: camera_2d::DestinationRect,
+        scale: f32,
+    ) {
+        let render_uniforms = Self::calculate_uniforms(new_size, frame, scale);
         queue.write_buffer(
             &self.render_uniforms_buffer,
             0,
@@ -118,7 +206,11 @@ impl Pipeline {
         );
     }
 
-    fn calculate_uniforms(size: PhysicalSize<u32>) -> render_shader::Uniforms {
+    fn calculate_uniforms(
+        size: PhysicalSize<u32>,
+        frame: camera_2d::DestinationRect,
+        scale: f32,
+    ) -> render_shader::Uniforms {
         camera_2d::Uniforms::source_to_screen(
             size.into(),
             camera_2d::SourceRect {
@@ -127,10 +219,10 @@ impl Pipeline {
             },
             // pin to the left edge of the header
             camera_2d::DestinationRect {
-                x: size.width as f32 - FFT_WIDTH as f32,
-                y: 0.0,
-                width: FFT_WIDTH as f32,
-                height: HEADER_HEIGHT as f32,
+                x: frame.x + frame.width - FFT_WIDTH as f32 * scale,
+                y: frame.y,
+                width: FFT_WIDTH as f32 * scale,
+                height: HEADER_HEIGHT as f32 * scale,
             },
             camera_2d::Mode::Fit,
         )
@@ -139,38 +231,79 @@ impl Pipeline {
 
     pub fn set_mode(&mut self, queue: &wgpu::Queue, mode: Mode) {
         let highlighted_index = match mode {
-            Mode::Fft { index, param: _ } => Some(index.0),
+            Mode::Fft { index, param: _ } | Mode::Bands { index } => Some(index.0),
             Mode::Normal | Mode::EnteringNumber(_) | Mode::Base(_) => None,
         };
         let color_data: Vec<glam::Vec4> = (0..NUM_BINS)
             .map(|index| {
                 if Some(index) == highlighted_index {
-                    // red
-                    glam::vec4(1.0, 0.0, 0.0, 1.0)
-                } else {
-                    // white
+                    // white, which stands out from all the band colors
                     glam::vec4(1.0, 1.0, 1.0, 1.0)
+                } else {
+                    bin_color(index)
                 }
             })
             .collect();
         queue.write_buffer(&self.color_buffer, 0, bytemuck::cast_slice(&color_data[..]));
     }
 
-    pub fn prepare(&mut self, queue: &wgpu::Queue, bins: &[f32; NUM_BINS]) {
-        let offset_data: Vec<glam::Vec2> =
-            bins.iter().map(|v| glam::vec2(0.0, *v * -0.2)).collect();
+    /// Moves the circles to follow `bins`, & the faint ones to follow `raw_bins` if they're shown.
+    pub fn prepare(
+        &mut self,
+        queue: &wgpu::Queue,
+        bins: &[f32; NUM_BINS],
+        raw_bins: &[f32; NUM_BINS],
+    ) {
         queue.write_buffer(
             &self.offset_buffer,
             0,
-            bytemuck::cast_slice(&offset_data[..]),
+            bytemuck::cast_slice(&self.offsets(bins)[..]),
         );
+        if self.show_raw {
+            queue.write_buffer(
+                &self.raw_offset_buffer,
+                0,
+                bytemuck::cast_slice(&self.offsets(raw_bins)[..]),
+            );
+        }
+    }
+
+    fn offsets(&self, bins: &[f32; NUM_BINS]) -> Vec<glam::Vec2> {
+        // Loud bins would otherwise push their circle up off the top of the header. Written so a
+        // NaN bin stays put rather than jumping to the top.
+        bins.iter()
+            .map(|v| glam::vec2(0.0, -(v * 0.2).max(0.0).min(self.max_rise)))
+            .collect()
     }
 
     pub fn render_pass(&self, render_pass: &mut wgpu::RenderPass) {
         pipelines::render_tris(render_pass);
 
-        render_shader::set_bind_groups(render_pass, &self.render_bind_group);
         render_pass.set_vertex_buffer(0, self.vertex_buffer.buffer.slice(..));
+        // Behind the bins being used, so those stay on top where they overlap
+        if self.show_raw {
+            render_shader::set_bind_groups(render_pass, &self.raw_bind_group);
+            render_pass.draw(0..self.vertex_buffer.num_vertices as u32, 0..1);
+        }
+        render_shader::set_bind_groups(render_pass, &self.render_bind_group);
         render_pass.draw(0..self.vertex_buffer.num_vertices as u32, 0..1);
     }
 }
+
+/// How bright the band colors are. Kept below full so the highlighted bin stands out.
+const BIN_BRIGHTNESS: f32 = 0.8;
+
+/// How bright the raw bins are next to the ones being used, so they read as faint.
+const RAW_BRIGHTNESS: f32 = 0.35;
+
+/// The color of a bin, going from deep blue for the lowest frequencies to red for the highest.
+fn bin_color(index: usize) -> glam::Vec4 {
+    let t = index as f32 / (NUM_BINS - 1).max(1) as f32;
+    // Hue in sixths of a turn: 4 is blue, 0 is red
+    let hue = 4.0 * (1.0 - t);
+    let channel = |n: f32| {
+        let k = (n + hue) % 6.0;
+        BIN_BRIGHTNESS * (1.0 - (k.min(4.0 - k)).clamp(0.0, 1.0))
+    };
+    glam::vec4(channel(5.0), channel(3.0), channel(1.0), 1.0)
+}
@@ -1,21 +1,45 @@
 use std::path::PathBuf;
 
-use winit::dpi::PhysicalSize;
+use bytemuck::Zeroable;
+use web_time::{Duration, Instant};
+use winit::dpi::{PhysicalPosition, PhysicalSize};
 use winit::keyboard::KeyCode;
 
 use crate::AudioDisplay;
+use crate::action::Action;
+use crate::audio;
+use crate::constants;
 use crate::fs::AllSettings;
+use crate::fs::point_settings::PointSettings;
 use crate::fs::settings;
+use crate::shaders::compute_shader;
 
+mod beat;
+pub use beat::Beats;
+mod brush;
+pub use brush::Stroke;
 mod camera_2d;
+pub use camera_2d::Aspect;
+mod capture;
+pub use capture::Image;
+mod feedback;
 mod fft;
+pub use fft::VisualizerSizes;
 mod geometry_2d;
 mod physarum;
+pub use physarum::{Distribution, Edges, Glow, required_limits};
 mod playback;
 mod preset;
+mod rewind;
+mod screensaver;
 #[path = "./settings.rs"]
 mod settings_display;
+mod smoothing;
+mod sparkline;
+mod stats;
 mod text;
+pub use text::Theme;
+mod watchdog;
 
 #[derive(Copy, Clone)]
 pub enum Mode {
@@ -28,20 +52,72 @@ pub enum Mode {
         /// Which FFT bin we're changing for. MUST be in the range 0..NUM_BINS
         index: settings::BinIndex,
     },
+    /// Changing which frequencies go into each FFT bin.
+    Bands {
+        /// Which FFT bin we're changing the edges of. MUST be in the range 0..NUM_BINS
+        index: settings::BinIndex,
+    },
 }
 
+/// Ratio band edges get nudged by in one keypress, a semitone.
+const BAND_EDGE_STEP: f32 = 1.059_463_1;
+/// Lowest & highest any band edge can go, in Hz.
+const BAND_EDGE_RANGE: (f32, f32) = (10.0, 20_000.0);
+
 pub struct Pipeline {
     mode: Mode,
+    display_source: physarum::DisplaySource,
 
     settings: AllSettings,
+    /// Which frequencies go into each FFT bin, shared with the audio worker.
+    frequency_ranges: audio::SharedRanges,
 
-    playback: playback::Pipeline,
-    fft_visualizer: fft::Pipeline,
+    /// Left out entirely when there's no header, so the simulation gets the whole window.
+    header: Option<Header>,
     physarum: physarum::Pipeline,
+    brush: brush::Brush,
+    /// Where the mouse is on the screen, if it's over the window.
+    cursor: Option<PhysicalPosition<f64>>,
+    /// Whether the magnifier is being held down.
+    magnifying: bool,
+    feedback: feedback::Pipeline,
+    rewind: rewind::Rewind,
+    watchdog: watchdog::Watchdog,
+    capture: capture::Capture,
+    /// How much bigger to draw the header than its normal size, to account for high DPI screens.
+    ui_scale: f32,
+    /// The shape to letterbox everything to, if not the window's.
+    aspect: Option<Aspect>,
+    /// Whether the simulation is being held where it is.
+    paused: bool,
+    /// How many steps the simulation takes next frame, if something other than the frame rate is
+    /// deciding. Otherwise it takes one step a frame.
+    steps: Option<u32>,
+    /// When the simulation last stepped by itself, for decaying the trail by how long it's been.
+    last_stepped: Option<Instant>,
+    /// Smooths out each FFT bin as its settings ask for.
+    smoothing: smoothing::Smoothing,
+    beats: beat::Beats,
+    /// How many particles the strongest beats respawn, if beats respawn any.
+    beat_spawn: Option<usize>,
+    /// Morphing through random settings in place of the preset's, if nobody's been around for a
+    /// while.
+    screensaver: Option<screensaver::Screensaver>,
+    /// What the base settings resolved to last frame once the music was mixed in, if they're
+    /// being shown alongside them.
+    resolved: Option<PointSettings>,
 
-    text: text::Pipeline,
     settings_text: settings_display::Text,
     preset_text: preset::Text,
+    stats_text: stats::Text,
+}
+
+/// Everything drawn in the header above the simulation.
+struct Header {
+    text: text::Pipeline,
+    playback: playback::Pipeline,
+    fft_visualizer: fft::Pipeline,
+    sparkline: sparkline::Pipeline,
 }
 
 impl Pipeline {
@@ -50,16 +126,52 @@ impl Pipeline {
         queue: &wgpu::Queue,
         size: PhysicalSize<u32>,
         surface_format: wgpu::TextureFormat,
+        edges: Edges,
+        background: Option<&Image>,
+        header: Option<VisualizerSizes>,
     ) -> Self {
+        let brush = brush::Brush::new(device);
         let mut out = Self {
             mode: Mode::Normal,
+            display_source: physarum::DisplaySource::Particles,
             settings: AllSettings::default(),
-            playback: playback::Pipeline::new(device, queue, surface_format),
-            fft_visualizer: fft::Pipeline::new(device, queue, surface_format),
-            physarum: physarum::Pipeline::new(device, queue, surface_format),
-            text: text::Pipeline::new(device, size, surface_format),
+            frequency_ranges: std::sync::Arc::new(std::sync::Mutex::new(
+                audio::DEFAULT_FREQUENCY_RANGES,
+            )),
+            header: header.map(|visualizer| Header {
+                text: text::Pipeline::new(device, size, surface_format),
+                playback: playback::Pipeline::new(device, queue, surface_format),
+                fft_visualizer: fft::Pipeline::new(device, queue, surface_format, visualizer),
+                sparkline: sparkline::Pipeline::new(device, queue, surface_format),
+            }),
+            physarum: physarum::Pipeline::new(
+                device,
+                queue,
+                surface_format,
+                edges,
+                &brush,
+                background,
+            ),
+            brush,
+            cursor: None,
+            magnifying: false,
+            feedback: feedback::Pipeline::new(device, surface_format),
+            rewind: rewind::Rewind::new(0, Duration::ZERO),
+            watchdog: watchdog::Watchdog::new(device),
+            capture: capture::Capture::new(),
+            ui_scale: 1.0,
+            aspect: None,
+            paused: false,
+            steps: None,
+            last_stepped: None,
+            smoothing: smoothing::Smoothing::default(),
+            beats: beat::Beats::default(),
+            beat_spawn: None,
+            screensaver: None,
+            resolved: None,
             settings_text: settings_display::Text::new(),
             preset_text: preset::Text::new(),
+            stats_text: stats::Text::new(),
         };
 
         out.set_mode(queue, Mode::Normal);
@@ -68,21 +180,292 @@ impl Pipeline {
     }
 
     pub fn read_settings_file(&mut self, queue: &wgpu::Queue, path: PathBuf) {
-        self.settings = AllSettings::read_or_default(path);
+        self.use_settings(queue, AllSettings::read_or_default(path));
+    }
+
+    /// Reads the settings file again, e.g. after it's been edited by hand, staying on the same
+    /// preset if it's still there. If the file can't be read, everything already loaded is kept
+    /// rather than lost.
+    pub fn reload_settings_file(&mut self, queue: &wgpu::Queue) {
+        let Some(path) = self.settings.filename.clone() else {
+            eprintln!("No settings file to reload");
+            return;
+        };
+        match AllSettings::read(path.clone()) {
+            Ok(mut settings) => {
+                settings.set_index(self.settings.get_index());
+                self.use_settings(queue, settings);
+                println!("Reloaded settings from {}", path.display());
+            }
+            Err(e) => {
+                eprintln!("Error reloading settings: {e}");
+                eprintln!("Keeping the settings already loaded...");
+            }
+        }
+    }
+
+    fn use_settings(&mut self, queue: &wgpu::Queue, settings: AllSettings) {
+        self.settings = settings;
+        let theme = self.settings.theme().clone();
+        self.settings_text.set_theme(theme.clone());
+        self.preset_text.set_theme(theme.clone());
+        self.stats_text.set_theme(theme.clone());
+        if let Some(header) = &mut self.header {
+            header.playback.set_theme(queue, theme);
+        }
+        self.physarum.set_framing(queue, self.settings.framing());
         self.set_mode(queue, Mode::Normal);
     }
 
+    /// Sets how many particles on a single pixel it takes to get close to full brightness.
+    pub fn set_count_scale(&mut self, queue: &wgpu::Queue, count_scale: f32) {
+        self.physarum.set_count_scale(queue, count_scale);
+    }
+
+    /// Sets how many steps particles live for before respawning somewhere random, or 0 for
+    /// forever.
+    pub fn set_particle_lifetime(&mut self, queue: &wgpu::Queue, steps: u32) {
+        self.physarum.set_particle_lifetime(queue, steps);
+    }
+
+    /// Sets how much of the previous frames stay on screen, from 0 (none) to just under 1.
+    pub fn set_feedback(&mut self, amount: f32) {
+        self.feedback.set_amount(amount);
+    }
+
+    /// Flips the simulation's colors to their opposites while `inverted`, for a flash. The header
+    /// stays as it is so it can still be read.
+    pub fn set_inverted(&mut self, inverted: bool) {
+        self.physarum.set_inverted(inverted);
+    }
+
+    /// Draws a drop shadow behind the header text, so it can be read over bright parts of the
+    /// simulation.
+    pub fn set_text_shadow(&mut self, shadow: bool) {
+        if let Some(header) = &mut self.header {
+            header.text.set_shadow(shadow);
+        }
+    }
+
+    /// Makes bright parts of the trail glow, or not with `None`.
+    pub fn set_glow(&mut self, glow: Option<Glow>) {
+        self.physarum.set_glow(glow);
+    }
+
+    /// Keeps up to `snapshots` past states of the simulation to rewind through, taken every
+    /// `interval`.
+    pub fn set_rewind(&mut self, snapshots: usize, interval: Duration) {
+        self.rewind = rewind::Rewind::new(snapshots, interval);
+    }
+
+    /// Makes beats in the music respawn up to this many particles at once, in a bloom.
+    pub fn set_beat_spawn(&mut self, count: usize) {
+        self.beat_spawn = Some(count);
+    }
+
+    /// Holds the simulation where it is, or lets it carry on.
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Starts slowly morphing through random settings, each morph taking `morph_time`, until
+    /// `stop_screensaver()`. The preset itself is left alone.
+    pub fn start_screensaver(&mut self, morph_time: Duration) {
+        println!("Nobody's around; starting the screensaver");
+        self.screensaver = Some(screensaver::Screensaver::start(
+            self.settings.get_live_settings().base.current.clone(),
+            self.settings.randomizer(),
+            morph_time,
+            Instant::now(),
+        ));
+    }
+
+    /// Goes back to the preset's own settings, returning whether the screensaver was running.
+    pub fn stop_screensaver(&mut self) -> bool {
+        let stopped = self.screensaver.take().is_some();
+        if stopped {
+            println!("Stopping the screensaver");
+        }
+        stopped
+    }
+
+    pub fn is_screensaving(&self) -> bool {
+        self.screensaver.is_some()
+    }
+
+    /// Makes the simulation take this many steps next frame, instead of one. Only lasts a frame.
+    pub fn set_steps(&mut self, steps: u32) {
+        self.steps = Some(steps);
+    }
+
+    /// Sets whether the simulation's pixels are kept sharp instead of blended when scaled up.
+    pub fn set_pixelated(&mut self, pixelated: bool) {
+        self.physarum.set_pixelated(pixelated);
+    }
+
+    /// Shows or hides the heatmap of how many particles are on each pixel.
+    pub fn toggle_heatmap(&mut self, queue: &wgpu::Queue) {
+        let heatmap = !self.physarum.heatmap();
+        self.physarum.set_heatmap(queue, heatmap);
+        if heatmap {
+            println!("Showing where particles are crowding together");
+        } else {
+            println!("Hiding the particle heatmap");
+        }
+    }
+
+    /// Turns the simulation display clockwise by this many quarter turns.
+    pub fn set_rotation(&mut self, queue: &wgpu::Queue, quarter_turns: u32) {
+        self.physarum.set_rotation(queue, quarter_turns);
+    }
+
+    pub fn set_cursor(&mut self, position: Option<PhysicalPosition<f64>>) {
+        self.cursor = position;
+    }
+
+    /// Zooms in on the simulation around the cursor while `magnifying`, following it as it moves.
+    pub fn set_magnifying(&mut self, magnifying: bool) {
+        self.magnifying = magnifying;
+    }
+
+    /// Starts or stops painting settings onto the simulation with the mouse.
+    pub fn set_stroke(&mut self, queue: &wgpu::Queue, stroke: Option<Stroke>) {
+        if stroke.is_some() && !self.brush.has_settings() {
+            // Nothing captured yet, so paint with what's showing now
+            self.capture_brush_settings(queue);
+        }
+        self.brush.set_stroke(stroke);
+    }
+
+    /// Makes the brush paint with the current preset's settings.
+    fn capture_brush_settings(&mut self, queue: &wgpu::Queue) {
+        self.brush.set_settings(
+            queue,
+            &self
+                .settings
+                .get_live_settings()
+                .base
+                .current
+                .clone()
+                .into(),
+        );
+    }
+
+    /// Reads back the next frame, which `render()` will return.
+    pub fn request_capture(&mut self) {
+        self.capture.request();
+    }
+
+    /// Only moves the first `count` particles, for benchmarking.
+    pub fn set_active_particles(&mut self, count: usize) {
+        self.physarum.set_active_particles(count);
+    }
+
+    /// Shows or hides the frame pacing stats over the corner of the simulation.
+    pub fn set_frame_stats_shown(&mut self, shown: bool) {
+        self.stats_text.set_shown(shown);
+    }
+
+    /// Shows or hides the frame pacing stats, & says whether they're shown now.
+    pub fn toggle_frame_stats(&mut self) -> bool {
+        let shown = !self.stats_text.is_shown();
+        self.stats_text.set_shown(shown);
+        shown
+    }
+
+    /// Updates the frame pacing stats shown, if they're shown.
+    pub fn set_frame_stats(&mut self, fps: f32, dropped: u32, frames: u32, longest: Duration) {
+        let particles = self.physarum.active_particles();
+        self.stats_text
+            .update(fps, dropped, frames, longest, particles);
+    }
+
+    /// Shows whether a GIF is being recorded in the header.
+    pub fn set_recording(&mut self, recording: bool) {
+        self.preset_text.set_recording(recording);
+        self.set_preset_text();
+    }
+
+    /// Sets where particles start out, and starts the simulation over from there.
+    pub fn set_distribution(&mut self, queue: &wgpu::Queue, distribution: Distribution) {
+        self.physarum.set_distribution(distribution);
+        self.reset_simulation(queue);
+    }
+
+    /// The frequency ranges to bucket music into, which get changed in place while editing them.
+    pub fn frequency_ranges(&self) -> audio::SharedRanges {
+        self.frequency_ranges.clone()
+    }
+
+    pub fn settings(&self) -> &AllSettings {
+        &self.settings
+    }
+
+    /// Sets how much bigger to draw the header, taking effect on the next resize.
+    pub fn set_ui_scale(&mut self, scale: f32) {
+        self.ui_scale = scale;
+    }
+
+    /// Sets the shape to draw everything in, with black bars around it. Takes effect on the next
+    /// resize.
+    pub fn set_aspect(&mut self, aspect: Option<Aspect>) {
+        self.aspect = aspect;
+    }
+
     pub fn set_playing(&mut self, playing: bool) {
-        self.playback.set_playing(playing);
+        if let Some(header) = &mut self.header {
+            header.playback.set_playing(playing);
+        }
+    }
+
+    pub fn resize(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        new_size: PhysicalSize<u32>,
+    ) {
+        self.feedback.resize(device, new_size);
+        let scale = self.ui_scale;
+        let frame = camera_2d::DestinationRect::letterbox(new_size, self.aspect);
+        let header_height = match &mut self.header {
+            Some(header) => {
+                header.playback.resize(queue, new_size, frame, scale);
+                header.fft_visualizer.resize(queue, new_size, frame, scale);
+                header.sparkline.resize(queue, new_size, frame, scale);
+                header.text.resize(queue, new_size);
+                constants::HEADER_HEIGHT as f32 * scale
+            }
+            None => 0.0,
+        };
+        self.physarum.resize(queue, new_size, frame, header_height);
+        self.settings_text.resize(frame, scale);
+        self.preset_text.resize(frame, scale);
+        self.stats_text.resize(frame, scale, header_height);
     }
 
-    pub fn resize(&mut self, queue: &wgpu::Queue, new_size: PhysicalSize<u32>) {
-        self.playback.resize(queue, new_size);
-        self.fft_visualizer.resize(queue, new_size);
-        self.physarum.resize(queue, new_size);
-        self.text.resize(queue, new_size);
-        self.settings_text.resize(new_size);
-        self.preset_text.resize(new_size);
+    /// What a keypress asks for, if it's something that can be asked for in other ways too.
+    pub fn key_action(&self, key: KeyCode, repeat: bool) -> Option<Action> {
+        match key {
+            KeyCode::Space if !repeat => Some(Action::TogglePause),
+            // While typing a preset number, only keys to do with that number count
+            _ if matches!(self.mode, Mode::EnteringNumber(_)) => None,
+            KeyCode::BracketLeft => Some(Action::PreviousPreset),
+            KeyCode::BracketRight => Some(Action::NextPreset),
+            _ => None,
+        }
+    }
+
+    /// Calls off deleting the current preset, if it was about to be. Any key besides F9 does this,
+    /// before it does anything else.
+    pub fn disarm_delete(&mut self) {
+        if self.settings.disarm_delete() {
+            println!("Not deleting the preset");
+            self.set_preset_text();
+        }
     }
 
     pub fn handle_keypress(&mut self, queue: &wgpu::Queue, key: KeyCode) {
@@ -91,13 +474,111 @@ impl Pipeline {
             return;
         }
 
-        if self.settings.handle_keypress(key) {
+        if key == KeyCode::F6 {
+            self.display_source = self.display_source.next();
+            self.physarum.set_display_source(queue, self.display_source);
+            return;
+        }
+
+        if key == KeyCode::Backquote {
+            self.physarum
+                .set_rotation(queue, self.physarum.rotation() + 1);
+            return;
+        }
+
+        if key == KeyCode::Semicolon {
+            if let Some(header) = &mut self.header {
+                header.sparkline.toggle();
+            }
+            return;
+        }
+
+        if key == KeyCode::Backslash {
+            self.physarum.set_pixelated(!self.physarum.pixelated());
+            return;
+        }
+
+        if key == KeyCode::NumpadSubtract {
+            self.settings.toggle_framing();
+            self.physarum.set_framing(queue, self.settings.framing());
+            return;
+        }
+
+        if matches!(key, KeyCode::Comma | KeyCode::Period) {
+            let step = if key == KeyCode::Comma {
+                -feedback::FEEDBACK_STEP
+            } else {
+                feedback::FEEDBACK_STEP
+            };
+            self.feedback.set_amount(self.feedback.amount() + step);
+            return;
+        }
+
+        let rewound = match key {
+            KeyCode::PageUp => self.rewind.step_back(),
+            KeyCode::PageDown => self.rewind.step_forward(),
+            _ => false,
+        };
+        if rewound {
+            return;
+        }
+
+        if key == KeyCode::KeyH {
+            self.capture_brush_settings(queue);
+            return;
+        }
+
+        if key == KeyCode::Delete {
+            self.brush.clear(queue);
+            return;
+        }
+
+        if key == KeyCode::F10 {
+            self.reset_simulation(queue);
+            return;
+        }
+
+        if key == KeyCode::Quote {
+            let distribution = self.physarum.distribution().next();
+            println!(
+                "Starting particles over in a {} pattern",
+                distribution.name()
+            );
+            self.set_distribution(queue, distribution);
+            return;
+        }
+
+        if key == KeyCode::Home {
+            self.settings.toggle_staging();
+            if self.settings.is_staging() {
+                println!("Staging edits, press Enter to apply them");
+            } else {
+                println!("Editing live");
+            }
+            self.set_preset_text();
+            return;
+        }
+
+        // While typing a preset number, keys like Enter mean "go to preset" rather than anything
+        // to do with the current preset.
+        if !matches!(self.mode, Mode::EnteringNumber(_)) && self.settings.handle_keypress(key) {
             self.set_settings_text();
             self.set_preset_text();
             return;
         }
 
         use Mode::*;
+        if key == KeyCode::KeyK {
+            let new_mode = match self.mode {
+                Bands { .. } => Normal,
+                _ => Bands {
+                    index: settings::BinIndex(0),
+                },
+            };
+            self.set_mode(queue, new_mode);
+            return;
+        }
+
         match self.mode {
             Normal => {
                 if let Some(digit) = key_to_digit(key) {
@@ -112,16 +593,30 @@ impl Pipeline {
                     self.set_mode(queue, Fft { param: None, index });
                 }
             }
+            Bands { index } => {
+                if self.nudge_band_edge(index, key) {
+                    self.set_settings_text();
+                    return;
+                }
+                if let Some(new_index) = settings::BinIndex::activate(key) {
+                    self.set_mode(queue, Bands { index: new_index });
+                }
+            }
             EnteringNumber(current_number) => {
                 if let Some(new_digit) = key_to_digit(key) {
-                    let new_number = current_number * 10 + new_digit;
-                    self.set_mode(queue, EnteringNumber(new_number));
+                    // Once it's past the last preset, more digits can't change which one it picks,
+                    // & would only overflow
+                    if current_number <= self.settings.num_presets() {
+                        let new_number = current_number * 10 + new_digit;
+                        self.set_mode(queue, EnteringNumber(new_number));
+                    }
                     return;
                 }
                 match key {
-                    KeyCode::Equal => {
-                        // Activate the currently highlighted preset
-                        self.settings.set_index(current_number.saturating_sub(1));
+                    KeyCode::Equal | KeyCode::Enter | KeyCode::NumpadEnter => {
+                        // Activate the currently highlighted preset, or the last one if we typed
+                        // past the end
+                        self.set_preset(current_number.saturating_sub(1));
                         self.set_mode(queue, Normal);
                     }
                     KeyCode::Backspace => {
@@ -158,6 +653,12 @@ impl Pipeline {
                 }
             }
             Fft { param, index } => {
+                if key == KeyCode::Minus {
+                    self.settings.flip_bin_sign(index);
+                    self.set_settings_text();
+                    self.set_preset_text();
+                    return;
+                }
                 if let Some(param) = param
                     && self.settings.handle_fft_keypress(param, index, key)
                 {
@@ -202,22 +703,177 @@ impl Pipeline {
         }
     }
 
+    /// Selects the next param along from the one being changed (or previous, if not
+    /// `forwards`), while changing one. Stays with the same band if one is selected.
+    pub fn step_param(&mut self, queue: &wgpu::Queue, forwards: bool) {
+        let new_mode = match self.mode {
+            Mode::Base(param) => Mode::Base(settings_display::step_param(Some(param), forwards)),
+            Mode::Fft { param, index } => Mode::Fft {
+                param: Some(settings_display::step_param(param, forwards)),
+                index,
+            },
+            Mode::Normal | Mode::EnteringNumber(_) | Mode::Bands { .. } => return,
+        };
+        self.set_mode(queue, new_mode);
+    }
+
+    /// Moves one of the edges of a band, if the key is for doing that. Left/right move the low
+    /// edge & down/up move the high edge, never letting them cross.
+    fn nudge_band_edge(&mut self, index: settings::BinIndex, key: KeyCode) -> bool {
+        let mut ranges = self.frequency_ranges.lock().unwrap();
+        let range = &mut ranges[index.0];
+        let (min, max) = BAND_EDGE_RANGE;
+        match key {
+            KeyCode::ArrowLeft => range.lo = (range.lo / BAND_EDGE_STEP).max(min),
+            KeyCode::ArrowRight => {
+                range.lo = (range.lo * BAND_EDGE_STEP).min(range.hi / BAND_EDGE_STEP)
+            }
+            KeyCode::ArrowDown => {
+                range.hi = (range.hi / BAND_EDGE_STEP).max(range.lo * BAND_EDGE_STEP)
+            }
+            KeyCode::ArrowUp => range.hi = (range.hi * BAND_EDGE_STEP).min(max),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Starts the particles over from scratch, without touching any settings.
+    fn reset_simulation(&mut self, queue: &wgpu::Queue) {
+        // Whatever we were showing is about to be thrown away
+        self.rewind.resume();
+        self.physarum.reset(queue);
+        self.watchdog.clear();
+    }
+
+    /// Goes to the next preset as part of a slideshow. Does nothing if the user is in the middle
+    /// of editing something, so we don't throw away their work.
+    pub fn advance_slideshow(&mut self) {
+        if !matches!(self.mode, Mode::Normal) || self.settings.get_dirty() {
+            return;
+        }
+        self.settings.next_preset();
+        self.set_settings_text();
+        self.set_preset_text();
+    }
+
+    pub fn previous_preset(&mut self) {
+        self.settings.previous_preset();
+        self.set_settings_text();
+        self.set_preset_text();
+    }
+
+    pub fn next_preset(&mut self) {
+        self.settings.next_preset();
+        self.set_settings_text();
+        self.set_preset_text();
+    }
+
+    pub fn nudge_turn_strength(&mut self, stronger: bool) {
+        self.settings.nudge_turn_strength(stronger);
+        self.set_settings_text();
+    }
+
+    pub fn nudge_reactivity(&mut self, up: bool) {
+        self.settings.nudge_reactivity(up);
+    }
+
+    pub fn nudge_exposure(&mut self, up: bool) {
+        self.settings.nudge_exposure(up);
+    }
+
+    pub fn nudge_gamma(&mut self, up: bool) {
+        self.settings.nudge_gamma(up);
+    }
+
+    pub fn nudge_deposit_factor(&mut self, up: bool) {
+        self.settings.nudge_deposit_factor(up);
+        self.set_settings_text();
+    }
+
+    pub fn nudge_repulsion(&mut self, up: bool) {
+        self.settings.nudge_repulsion(up);
+    }
+
+    pub fn store_slot(&mut self, slot: crate::fs::Slot) {
+        self.settings.store_slot(slot);
+    }
+
+    pub fn switch_slot(&mut self) {
+        self.settings.switch_slot();
+        self.set_settings_text();
+        self.set_preset_text();
+    }
+
+    pub fn move_preset(&mut self, later: bool) {
+        self.settings.move_preset(later);
+        self.set_preset_text();
+    }
+
+    /// Goes to the preset at this index, or the last one if it's past the end.
+    pub fn set_preset(&mut self, index: usize) {
+        self.settings
+            .set_index(index.min(self.settings.num_presets().saturating_sub(1)));
+        self.set_settings_text();
+        self.set_preset_text();
+    }
+
+    pub fn set_base_param(&mut self, param: settings::Param, value: f32) {
+        self.settings.set_base(param, value);
+        self.set_settings_text();
+        self.set_preset_text();
+    }
+
     fn set_settings_text(&mut self) {
-        let display_settings = match self.mode {
+        let (display_settings, resolved) = match self.mode {
             Mode::Normal | Mode::EnteringNumber(_) | Mode::Base(_) => {
-                &self.settings.get_settings().base
+                (&self.settings.get_settings().base, self.resolved.as_ref())
+            }
+            // A bin's settings only make up part of what gets resolved, so there's nothing to
+            // compare them to
+            Mode::Fft { index, param: _ } => (&self.settings.get_settings().fft[index.0], None),
+            Mode::Bands { .. } => {
+                let ranges = *self.frequency_ranges.lock().unwrap();
+                self.settings_text.set_frequency_ranges(&ranges);
+                return;
             }
-            Mode::Fft { index, param: _ } => &self.settings.get_settings().fft[index.0],
         };
-        self.settings_text.set_settings(display_settings);
+        self.settings_text
+            .set_settings(display_settings, resolved, self.settings.get_settings());
+    }
+
+    /// Shows faint markers for the bins straight out of the FFT behind the ones being used, or
+    /// stops showing them.
+    pub fn toggle_raw_bins(&mut self) {
+        let Some(header) = &mut self.header else {
+            return;
+        };
+        if header.fft_visualizer.toggle_raw() {
+            println!("Showing the raw bins behind the smoothed ones");
+        } else {
+            println!("Hiding the raw bins");
+        }
+    }
+
+    /// Shows or hides what each setting resolves to once the music is mixed in, in place of how
+    /// much a keypress changes it by.
+    pub fn toggle_resolved(&mut self) {
+        self.resolved = match self.resolved {
+            Some(_) => None,
+            None => Some(self.settings.get_live_settings().base.current.clone()),
+        };
+        self.set_settings_text();
     }
 
     fn set_preset_text(&mut self) {
         match self.mode {
-            Mode::Normal | Mode::Base(_) | Mode::Fft { .. } => {
+            Mode::Normal | Mode::Base(_) | Mode::Fft { .. } | Mode::Bands { .. } => {
                 self.preset_text.update(
                     self.settings.get_index(),
-                    if self.settings.get_dirty() {
+                    if self.settings.is_delete_armed() {
+                        preset::PresetMode::ConfirmingDelete
+                    } else if self.settings.get_pending() {
+                        preset::PresetMode::Pending
+                    } else if self.settings.get_dirty() {
                         preset::PresetMode::Dirty
                     } else {
                         preset::PresetMode::Normal
@@ -231,12 +887,36 @@ impl Pipeline {
         }
     }
 
+    /// The parameter currently being changed, if any.
+    fn active_param(&self) -> Option<settings::Param> {
+        match self.mode {
+            Mode::Base(param)
+            | Mode::Fft {
+                param: Some(param), ..
+            } => Some(param),
+            Mode::Normal
+            | Mode::EnteringNumber(_)
+            | Mode::Fft { param: None, .. }
+            | Mode::Bands { .. } => None,
+        }
+    }
+
     fn set_mode(&mut self, queue: &wgpu::Queue, new_mode: Mode) {
         self.mode = new_mode;
+        if let Some(header) = &mut self.header {
+            // Match the color the parameter is highlighted with
+            header.sparkline.set_color(
+                queue,
+                match self.mode {
+                    Mode::Fft { .. } => self.settings.theme().fft_highlight,
+                    _ => self.settings.theme().highlight,
+                },
+            );
+            header.fft_visualizer.set_mode(queue, self.mode);
+        }
         self.settings_text.set_mode(self.mode);
         self.set_settings_text();
         self.set_preset_text();
-        self.fft_visualizer.set_mode(queue, self.mode);
     }
 }
 
@@ -248,54 +928,179 @@ impl Pipeline {
         surface_texture: &wgpu::Texture,
         surface_format: wgpu::TextureFormat,
         data: Option<&AudioDisplay>,
-    ) {
-        self.text.prepare(
-            device,
-            queue,
-            [
-                self.settings_text.section(),
-                self.preset_text.section(),
-                self.playback.section(),
-            ],
-        );
-        let render_fft = match data {
-            Some(data) => {
-                self.playback
-                    .prepare(queue, data.position, data.total_duration);
-                self.fft_visualizer.prepare(queue, &data.bins);
-                let mut combined_settings = self.settings.get_settings().base.current.clone();
-                for (bin_settings, scale) in self
+    ) -> Option<Image> {
+        if let Some(problem) = self.watchdog.check(device) {
+            println!("Simulation looks stuck ({problem}); resetting particles");
+            self.reset_simulation(queue);
+        }
+        if self.settings.check_delete_timeout() {
+            self.set_preset_text();
+        }
+
+        if let Some(header) = &mut self.header {
+            header.text.prepare(
+                device,
+                queue,
+                [
+                    self.settings_text.section(),
+                    self.preset_text.section(),
+                    header.playback.section(),
+                    self.stats_text.section(),
+                ],
+            );
+        }
+        // Music that isn't stereo, or no music at all, stays balanced
+        let stereo_bias = data.map_or(0.0, |data| {
+            data.balance
+                * self
                     .settings
-                    .get_settings()
-                    .fft
-                    .iter()
-                    .zip(data.bins.iter())
-                {
-                    combined_settings = combined_settings + bin_settings.current.clone() * *scale;
+                    .get_live_settings()
+                    .stereo_lean
+                    .clamp(0.0, 1.0)
+        });
+        self.physarum.set_stereo_bias(queue, stereo_bias);
+        let base = match &mut self.screensaver {
+            Some(screensaver) => screensaver.update(self.settings.randomizer(), Instant::now()),
+            None => self.settings.get_live_settings().base.current.clone(),
+        };
+        let (resolved_settings, render_fft) = match data {
+            Some(data) => {
+                let live = self.settings.get_live_settings();
+                let bins = self
+                    .smoothing
+                    .update(&data.bins, &live.smoothing, Instant::now());
+                if let Some(header) = &mut self.header {
+                    header
+                        .playback
+                        .prepare(queue, data.position, data.total_duration);
+                    header.fft_visualizer.prepare(queue, &bins, &data.raw_bins);
                 }
-                self.physarum.set_settings(queue, &combined_settings.into());
-                true
-            }
-            None => {
-                self.physarum.set_settings(
-                    queue,
-                    &self.settings.get_settings().base.current.clone().into(),
+                let fft_settings = live.fft.iter().zip(bins.iter()).fold(
+                    compute_shader::PointSettings::zeroed().into(),
+                    |sum: PointSettings, (bin_settings, scale)| {
+                        sum + bin_settings.current.clone() * *scale
+                    },
                 );
-                false
+                let (min, max) = constants::REACTIVITY_RANGE;
+                let fft_settings = if live.reactivity.is_finite() {
+                    fft_settings * live.reactivity.clamp(min, max)
+                } else {
+                    fft_settings
+                };
+                let combined_settings = match live.fft_mix {
+                    settings::FftMix::Add => base + fft_settings,
+                    // base * (1 + fft_settings)
+                    settings::FftMix::Multiply => base.clone() + base * fft_settings,
+                };
+                (combined_settings, true)
             }
+            None => (base, false),
         };
+        if self.resolved.is_some() {
+            self.resolved = Some(resolved_settings.clone());
+            self.set_settings_text();
+        }
+        let active_value = self
+            .active_param()
+            .map(|param| param.get(&resolved_settings));
+        if let Some(header) = &mut self.header {
+            header.sparkline.prepare(queue, active_value);
+        }
+        self.physarum.set_settings(queue, &resolved_settings.into());
+        self.physarum
+            .set_sensors(queue, &self.settings.get_live_settings().sensors);
+        self.physarum
+            .set_diffusion(queue, &self.settings.get_live_settings().diffusion);
+        self.physarum
+            .set_move_speed(queue, self.settings.get_live_settings().move_speed);
+        self.physarum
+            .set_turn_strength(queue, self.settings.get_live_settings().turn_strength);
+        self.physarum
+            .set_trail_colors(queue, self.settings.get_live_settings().trail_colors);
+        self.physarum
+            .set_deposit_factor(queue, self.settings.get_live_settings().deposit_factor);
+        self.physarum
+            .set_repulsion(queue, self.settings.get_live_settings().repulsion);
+        self.physarum
+            .set_magnifier(queue, self.magnifying, self.cursor);
+        self.brush.prepare(
+            queue,
+            self.cursor
+                .and_then(|cursor| self.physarum.screen_to_simulation(cursor)),
+        );
+        let live = self.settings.get_live_settings();
+        let [r, g, b] = live.tint;
+        let background = glam::Vec4::from(live.background);
+        let clamp = |value: f32, (min, max): (f32, f32)| {
+            if value.is_finite() {
+                value.clamp(min, max)
+            } else {
+                1.0
+            }
+        };
+        self.physarum.set_render_settings(
+            queue,
+            glam::vec4(r, g, b, 1.0),
+            (background.truncate() * background.w).extend(1.0),
+            clamp(live.exposure, constants::EXPOSURE_RANGE),
+            clamp(live.gamma, constants::GAMMA_RANGE),
+        );
 
         let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
             label: Some("encoder"),
         });
 
+        // While rewound or paused, the simulation stays frozen on what's being shown. Its display
+        // texture keeps the last frame it drew, so only the header needs any work.
+        let triggered = self.steps.is_some();
+        let steps = self.steps.take().unwrap_or(1);
+        let stepping = !self.rewind.is_rewound() && !self.paused && steps > 0;
+        if !stepping {
+            // Time spent frozen doesn't count, so start timing again once it carries on
+            self.last_stepped = None;
+        } else if triggered {
+            // Triggers set the pace instead of the frame rate, so each step decays the usual
+            // amount
+            self.physarum.set_decay_time(queue, None);
+            self.last_stepped = None;
+        } else {
+            let now = Instant::now();
+            let elapsed = self
+                .last_stepped
+                .map_or(constants::DECAY_FRAME_TIME, |last| now - last);
+            self.physarum.set_decay_time(queue, Some(elapsed));
+            self.last_stepped = Some(now);
+        }
+        if stepping
+            && let (Some(count), Some(data)) = (self.beat_spawn, data)
+            && let Some(strength) = self.beats.update(&data.bins, Instant::now())
         {
+            self.physarum
+                .spawn_bloom(queue, (count as f32 * strength) as usize);
+        }
+        if stepping {
             let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
                 label: Some("compute_pass"),
                 timestamp_writes: None,
             });
 
-            self.physarum.compute_pass(&mut compute_pass);
+            for _ in 0..steps {
+                self.physarum.compute_pass(
+                    &mut compute_pass,
+                    &self.settings.get_live_settings().steps,
+                    &self.brush,
+                );
+            }
+        }
+        // Snapshots of a simulation that isn't moving would all be the same, and an empty trail
+        // is only a problem if it stays empty while running
+        if stepping || self.rewind.is_rewound() {
+            self.rewind
+                .update(device, &mut encoder, self.physarum.live());
+        }
+        if stepping {
+            self.watchdog
+                .copy_sample(&mut encoder, self.physarum.current_trail_texture());
         }
 
         let surface_texture_view = surface_texture.create_view(&wgpu::TextureViewDescriptor {
@@ -310,16 +1115,23 @@ impl Pipeline {
             array_layer_count: None,
         });
 
+        // Bars around a letterboxed frame are kept clean, so the header goes without a background
+        // to match them
+        let clear_color = if self.aspect.is_some() {
+            wgpu::Color::BLACK
+        } else {
+            header_color(background)
+        };
         {
             // Create the renderpass which will clear the screen before drawing anything
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: None,
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &surface_texture_view,
+                    view: self.feedback.scene_view(&surface_texture_view),
                     depth_slice: None,
                     resolve_target: None,
                     ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        load: wgpu::LoadOp::Clear(clear_color),
                         store: wgpu::StoreOp::Store,
                     },
                 })],
@@ -329,14 +1141,42 @@ impl Pipeline {
             });
 
             self.physarum.render_pass(&mut render_pass);
-            self.text.render_pass(&mut render_pass);
-            if render_fft {
-                self.playback.render_pass(&mut render_pass);
-                self.fft_visualizer.render_pass(&mut render_pass);
+            if let Some(header) = &self.header {
+                header.text.render_pass(&mut render_pass);
+                header.sparkline.render_pass(&mut render_pass);
+                if render_fft {
+                    header.playback.render_pass(&mut render_pass);
+                    header.fft_visualizer.render_pass(&mut render_pass);
+                }
             }
         }
+        self.feedback.composite(&mut encoder, &surface_texture_view);
+        self.capture
+            .copy_frame(device, &mut encoder, surface_texture);
 
         queue.submit([encoder.finish()]);
+        self.watchdog.after_submit();
+        self.capture.read(device)
+    }
+}
+
+/// What to clear the screen with, which only ends up showing behind the header. Bright
+/// backgrounds get dimmed, so the header's text stays readable on top of them.
+fn header_color(background: glam::Vec4) -> wgpu::Color {
+    /// Brightest the header gets. Everything drawn in it is bright, so it needs to stay dark.
+    const MAX_LUMINANCE: f32 = 0.1;
+    let color = background.truncate() * background.w;
+    let luminance = color.dot(glam::vec3(0.2126, 0.7152, 0.0722));
+    let color = if luminance > MAX_LUMINANCE {
+        color * (MAX_LUMINANCE / luminance)
+    } else {
+        color
+    };
+    wgpu::Color {
+        r: f64::from(color.x),
+        g: f64::from(color.y),
+        b: f64::from(color.z),
+        a: 1.0,
     }
 }
 
@@ -1,27 +1,146 @@
-use winit::dpi::PhysicalSize;
+use std::time::Duration;
+
+use rand::Rng;
+use winit::dpi::{PhysicalPosition, PhysicalSize};
 
 use crate::constants::*;
+use crate::fs::settings::{Diffusion, Framing, Sensors, Steps, TrailColors};
+use crate::graphics::Image;
+use crate::graphics::brush;
 use crate::graphics::camera_2d;
+use crate::graphics::rewind;
 use crate::shaders::compute_shader;
 use crate::shaders::compute_shader::PointSettings;
 use crate::shaders::rect_render_shader as render_shader;
 
+/// How spread out the particles in a bloom are, in simulation pixels.
+const BLOOM_SPREAD: f32 = 24.0;
+/// How many times bigger the magnifier shows the simulation.
+const MAGNIFIER_ZOOM: f32 = 4.0;
+
+/// Makes bright parts of the trail shine out onto what's around them.
+#[derive(Copy, Clone)]
+pub struct Glow {
+    /// How bright the trail needs to be on screen before it glows, from 0 to 1.
+    pub threshold: f32,
+    /// How strongly it glows, where 1 adds about as much light as the trail itself.
+    pub intensity: f32,
+}
+
+/// What the simulation display is made out of.
+#[derive(Copy, Clone)]
+pub enum DisplaySource {
+    /// How many particles are on each pixel. Gives a sharper look.
+    Particles,
+    /// An even mix of the particles & the trail.
+    Mixed,
+    /// The trail the particles leave behind, which is what they actually sense.
+    Trail,
+}
+
+impl DisplaySource {
+    pub fn next(self) -> Self {
+        match self {
+            Self::Particles => Self::Mixed,
+            Self::Mixed => Self::Trail,
+            Self::Trail => Self::Particles,
+        }
+    }
+
+    fn count_blend(self) -> f32 {
+        match self {
+            Self::Particles => 1.0,
+            Self::Mixed => 0.5,
+            Self::Trail => 0.0,
+        }
+    }
+}
+
+/// What happens when particles & trails reach the edges of the simulation.
+#[derive(Copy, Clone)]
+pub enum Edges {
+    /// They wrap around to the other side, as if the simulation were on a torus.
+    Wrap,
+    /// They bounce off the edges.
+    Bounded,
+}
+
+impl Edges {
+    fn address_mode(self) -> wgpu::AddressMode {
+        match self {
+            Self::Wrap => wgpu::AddressMode::Repeat,
+            Self::Bounded => wgpu::AddressMode::ClampToEdge,
+        }
+    }
+}
+
 pub struct Pipeline {
+    /// The constants currently on the GPU. Change them with `update_constants()`.
+    constants: compute_shader::Constants,
+    constants_buffer: wgpu::Buffer,
     point_settings_buffer: wgpu::Buffer,
 
     constants_bind_group: compute_shader::bind_groups::BindGroup0,
     state_bind_group: compute_shader::bind_groups::BindGroup1,
+    /// Reads from trail_read_texture, writes to trail_write_texture
     trail_read_bind_group: compute_shader::bind_groups::BindGroup2,
+    /// Reads from trail_write_texture, writes to trail_read_texture
     trail_write_bind_group: compute_shader::bind_groups::BindGroup2,
+    particle_params_buffer: wgpu::Buffer,
+    fbo_texture: wgpu::Texture,
+    trail_read_texture: wgpu::Texture,
+    trail_write_texture: wgpu::Texture,
+    /// Whether the most recent trail is in trail_write_texture rather than trail_read_texture.
+    /// Depending on how many passes write to the trail each frame, this can change frame to frame.
+    trail_in_write_texture: bool,
+    /// Number of times compute_pass() has been run, used for passes that don't run every frame.
+    frame: u64,
 
     setter_pipeline: wgpu::ComputePipeline,
     move_pipeline: wgpu::ComputePipeline,
     deposit_pipeline: wgpu::ComputePipeline,
+    /// Only updates the display, for the steps that skip depositing.
+    display_pipeline: wgpu::ComputePipeline,
     diffusion_pipeline: wgpu::ComputePipeline,
 
     render_uniforms_buffer: wgpu::Buffer,
-    render_bind_group: render_shader::bind_groups::BindGroup0,
+    /// The size of the screen, kept around so the uniforms can be recalculated when rotating.
+    screen_size: PhysicalSize<u32>,
+    /// The part of the screen everything's drawn in, including the header.
+    area: camera_2d::DestinationRect,
+    /// How tall the header above the simulation is on screen, in pixels.
+    header_height: f32,
+    /// How many times the display is turned clockwise by 90 degrees.
+    quarter_turns: u32,
+    /// How the simulation is fitted into the space below the header.
+    framing: Framing,
+    /// How big the image behind the simulation is, if there is one.
+    background_size: Option<glam::UVec2>,
+    /// Whether the display is zoomed in to look at the details.
+    magnifying: bool,
+    /// Where on the screen the magnifier zooms in around, in pixels. The middle of what's showing
+    /// if `None`.
+    magnifier_focus: Option<glam::Vec2>,
+    render_settings_buffer: wgpu::Buffer,
+    /// Blends between simulation pixels when scaling them up
+    smooth_render_bind_group: render_shader::bind_groups::BindGroup0,
+    /// Keeps simulation pixels sharp when scaling them up
+    pixelated_render_bind_group: render_shader::bind_groups::BindGroup0,
+    /// Whether to use `pixelated_render_bind_group`.
+    pixelated: bool,
+    /// Where particles start out after a reset.
+    distribution: Distribution,
+    /// The first particle the next bloom respawns.
+    next_bloom_particle: usize,
+    /// How many particles get moved each step. Always all of them, except while benchmarking.
+    active_particles: usize,
+    /// Whether to draw a glow on top of the trail, & what it looks like.
+    glow: Option<Glow>,
+    /// Whether the colors are flipped to their opposites, for a flash.
+    inverted: bool,
     render_pipeline: wgpu::RenderPipeline,
+    /// Adds the glow onto what `render_pipeline` drew.
+    glow_pipeline: wgpu::RenderPipeline,
 }
 
 impl Pipeline {
@@ -29,7 +148,15 @@ impl Pipeline {
         device: &wgpu::Device,
         queue: &wgpu::Queue,
         surface_format: wgpu::TextureFormat,
+        edges: Edges,
+        brush: &brush::Brush,
+        background: Option<&Image>,
     ) -> Self {
+        let constants = compute_shader::Constants {
+            wrap_edges: matches!(edges, Edges::Wrap).into(),
+            ..CONSTANTS
+        };
+
         let buffer = |name: &str, size: u64, usage: wgpu::BufferUsages| {
             device.create_buffer(&wgpu::BufferDescriptor {
                 label: Some(&format!("{name}_buffer")),
@@ -44,7 +171,7 @@ impl Pipeline {
             size_of::<compute_shader::Constants>() as u64,
             wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
         );
-        queue.write_buffer(&constants_buffer, 0, bytemuck::bytes_of(&CONSTANTS));
+        queue.write_buffer(&constants_buffer, 0, bytemuck::bytes_of(&constants));
 
         let point_settings_buffer = buffer(
             "point_settings",
@@ -59,27 +186,24 @@ impl Pipeline {
             compute_shader::bind_groups::BindGroupLayout0 {
                 constants: constants_buffer.as_entire_buffer_binding(),
                 params: point_settings_buffer.as_entire_buffer_binding(),
+                brush_params: brush.buffers().settings.as_entire_buffer_binding(),
+                brush: brush.buffers().uniforms.as_entire_buffer_binding(),
             },
         );
 
-        // Randomly initialize the particles' starting positions and headings
-        let mut particles = vec![0u16; SIMULATION_NUM_PARTICLES * 4];
-        fn float_as_u16(f: f32) -> u16 {
-            (f.clamp(0., 1.) * 65535.).round() as u16
-        }
-        for (i, p) in particles.iter_mut().enumerate() {
-            if i % 4 == 0 {
-                *p = float_as_u16(rand::random_range(0..SIMULATION_WIDTH) as f32);
-            } else if i % 4 == 1 {
-                *p = float_as_u16(rand::random_range(0..SIMULATION_HEIGHT) as f32);
-            } else {
-                *p = float_as_u16(rand::random_range(0..u16::MAX) as f32 / u16::MAX as f32);
-            }
-        }
+        // Can't be checked at compile time, but the trail gets read back & written assuming this
+        assert_eq!(
+            TRAIL_FORMAT.block_copy_size(None),
+            Some(TRAIL_TEXEL_SIZE),
+            "TRAIL_TEXEL_SIZE must match TRAIL_FORMAT"
+        );
+        let particles = random_particles(Distribution::Uniform);
         let particle_params_buffer = buffer(
             "particle_params",
-            particles.len() as u64 * 2,
-            wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            SIMULATION_NUM_PARTICLES as u64 * PARTICLE_SIZE,
+            wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::COPY_SRC
+                | wgpu::BufferUsages::COPY_DST,
         );
         queue.write_buffer(
             &particle_params_buffer,
@@ -87,11 +211,15 @@ impl Pipeline {
             bytemuck::cast_slice(particles.as_slice()),
         );
 
+        // One count for the pixel as a whole, then one for each color channel, then the pixel's
+        // count as of the last step
         let particle_counts_buffer = buffer(
             "particle_counts",
-            (SIMULATION_WIDTH * SIMULATION_HEIGHT * 4) as u64,
+            (SIMULATION_WIDTH * SIMULATION_HEIGHT * 5) as u64 * 4,
             wgpu::BufferUsages::STORAGE,
         );
+        // The two numbers the heatmap is scaled by
+        let heatmap_max_buffer = buffer("heatmap_max", 2 * 4, wgpu::BufferUsages::STORAGE);
         // The counter is re-initialized by the shader every frame
 
         let texture = |label: &str, format: wgpu::TextureFormat, usage: wgpu::TextureUsages| {
@@ -113,7 +241,11 @@ impl Pipeline {
         let fbo_texture = texture(
             "fbo",
             wgpu::TextureFormat::Rgba8Unorm,
-            wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::TEXTURE_BINDING,
+            // Copies are for rewinding
+            wgpu::TextureUsages::STORAGE_BINDING
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC
+                | wgpu::TextureUsages::COPY_DST,
         );
 
         fn texture_view(
@@ -147,18 +279,26 @@ impl Pipeline {
                 particle_params: particle_params_buffer.as_entire_buffer_binding(),
                 particle_counters: particle_counts_buffer.as_entire_buffer_binding(),
                 fbo_display: &fbo_texture_view,
+                param_field: brush.buffers().field.as_entire_buffer_binding(),
+                heatmap_max: heatmap_max_buffer.as_entire_buffer_binding(),
             },
         );
 
         let trail_read_texture = texture(
             "trail_read",
-            wgpu::TextureFormat::R32Float,
-            wgpu::TextureUsages::STORAGE_BINDING,
+            TRAIL_FORMAT,
+            // Copies are for checking on & resetting the trail
+            wgpu::TextureUsages::STORAGE_BINDING
+                | wgpu::TextureUsages::COPY_SRC
+                | wgpu::TextureUsages::COPY_DST,
         );
         let trail_write_texture = texture(
             "trail_write",
-            wgpu::TextureFormat::R32Float,
-            wgpu::TextureUsages::STORAGE_BINDING,
+            TRAIL_FORMAT,
+            // Copies are for checking on & resetting the trail
+            wgpu::TextureUsages::STORAGE_BINDING
+                | wgpu::TextureUsages::COPY_SRC
+                | wgpu::TextureUsages::COPY_DST,
         );
 
         let trail_read_texture_view = texture_view(
@@ -192,6 +332,7 @@ impl Pipeline {
         let setter_pipeline = compute_shader::compute::create_cs_setter_pipeline(device);
         let move_pipeline = compute_shader::compute::create_cs_move_pipeline(device);
         let deposit_pipeline = compute_shader::compute::create_cs_deposit_pipeline(device);
+        let display_pipeline = compute_shader::compute::create_cs_display_pipeline(device);
         let diffusion_pipeline = compute_shader::compute::create_cs_diffusion_pipeline(device);
 
         let render_shader_module = render_shader::create_shader_module(device);
@@ -214,22 +355,57 @@ impl Pipeline {
             multiview: Default::default(),
             cache: Default::default(),
         });
-
-        let fbo_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
-            label: Some("fbo_sampler"),
-            address_mode_u: wgpu::AddressMode::Repeat,
-            address_mode_v: wgpu::AddressMode::Repeat,
-            address_mode_w: wgpu::AddressMode::ClampToEdge,
-            mag_filter: wgpu::FilterMode::Linear,
-            min_filter: wgpu::FilterMode::Linear,
-            mipmap_filter: wgpu::FilterMode::Nearest,
-            lod_min_clamp: 0.,
-            lod_max_clamp: 32.,
-            compare: None,
-            anisotropy_clamp: 1,
-            border_color: None,
+        let glow_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("glow pipeline"),
+            layout: Some(&render_pipeline_layout),
+            vertex: render_shader::vertex_state(&render_shader_module, &render_shader::vs_entry()),
+            fragment: Some(render_shader::fragment_state(
+                &render_shader_module,
+                &render_shader::fs_glow_entry([Some(wgpu::ColorTargetState {
+                    format: surface_format,
+                    // Light adds up, so the glow brightens whatever's below it
+                    blend: Some(wgpu::BlendState {
+                        color: wgpu::BlendComponent {
+                            src_factor: wgpu::BlendFactor::One,
+                            dst_factor: wgpu::BlendFactor::One,
+                            operation: wgpu::BlendOperation::Add,
+                        },
+                        alpha: wgpu::BlendComponent {
+                            src_factor: wgpu::BlendFactor::Zero,
+                            dst_factor: wgpu::BlendFactor::One,
+                            operation: wgpu::BlendOperation::Add,
+                        },
+                    }),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })]),
+            )),
+            primitive: Default::default(),
+            depth_stencil: Default::default(),
+            multisample: Default::default(),
+            multiview: Default::default(),
+            cache: Default::default(),
         });
 
+        let fbo_sampler = |label: &str, filter: wgpu::FilterMode| {
+            device.create_sampler(&wgpu::SamplerDescriptor {
+                label: Some(label),
+                // Match what the simulation does, so the edges blend correctly
+                address_mode_u: edges.address_mode(),
+                address_mode_v: edges.address_mode(),
+                address_mode_w: wgpu::AddressMode::ClampToEdge,
+                mag_filter: filter,
+                min_filter: filter,
+                mipmap_filter: wgpu::FilterMode::Nearest,
+                lod_min_clamp: 0.,
+                lod_max_clamp: 32.,
+                compare: None,
+                anisotropy_clamp: 1,
+                border_color: None,
+            })
+        };
+        let smooth_sampler = fbo_sampler("fbo_smooth_sampler", wgpu::FilterMode::Linear);
+        let pixelated_sampler = fbo_sampler("fbo_pixelated_sampler", wgpu::FilterMode::Nearest);
+
         let fbo_render_texture_view = texture_view(
             "fbo_render",
             &fbo_texture,
@@ -245,36 +421,147 @@ impl Pipeline {
         });
         // Set when screen is resized
 
-        let render_bind_group = render_shader::bind_groups::BindGroup0::from_bindings(
-            device,
-            render_shader::bind_groups::BindGroupLayout0 {
-                uni: render_uniforms_buffer.as_entire_buffer_binding(),
-                ourSampler: &fbo_sampler,
-                ourTexture: &fbo_render_texture_view,
-            },
+        let render_settings_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("render_settings"),
+            size: size_of::<render_shader::RenderSettings>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        // Written every frame, alongside the point settings
+
+        let background = background.filter(|image| {
+            let max = device.limits().max_texture_dimension_2d;
+            let fits = image.width <= max && image.height <= max;
+            if !fits {
+                eprintln!(
+                    "Background image is {}x{}, but this GPU can only handle up to {max}x{max}",
+                    image.width, image.height
+                );
+                eprintln!("Continuing without it...");
+            }
+            fits
+        });
+        let background_image_view = texture_view(
+            "background_image",
+            &background_texture(device, queue, background),
+            None,
+            wgpu::TextureUsages::TEXTURE_BINDING,
         );
+        let image_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("background_image_sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let render_bind_group = |sampler: &wgpu::Sampler| {
+            render_shader::bind_groups::BindGroup0::from_bindings(
+                device,
+                render_shader::bind_groups::BindGroupLayout0 {
+                    uni: render_uniforms_buffer.as_entire_buffer_binding(),
+                    render_settings: render_settings_buffer.as_entire_buffer_binding(),
+                    ourSampler: sampler,
+                    ourTexture: &fbo_render_texture_view,
+                    backgroundImage: &background_image_view,
+                    imageSampler: &image_sampler,
+                },
+            )
+        };
+        let smooth_render_bind_group = render_bind_group(&smooth_sampler);
+        let pixelated_render_bind_group = render_bind_group(&pixelated_sampler);
 
         Self {
+            constants,
+            constants_buffer,
             point_settings_buffer,
 
             constants_bind_group,
             trail_read_bind_group,
             trail_write_bind_group,
+            particle_params_buffer,
+            fbo_texture,
+            trail_read_texture,
+            trail_write_texture,
+            trail_in_write_texture: false,
+            frame: 0,
             state_bind_group,
 
             setter_pipeline,
             move_pipeline,
             deposit_pipeline,
+            display_pipeline,
             diffusion_pipeline,
 
             render_uniforms_buffer,
-            render_bind_group,
+            screen_size: PhysicalSize::new(0, 0),
+            area: camera_2d::DestinationRect::letterbox(PhysicalSize::new(0, 0), None),
+            header_height: HEADER_HEIGHT as f32,
+            quarter_turns: 0,
+            framing: Framing::default(),
+            background_size: background.map(|image| glam::uvec2(image.width, image.height)),
+            magnifying: false,
+            magnifier_focus: None,
+            render_settings_buffer,
+            smooth_render_bind_group,
+            pixelated_render_bind_group,
+            pixelated: false,
+            distribution: Distribution::Uniform,
+            next_bloom_particle: 0,
+            active_particles: SIMULATION_NUM_PARTICLES,
+            glow: None,
+            inverted: false,
             render_pipeline,
+            glow_pipeline,
         }
     }
 
-    pub fn resize(&mut self, queue: &wgpu::Queue, new_size: PhysicalSize<u32>) {
-        let render_uniforms = Self::calculate_uniforms(new_size);
+    pub fn resize(
+        &mut self,
+        queue: &wgpu::Queue,
+        new_size: PhysicalSize<u32>,
+        area: camera_2d::DestinationRect,
+        header_height: f32,
+    ) {
+        self.screen_size = new_size;
+        self.area = area;
+        self.header_height = header_height;
+        self.write_uniforms(queue);
+    }
+
+    /// Turns the display clockwise by this many quarter turns, without changing the simulation.
+    pub fn set_rotation(&mut self, queue: &wgpu::Queue, quarter_turns: u32) {
+        self.quarter_turns = quarter_turns % 4;
+        self.write_uniforms(queue);
+    }
+
+    pub fn rotation(&self) -> u32 {
+        self.quarter_turns
+    }
+
+    pub fn set_framing(&mut self, queue: &wgpu::Queue, framing: Framing) {
+        self.framing = framing;
+        self.write_uniforms(queue);
+    }
+
+    /// Zooms the display in around a point on the screen (or the middle, if there isn't one),
+    /// or back out again. The simulation itself carries on the same either way.
+    pub fn set_magnifier(
+        &mut self,
+        queue: &wgpu::Queue,
+        magnifying: bool,
+        focus: Option<PhysicalPosition<f64>>,
+    ) {
+        let focus = focus.map(|focus| glam::vec2(focus.x as f32, focus.y as f32));
+        if (self.magnifying, self.magnifier_focus) == (magnifying, focus) {
+            return;
+        }
+        self.magnifying = magnifying;
+        self.magnifier_focus = focus;
+        self.write_uniforms(queue);
+    }
+
+    fn write_uniforms(&self, queue: &wgpu::Queue) {
+        let render_uniforms = self.calculate_uniforms();
         queue.write_buffer(
             &self.render_uniforms_buffer,
             0,
@@ -282,70 +569,769 @@ impl Pipeline {
         );
     }
 
-    fn calculate_uniforms(size: PhysicalSize<u32>) -> render_shader::Uniforms {
-        camera_2d::Uniforms::source_to_screen(
+    fn calculate_uniforms(&self) -> render_shader::Uniforms {
+        let size = self.screen_size;
+        let quarter_turns = self.quarter_turns;
+        // When turned on its side, the simulation takes up space the other way around
+        let (width, height) = if quarter_turns % 2 == 1 {
+            (SIMULATION_HEIGHT, SIMULATION_WIDTH)
+        } else {
+            (SIMULATION_WIDTH, SIMULATION_HEIGHT)
+        };
+        let uniforms: render_shader::Uniforms = camera_2d::Uniforms::source_to_screen(
             size.into(),
             camera_2d::SourceRect {
-                width: SIMULATION_WIDTH as f32,
-                height: SIMULATION_HEIGHT as f32,
+                width: width as f32,
+                height: height as f32,
             },
             camera_2d::DestinationRect {
-                x: 0.0,
-                y: HEADER_HEIGHT as f32,
-                width: size.width as f32,
-                height: size.height.saturating_sub(HEADER_HEIGHT) as f32,
+                x: self.area.x,
+                y: self.area.y + self.header_height,
+                width: self.area.width,
+                height: (self.area.height - self.header_height).max(0.0),
+            },
+            match self.framing {
+                Framing::Cover => camera_2d::Mode::Cover,
+                Framing::Fit => camera_2d::Mode::Fit,
             },
-            camera_2d::Mode::Cover,
         )
-        .into()
+        .into();
+        let uniforms = if self.magnifying {
+            self.magnify(uniforms, glam::vec2(width as f32, height as f32))
+        } else {
+            uniforms
+        };
+        // The background image covers the space below the header, whichever way the simulation is
+        // turned or fitted into it
+        let (image_scale, image_offset) = match self.background_size {
+            Some(image_size) => {
+                let image_size = image_size.as_vec2();
+                let origin = glam::vec2(self.area.x, self.area.y + self.header_height);
+                let space = glam::vec2(
+                    self.area.width,
+                    (self.area.height - self.header_height).max(0.0),
+                );
+                let size = image_size * (space / image_size).max_element();
+                let origin = origin + 0.5 * (space - size);
+                (1.0 / size, -origin / size)
+            }
+            None => (glam::Vec2::ZERO, glam::Vec2::ZERO),
+        };
+        render_shader::Uniforms {
+            quarter_turns,
+            image_scale,
+            image_offset,
+            ..uniforms
+        }
+    }
+
+    /// Zooms the uniforms in around the magnifier's focus, which stays in the same place on
+    /// screen. The focus is kept over what's showing of the simulation, so it always fills the
+    /// same space as before.
+    fn magnify(
+        &self,
+        uniforms: render_shader::Uniforms,
+        turned_dims: glam::Vec2,
+    ) -> render_shader::Uniforms {
+        let screen_size = glam::vec2(
+            self.screen_size.width as f32,
+            self.screen_size.height as f32,
+        );
+        let to_clip =
+            |position: glam::Vec2| (2.0 * position / screen_size - 1.0) * glam::vec2(1.0, -1.0);
+        let drawn = [
+            uniforms.offset,
+            uniforms.offset + turned_dims * uniforms.scale,
+        ];
+        let bounds = [to_clip(uniforms.lower_bound), to_clip(uniforms.upper_bound)];
+        let lo = drawn[0].min(drawn[1]).max(bounds[0].min(bounds[1]));
+        let hi = drawn[0].max(drawn[1]).min(bounds[0].max(bounds[1]));
+        if !lo.cmple(hi).all() {
+            // Nothing's showing to zoom in on
+            return uniforms;
+        }
+        let focus = self
+            .magnifier_focus
+            .map_or((lo + hi) / 2.0, |focus| to_clip(focus).clamp(lo, hi));
+        render_shader::Uniforms {
+            scale: uniforms.scale * MAGNIFIER_ZOOM,
+            offset: (uniforms.offset - focus) * MAGNIFIER_ZOOM + focus,
+            ..uniforms
+        }
+    }
+
+    /// Where a point on the screen is in the simulation, in simulation pixels. `None` if the point
+    /// isn't over the simulation.
+    pub fn screen_to_simulation(&self, position: PhysicalPosition<f64>) -> Option<glam::Vec2> {
+        let uniforms = self.calculate_uniforms();
+        let position = glam::vec2(position.x as f32, position.y as f32);
+        if !(uniforms.lower_bound.cmple(position).all()
+            && position.cmple(uniforms.upper_bound).all())
+        {
+            return None;
+        }
+
+        // Undo what the render shader does: screen pixels -> clip space -> the rectangle being
+        // drawn -> texture coordinates
+        let screen_size = glam::vec2(
+            self.screen_size.width as f32,
+            self.screen_size.height as f32,
+        );
+        let clip = (2.0 * position / screen_size - 1.0) * glam::vec2(1.0, -1.0);
+        let dims = glam::vec2(SIMULATION_WIDTH as f32, SIMULATION_HEIGHT as f32);
+        let turned_dims = if self.quarter_turns % 2 == 1 {
+            glam::vec2(dims.y, dims.x)
+        } else {
+            dims
+        };
+        let xy = (clip - uniforms.offset) / (turned_dims * uniforms.scale);
+        let texcoord = match self.quarter_turns % 4 {
+            1 => glam::vec2(xy.y, 1.0 - xy.x),
+            2 => 1.0 - xy,
+            3 => glam::vec2(1.0 - xy.y, xy.x),
+            _ => xy,
+        };
+        // Fitting leaves bars beside the simulation that are still within the bounds
+        if !(texcoord.cmpge(glam::Vec2::ZERO).all() && texcoord.cmple(glam::Vec2::ONE).all()) {
+            return None;
+        }
+        Some(texcoord * dims)
+    }
+
+    /// Modifies the simulation constants & uploads them to the GPU.
+    pub fn update_constants(
+        &mut self,
+        queue: &wgpu::Queue,
+        f: impl FnOnce(&mut compute_shader::Constants),
+    ) {
+        f(&mut self.constants);
+        queue.write_buffer(
+            &self.constants_buffer,
+            0,
+            bytemuck::bytes_of(&self.constants),
+        );
+    }
+
+    pub fn set_display_source(&mut self, queue: &wgpu::Queue, source: DisplaySource) {
+        self.update_constants(queue, |constants| {
+            constants.count_blend = source.count_blend();
+        });
+    }
+
+    /// Shows or hides the heatmap of where particles are crowding together.
+    pub fn set_heatmap(&mut self, queue: &wgpu::Queue, heatmap: bool) {
+        self.update_constants(queue, |constants| constants.heatmap = u32::from(heatmap));
+    }
+
+    pub fn heatmap(&self) -> bool {
+        self.constants.heatmap != 0
+    }
+
+    pub fn set_count_scale(&mut self, queue: &wgpu::Queue, count_scale: f32) {
+        self.update_constants(queue, |constants| {
+            constants.count_scale = count_scale;
+        });
+    }
+
+    /// Makes particles respawn somewhere random after this many steps, or never if 0.
+    pub fn set_particle_lifetime(&mut self, queue: &wgpu::Queue, steps: u32) {
+        self.update_constants(queue, |constants| {
+            constants.respawn_rate = if steps == 0 { 0.0 } else { 1.0 / steps as f32 };
+        });
+    }
+
+    /// Changes where particles sense the trail. Only touches the GPU if something changed, since
+    /// this gets called every frame.
+    pub fn set_sensors(&mut self, queue: &wgpu::Queue, sensors: &Sensors) {
+        let count = sensors.count.clamp(1, MAX_SENSORS);
+        let layout = sensors.layout.index();
+        if self.constants.sensor_count == count && self.constants.sensor_layout == layout {
+            return;
+        }
+        self.update_constants(queue, |constants| {
+            constants.sensor_count = count;
+            constants.sensor_layout = layout;
+        });
+    }
+
+    /// Sets how far the trail blurs along each axis. Only touches the GPU if it changed.
+    pub fn set_diffusion(&mut self, queue: &wgpu::Queue, diffusion: &Diffusion) {
+        let x = diffusion.radius_x.min(MAX_DIFFUSION_RADIUS);
+        let y = diffusion.radius_y.min(MAX_DIFFUSION_RADIUS);
+        if self.constants.diffusion_radius_x == x && self.constants.diffusion_radius_y == y {
+            return;
+        }
+        self.update_constants(queue, |constants| {
+            constants.diffusion_radius_x = x;
+            constants.diffusion_radius_y = y;
+        });
+    }
+
+    /// Leans the simulation with the music's stereo balance, from -1 (all left) to 1 (all right).
+    pub fn set_stereo_bias(&mut self, queue: &wgpu::Queue, stereo_bias: f32) {
+        let stereo_bias = if stereo_bias.is_finite() {
+            stereo_bias.clamp(-1.0, 1.0)
+        } else {
+            0.0
+        };
+        if self.constants.stereo_bias == stereo_bias {
+            return;
+        }
+        self.update_constants(queue, |constants| constants.stereo_bias = stereo_bias);
+    }
+
+    /// Sets how far particles move each step, relative to what the point settings say.
+    pub fn set_move_speed(&mut self, queue: &wgpu::Queue, move_speed: f32) {
+        let (min, max) = MOVE_SPEED_RANGE;
+        // Also catches NaN from a hand-edited settings file
+        let move_speed = if move_speed.is_finite() {
+            move_speed.clamp(min, max)
+        } else {
+            1.0
+        };
+        if self.constants.move_speed == move_speed {
+            return;
+        }
+        self.update_constants(queue, |constants| constants.move_speed = move_speed);
+    }
+
+    /// Sets how strongly particles turn towards the trail, relative to what the point settings say.
+    pub fn set_turn_strength(&mut self, queue: &wgpu::Queue, turn_strength: f32) {
+        let (min, max) = TURN_STRENGTH_RANGE;
+        let turn_strength = if turn_strength.is_finite() {
+            turn_strength.clamp(min, max)
+        } else {
+            1.0
+        };
+        if self.constants.turn_strength == turn_strength {
+            return;
+        }
+        self.update_constants(queue, |constants| constants.turn_strength = turn_strength);
+    }
+
+    /// Sets how much trail each particle lays down, falling back to the default if it isn't a
+    /// number.
+    pub fn set_deposit_factor(&mut self, queue: &wgpu::Queue, deposit_factor: f32) {
+        let (min, max) = DEPOSIT_FACTOR_RANGE;
+        let deposit_factor = if deposit_factor.is_finite() {
+            deposit_factor.clamp(min, max)
+        } else {
+            CONSTANTS.deposit_factor
+        };
+        if self.constants.deposit_factor == deposit_factor {
+            return;
+        }
+        self.update_constants(queue, |constants| constants.deposit_factor = deposit_factor);
+    }
+
+    /// Sets how strongly particles steer away from crowded pixels, turning it off if it isn't a
+    /// number.
+    pub fn set_repulsion(&mut self, queue: &wgpu::Queue, repulsion: f32) {
+        let (min, max) = REPULSION_RANGE;
+        let repulsion = if repulsion.is_finite() {
+            repulsion.clamp(min, max)
+        } else {
+            CONSTANTS.repulsion
+        };
+        if self.constants.repulsion == repulsion {
+            return;
+        }
+        self.update_constants(queue, |constants| constants.repulsion = repulsion);
+    }
+
+    /// Decays the trail for how long the frame took, or by the usual amount each step if `None`.
+    pub fn set_decay_time(&mut self, queue: &wgpu::Queue, elapsed: Option<Duration>) {
+        let decay_factor = match elapsed {
+            Some(elapsed) => CONSTANTS.decay_factor.powf(
+                elapsed.min(MAX_DECAY_FRAME_TIME).as_secs_f32() / DECAY_FRAME_TIME.as_secs_f32(),
+            ),
+            None => CONSTANTS.decay_factor,
+        };
+        if self.constants.decay_factor == decay_factor {
+            return;
+        }
+        self.update_constants(queue, |constants| constants.decay_factor = decay_factor);
+    }
+
+    /// Changes how the trail gets colored. Only touches the GPU if it changed.
+    pub fn set_trail_colors(&mut self, queue: &wgpu::Queue, trail_colors: TrailColors) {
+        let trail_colors = trail_colors.index();
+        if self.constants.trail_colors == trail_colors {
+            return;
+        }
+        self.update_constants(queue, |constants| constants.trail_colors = trail_colors);
+    }
+
+    pub fn set_pixelated(&mut self, pixelated: bool) {
+        self.pixelated = pixelated;
+    }
+
+    pub fn pixelated(&self) -> bool {
+        self.pixelated
     }
 
     pub fn set_settings(&mut self, queue: &wgpu::Queue, settings: &PointSettings) {
         queue.write_buffer(&self.point_settings_buffer, 0, bytemuck::bytes_of(settings));
     }
 
-    pub fn compute_pass(&self, compute_pass: &mut wgpu::ComputePass) {
-        compute_pass.set_pipeline(&self.setter_pipeline);
+    /// Sets the linear color the trail is multiplied with, the one that shows through behind it
+    /// (premultiplied by how strongly), & how the trail's brightness gets mapped before either.
+    pub fn set_render_settings(
+        &mut self,
+        queue: &wgpu::Queue,
+        tint: glam::Vec4,
+        background: glam::Vec4,
+        exposure: f32,
+        gamma: f32,
+    ) {
+        let glow = self.glow.unwrap_or(Glow {
+            threshold: 1.0,
+            intensity: 0.0,
+        });
+        let settings = render_shader::RenderSettings {
+            tint,
+            background,
+            glow_threshold: glow.threshold,
+            glow_intensity: glow.intensity,
+            exposure,
+            gamma,
+            invert: self.inverted.into(),
+            _padding1: 0,
+            _padding2: 0,
+            _padding3: 0,
+        };
+        queue.write_buffer(
+            &self.render_settings_buffer,
+            0,
+            bytemuck::bytes_of(&settings),
+        );
+    }
+
+    /// Only moves the first `count` particles from now on, rounded up to whole workgroups (but never
+    /// past all of them), for measuring how many this GPU can keep up with. The rest stay where
+    /// they are.
+    pub fn set_active_particles(&mut self, count: usize) {
+        let group = compute_shader::PARTICLE_WORK_GROUP_SIZE as usize;
+        self.active_particles =
+            (count.div_ceil(group).max(1) * group).min(SIMULATION_NUM_PARTICLES);
+    }
+
+    pub fn active_particles(&self) -> usize {
+        self.active_particles
+    }
+
+    /// Flips the colors to their opposites, or back. Takes effect the next time the render
+    /// settings are set.
+    pub fn set_inverted(&mut self, inverted: bool) {
+        self.inverted = inverted;
+    }
+
+    /// Turns the glow on with the given look, or off with `None`.
+    pub fn set_glow(&mut self, glow: Option<Glow>) {
+        self.glow = glow;
+    }
+
+    pub fn distribution(&self) -> Distribution {
+        self.distribution
+    }
+
+    /// Sets where particles start out the next time they're reset.
+    pub fn set_distribution(&mut self, distribution: Distribution) {
+        self.distribution = distribution;
+    }
+
+    /// Scatters the particles randomly again & clears the trail, for when the simulation has gotten
+    /// itself into a state it won't come back from.
+    pub fn reset(&mut self, queue: &wgpu::Queue) {
+        queue.write_buffer(
+            &self.particle_params_buffer,
+            0,
+            bytemuck::cast_slice(random_particles(self.distribution).as_slice()),
+        );
+        let zeros = vec![[0f32; 4]; (SIMULATION_WIDTH * SIMULATION_HEIGHT) as usize];
+        for texture in [&self.trail_read_texture, &self.trail_write_texture] {
+            queue.write_texture(
+                texture.as_image_copy(),
+                bytemuck::cast_slice(zeros.as_slice()),
+                wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(SIMULATION_WIDTH * TRAIL_TEXEL_SIZE),
+                    rows_per_image: None,
+                },
+                texture.size(),
+            );
+        }
+    }
+
+    /// Respawns some particles clumped around a random spot, heading outwards so they bloom out of
+    /// it. Goes round the particles in turn rather than adding any, so the ones respawned longest
+    /// ago are the next to go.
+    pub fn spawn_bloom(&mut self, queue: &wgpu::Queue, count: usize) {
+        let count = count.min(SIMULATION_NUM_PARTICLES);
+        let mut rng = rand::rng();
+        let size = glam::vec2(SIMULATION_WIDTH as f32, SIMULATION_HEIGHT as f32);
+        let center = glam::vec2(rng.random_range(0.0..size.x), rng.random_range(0.0..size.y));
+        let mut particles = Vec::with_capacity(count * 4);
+        for _ in 0..count {
+            // Gaussian distance from the middle, by way of Box-Muller
+            let distance = BLOOM_SPREAD * (-2.0 * (1.0 - rng.random::<f32>()).ln()).sqrt();
+            let angle = rng.random_range(0.0..std::f32::consts::TAU);
+            let position = (center + distance * glam::Vec2::from_angle(angle)).rem_euclid(size);
+            particles.extend(pack_particle(position, angle, &mut rng));
+        }
+
+        // Split in two wherever it runs off the end of the buffer
+        let first = count.min(SIMULATION_NUM_PARTICLES - self.next_bloom_particle);
+        let (wrapped, rest) = particles.split_at(first * 4);
+        queue.write_buffer(
+            &self.particle_params_buffer,
+            self.next_bloom_particle as u64 * PARTICLE_SIZE,
+            bytemuck::cast_slice(wrapped),
+        );
+        if !rest.is_empty() {
+            queue.write_buffer(&self.particle_params_buffer, 0, bytemuck::cast_slice(rest));
+        }
+        self.next_bloom_particle = (self.next_bloom_particle + count) % SIMULATION_NUM_PARTICLES;
+    }
+
+    /// The texture holding the most recent trail.
+    pub fn current_trail_texture(&self) -> &wgpu::Texture {
+        if self.trail_in_write_texture {
+            &self.trail_write_texture
+        } else {
+            &self.trail_read_texture
+        }
+    }
+
+    /// Everything that has to be saved to pick the simulation back up where it was.
+    pub fn live(&self) -> rewind::Live<'_> {
+        rewind::Live {
+            particles: &self.particle_params_buffer,
+            trail: self.current_trail_texture(),
+            display: &self.fbo_texture,
+        }
+    }
+
+    /// The bind group that reads the most recent trail, and writes to the other texture.
+    fn current_trail_bind_group(&self) -> &compute_shader::bind_groups::BindGroup2 {
+        if self.trail_in_write_texture {
+            &self.trail_write_bind_group
+        } else {
+            &self.trail_read_bind_group
+        }
+    }
+
+    pub fn compute_pass(
+        &mut self,
+        compute_pass: &mut wgpu::ComputePass,
+        steps: &Steps,
+        brush: &brush::Brush,
+    ) {
         self.constants_bind_group.set(compute_pass);
         self.state_bind_group.set(compute_pass);
-        self.trail_read_bind_group.set(compute_pass);
-        compute_pass.dispatch_workgroups(
-            SIMULATION_WIDTH / SIMULATION_WORK_GROUP_SIZE,
-            SIMULATION_HEIGHT / SIMULATION_WORK_GROUP_SIZE,
-            1,
-        );
+        self.current_trail_bind_group().set(compute_pass);
+        // Before anything reads the painted settings, so they're up to date
+        brush.compute_pass(compute_pass);
+
+        compute_pass.set_pipeline(&self.setter_pipeline);
+        compute_pass.dispatch_workgroups(PIXEL_WORKGROUPS.0, PIXEL_WORKGROUPS.1, 1);
 
         compute_pass.set_pipeline(&self.move_pipeline);
         // bind groups are the same
         compute_pass.dispatch_workgroups(
-            (SIMULATION_NUM_PARTICLES
-                / (SIMULATION_WORK_GROUP_SIZE * SIMULATION_WORK_GROUP_SIZE) as usize)
-                as u32,
+            self.active_particles
+                .div_ceil(compute_shader::PARTICLE_WORK_GROUP_SIZE as usize) as u32,
             1,
             1,
         );
 
-        compute_pass.set_pipeline(&self.deposit_pipeline);
-        // bind groups are the same
-        compute_pass.dispatch_workgroups(
-            SIMULATION_WIDTH / SIMULATION_WORK_GROUP_SIZE,
-            SIMULATION_HEIGHT / SIMULATION_WORK_GROUP_SIZE,
-            1,
-        );
+        // Each pass that writes to the trail flips which texture has the most recent trail, so we
+        // always need to re-bind before the next one.
+        if self
+            .frame
+            .is_multiple_of(u64::from(steps.deposit_interval.max(1)))
+        {
+            compute_pass.set_pipeline(&self.deposit_pipeline);
+            // bind groups are the same
+            compute_pass.dispatch_workgroups(PIXEL_WORKGROUPS.0, PIXEL_WORKGROUPS.1, 1);
+            self.trail_in_write_texture = !self.trail_in_write_texture;
+        } else {
+            // The display still needs updating with where the particles have moved to
+            compute_pass.set_pipeline(&self.display_pipeline);
+            // bind groups are the same
+            compute_pass.dispatch_workgroups(PIXEL_WORKGROUPS.0, PIXEL_WORKGROUPS.1, 1);
+        }
 
         compute_pass.set_pipeline(&self.diffusion_pipeline);
-        self.trail_write_bind_group.set(compute_pass);
-        // other bind groups are the same
-        compute_pass.dispatch_workgroups(
-            SIMULATION_WIDTH / SIMULATION_WORK_GROUP_SIZE,
-            SIMULATION_HEIGHT / SIMULATION_WORK_GROUP_SIZE,
-            1,
-        );
+        for _ in 0..steps.diffusion_passes.min(MAX_DIFFUSION_PASSES) {
+            self.current_trail_bind_group().set(compute_pass);
+            // other bind groups are the same
+            compute_pass.dispatch_workgroups(PIXEL_WORKGROUPS.0, PIXEL_WORKGROUPS.1, 1);
+            self.trail_in_write_texture = !self.trail_in_write_texture;
+        }
+
+        self.frame += 1;
     }
 
     pub fn render_pass(&self, render_pass: &mut wgpu::RenderPass) {
         render_pass.set_pipeline(&self.render_pipeline);
-        self.render_bind_group.set(render_pass);
+        if self.pixelated {
+            self.pixelated_render_bind_group.set(render_pass);
+        } else {
+            self.smooth_render_bind_group.set(render_pass);
+        }
         render_pass.draw(0..6, 0..1);
+        if self.glow.is_some() {
+            // Same bind group as before
+            render_pass.set_pipeline(&self.glow_pipeline);
+            render_pass.draw(0..6, 0..1);
+        }
+    }
+}
+
+/// The image shown behind the simulation, or a single transparent pixel if there isn't one.
+fn background_texture(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    image: Option<&Image>,
+) -> wgpu::Texture {
+    let (width, height, rgba) = match image {
+        Some(image) => (image.width, image.height, &image.rgba[..]),
+        None => (1, 1, &[0; 4][..]),
+    };
+    let size = wgpu::Extent3d {
+        width,
+        height,
+        depth_or_array_layers: 1,
+    };
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("background_image_texture"),
+        size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        // Images are stored in sRGB, and this turns them linear when sampled
+        format: wgpu::TextureFormat::Rgba8UnormSrgb,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        view_formats: &[],
+    });
+    queue.write_texture(
+        texture.as_image_copy(),
+        rgba,
+        wgpu::TexelCopyBufferLayout {
+            offset: 0,
+            bytes_per_row: Some(width * 4),
+            rows_per_image: None,
+        },
+        size,
+    );
+    texture
+}
+
+/// What the trail is stored as: the trail particles sense, then how much of it is in each color
+/// channel.
+pub const TRAIL_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba32Float;
+/// Bytes per pixel of `TRAIL_FORMAT`.
+pub const TRAIL_TEXEL_SIZE: u32 = 4 * size_of::<f32>() as u32;
+
+/// Bytes per particle: the shader reads two `u32`s for each one, position then respawn progress &
+/// heading, each packing two unorm16s.
+const PARTICLE_SIZE: u64 = 2 * size_of::<u32>() as u64;
+
+/// How many workgroups it takes to cover every pixel of the simulation. Rounded up, so sizes that
+/// aren't a multiple of the workgroup size still get their last row & column; the shaders skip
+/// whatever hangs over the edge.
+const PIXEL_WORKGROUPS: (u32, u32) = (
+    SIMULATION_WIDTH.div_ceil(SIMULATION_WORK_GROUP_SIZE),
+    SIMULATION_HEIGHT.div_ceil(SIMULATION_WORK_GROUP_SIZE),
+);
+
+/// The least a GPU needs to support to run the simulation: wgpu's downlevel defaults, raised
+/// wherever the simulation's sizes need more than those.
+pub fn required_limits() -> wgpu::Limits {
+    let base = wgpu::Limits::downlevel_defaults();
+    let particle_bytes = SIMULATION_NUM_PARTICLES as u64 * PARTICLE_SIZE;
+    let particle_workgroups =
+        (SIMULATION_NUM_PARTICLES as u32).div_ceil(compute_shader::PARTICLE_WORK_GROUP_SIZE);
+    // Every shader's workgroups has to fit, not just the biggest one overall
+    let workgroup_sizes = {
+        use compute_shader::compute::*;
+        [
+            CS_SETTER_WORKGROUP_SIZE,
+            CS_BRUSH_WORKGROUP_SIZE,
+            CS_MOVE_WORKGROUP_SIZE,
+            CS_DEPOSIT_WORKGROUP_SIZE,
+            CS_DIFFUSION_WORKGROUP_SIZE,
+            CS_DISPLAY_WORKGROUP_SIZE,
+        ]
+    };
+    let largest = |axis: usize| workgroup_sizes.iter().map(|size| size[axis]).max();
+    let invocations = workgroup_sizes
+        .iter()
+        .map(|size| size.iter().product())
+        .max()
+        .unwrap_or(1);
+    wgpu::Limits {
+        max_texture_dimension_2d: base
+            .max_texture_dimension_2d
+            .max(SIMULATION_WIDTH.max(SIMULATION_HEIGHT)),
+        max_storage_buffer_binding_size: base
+            .max_storage_buffer_binding_size
+            .max(particle_bytes as u32),
+        max_buffer_size: base.max_buffer_size.max(particle_bytes),
+        max_compute_invocations_per_workgroup: base
+            .max_compute_invocations_per_workgroup
+            .max(invocations),
+        max_compute_workgroup_size_x: base
+            .max_compute_workgroup_size_x
+            .max(largest(0).unwrap_or(1)),
+        max_compute_workgroup_size_y: base
+            .max_compute_workgroup_size_y
+            .max(largest(1).unwrap_or(1)),
+        max_compute_workgroup_size_z: base
+            .max_compute_workgroup_size_z
+            .max(largest(2).unwrap_or(1)),
+        max_compute_workgroups_per_dimension: base
+            .max_compute_workgroups_per_dimension
+            .max(particle_workgroups),
+        ..base
+    }
+}
+
+/// Where particles start out when the simulation is started or reset.
+#[derive(Copy, Clone, Debug)]
+pub enum Distribution {
+    /// Evenly spread over the whole simulation, heading every which way.
+    Uniform,
+    /// Clumped up in the middle, heading outwards.
+    Center,
+    /// In a ring around the middle, heading inwards.
+    Ring,
+    /// On the points of an evenly spaced grid, heading every which way.
+    Grid,
+}
+
+impl Distribution {
+    pub const ALL: [Self; 4] = [Self::Uniform, Self::Center, Self::Ring, Self::Grid];
+
+    pub fn next(self) -> Self {
+        match self {
+            Self::Uniform => Self::Center,
+            Self::Center => Self::Ring,
+            Self::Ring => Self::Grid,
+            Self::Grid => Self::Uniform,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Uniform => "uniform",
+            Self::Center => "center",
+            Self::Ring => "ring",
+            Self::Grid => "grid",
+        }
+    }
+
+    /// A random starting position (in simulation pixels) & heading (in radians) for one particle.
+    fn sample(self, rng: &mut impl Rng) -> (glam::Vec2, f32) {
+        use std::f32::consts::TAU;
+        let size = glam::vec2(SIMULATION_WIDTH as f32, SIMULATION_HEIGHT as f32);
+        let center = size / 2.0;
+        let random_heading = rng.random_range(0.0..TAU);
+        match self {
+            Self::Uniform => (
+                glam::vec2(rng.random_range(0.0..size.x), rng.random_range(0.0..size.y)),
+                random_heading,
+            ),
+            Self::Center => {
+                // Gaussian distance from the middle, by way of Box-Muller
+                let spread = size.y / 8.0;
+                let distance = spread * (-2.0 * (1.0 - rng.random::<f32>()).ln()).sqrt();
+                let angle = rng.random_range(0.0..TAU);
+                let position = center + distance * glam::Vec2::from_angle(angle);
+                (position.clamp(glam::Vec2::ZERO, size - 1.0), angle)
+            }
+            Self::Ring => {
+                let radius = size.y * rng.random_range(0.33..0.37);
+                let angle = rng.random_range(0.0..TAU);
+                let position = center + radius * glam::Vec2::from_angle(angle);
+                (position, (angle + TAU / 2.0) % TAU)
+            }
+            Self::Grid => {
+                const SPACING: u32 = 32;
+                let x = rng.random_range(0..SIMULATION_WIDTH / SPACING) * SPACING + SPACING / 2;
+                let y = rng.random_range(0..SIMULATION_HEIGHT / SPACING) * SPACING + SPACING / 2;
+                (glam::vec2(x as f32, y as f32), random_heading)
+            }
+        }
+    }
+}
+
+impl std::str::FromStr for Distribution {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|distribution| distribution.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                let names: Vec<_> = Self::ALL.iter().map(|d| d.name()).collect();
+                format!("expected one of {}", names.join(", "))
+            })
+    }
+}
+
+/// Random starting positions and headings for all the particles, packed the way the shader
+/// expects them.
+fn random_particles(distribution: Distribution) -> Vec<u16> {
+    let mut rng = rand::rng();
+    let mut particles = Vec::with_capacity(SIMULATION_NUM_PARTICLES * 4);
+    for _ in 0..SIMULATION_NUM_PARTICLES {
+        let (position, heading) = distribution.sample(&mut rng);
+        particles.extend(pack_particle(position, heading, &mut rng));
+    }
+    particles
+}
+
+/// Packs a particle at this position (in simulation pixels) & heading (in radians) the way the
+/// shader expects it.
+fn pack_particle(position: glam::Vec2, heading: f32, rng: &mut impl Rng) -> [u16; 4] {
+    fn float_as_u16(f: f32) -> u16 {
+        (f.clamp(0., 1.) * 65535.).round() as u16
+    }
+    let size = glam::vec2(SIMULATION_WIDTH as f32, SIMULATION_HEIGHT as f32);
+    let position = position / size;
+    [
+        float_as_u16(position.x),
+        float_as_u16(position.y),
+        // How far along the particle is to respawning
+        float_as_u16(rng.random()),
+        float_as_u16(heading.rem_euclid(std::f32::consts::TAU) / std::f32::consts::TAU),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+
+    #[test]
+    fn distributions_stay_inside_the_simulation() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+        let size = glam::vec2(SIMULATION_WIDTH as f32, SIMULATION_HEIGHT as f32);
+        for distribution in Distribution::ALL {
+            for _ in 0..100_000 {
+                let (position, heading) = distribution.sample(&mut rng);
+                assert!(
+                    position.cmpge(glam::Vec2::ZERO).all() && position.cmplt(size).all(),
+                    "{distribution:?} put a particle at {position}"
+                );
+                assert!(
+                    heading.is_finite(),
+                    "{distribution:?} gave heading {heading}"
+                );
+            }
+        }
     }
 }
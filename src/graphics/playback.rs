@@ -20,8 +20,8 @@ use crate::graphics::geometry_2d::VertexBuffer;
 use crate::graphics::geometry_2d::make_circle;
 use crate::graphics::geometry_2d::make_line;
 use crate::graphics::geometry_2d::vertex_buffer_from_geometry;
-use crate::graphics::text::COLOR_WHITE;
-use crate::graphics::text::FONT_SIZE;
+use crate::graphics::text::{COLOR_WHITE, Theme};
+use crate::graphics::text::{FONT_SIZE, set_font_size};
 use crate::shaders::{pipelines, tris_render_shader as render_shader};
 
 const POSITION_HEIGHT: u32 = 6;
@@ -32,6 +32,7 @@ pub struct Pipeline {
     playing: bool,
     /// Text for the position indicator
     section: OwnedSection,
+    font_size: f32,
 
     /// Uniforms for the play/pause indicator.
     render_uniforms_buffer_play: wgpu::Buffer,
@@ -48,6 +49,10 @@ pub struct Pipeline {
     /// The offsets to apply to the position geometry. It contains things type glam::Vec2, and has
     /// length 2.
     offset_buffer: wgpu::Buffer,
+    /// The colors of the indicators, which has length 2 too.
+    color_buffer: wgpu::Buffer,
+    /// The color of the position text.
+    color: [f32; 4],
 
     /// Bind group for the play/pause indicator
     bind_group_play: render_shader::bind_groups::BindGroup0,
@@ -166,12 +171,15 @@ impl Pipeline {
             section: Section::default()
                 .with_layout(Layout::default_wrap().v_align(VerticalAlign::Bottom))
                 .to_owned(),
+            font_size: FONT_SIZE,
             render_uniforms_buffer_play,
             render_uniforms_buffer_position,
             vertex_buffer_play,
             vertex_buffer_pause,
             vertex_buffer_position,
             offset_buffer,
+            color_buffer,
+            color: COLOR_WHITE,
             bind_group_play,
             bind_group_position,
         }
@@ -181,8 +189,14 @@ impl Pipeline {
         &self.section
     }
 
-    pub fn resize(&mut self, queue: &wgpu::Queue, size: PhysicalSize<u32>) {
-        let x = (size.width - FFT_WIDTH - PLAYBACK_WIDTH) as f32;
+    pub fn resize(
+        &mut self,
+        queue: &wgpu::Queue,
+        size: PhysicalSize<u32>,
+        frame: camera_2d::DestinationRect,
+        scale: f32,
+    ) {
+        let x = frame.x + (frame.width - (FFT_WIDTH + PLAYBACK_WIDTH) as f32 * scale).max(0.0);
 
         let play_uniforms: render_shader::Uniforms = camera_2d::Uniforms::source_to_screen(
             size.into(),
@@ -192,9 +206,9 @@ impl Pipeline {
             },
             camera_2d::DestinationRect {
                 x,
-                y: 0.0,
-                width: PLAY_HEIGHT as f32,
-                height: PLAY_HEIGHT as f32,
+                y: frame.y,
+                width: PLAY_HEIGHT as f32 * scale,
+                height: PLAY_HEIGHT as f32 * scale,
             },
             camera_2d::Mode::Fit,
         )
@@ -213,9 +227,9 @@ impl Pipeline {
             },
             camera_2d::DestinationRect {
                 x,
-                y: (PLAY_HEIGHT + 3) as f32,
-                width: PLAYBACK_WIDTH as f32,
-                height: POSITION_HEIGHT as f32,
+                y: frame.y + (PLAY_HEIGHT + 3) as f32 * scale,
+                width: PLAYBACK_WIDTH as f32 * scale,
+                height: POSITION_HEIGHT as f32 * scale,
             },
             camera_2d::Mode::Fit,
         )
@@ -226,8 +240,21 @@ impl Pipeline {
             bytemuck::bytes_of(&position_uniforms),
         );
 
-        self.section.screen_position = (x + PLAY_HEIGHT as f32, PLAY_HEIGHT as f32);
-        self.section.bounds = (PLAYBACK_WIDTH as f32, PLAY_HEIGHT as f32);
+        let play_height = PLAY_HEIGHT as f32 * scale;
+        self.section.screen_position = (x + play_height, frame.y + play_height);
+        self.section.bounds = (PLAYBACK_WIDTH as f32 * scale, play_height);
+        self.font_size = FONT_SIZE * scale;
+        set_font_size(&mut self.section, self.font_size);
+    }
+
+    /// Draws the text & indicators in the theme's text color.
+    pub fn set_theme(&mut self, queue: &wgpu::Queue, theme: Theme) {
+        queue.write_buffer(
+            &self.color_buffer,
+            0,
+            bytemuck::cast_slice(&[theme.text, theme.text]),
+        );
+        self.color = theme.text;
     }
 
     pub fn set_playing(&mut self, playing: bool) {
@@ -246,8 +273,8 @@ impl Pipeline {
                     position.as_secs() / 60,
                     position.as_secs_f32() % 60.0
                 ))
-                .with_scale(FONT_SIZE)
-                .with_color(COLOR_WHITE),
+                .with_scale(self.font_size)
+                .with_color(self.color),
         );
 
         let frac = position.as_secs_f32() / total_duration.as_secs_f32();
@@ -3,28 +3,37 @@ use wgpu_text::glyph_brush::Layout;
 use wgpu_text::glyph_brush::OwnedSection;
 use wgpu_text::glyph_brush::OwnedText;
 use wgpu_text::glyph_brush::Section;
-use winit::dpi::PhysicalSize;
 
 use crate::constants::PLAYBACK_WIDTH;
 use crate::constants::{FFT_WIDTH, HEADER_HEIGHT};
-use crate::graphics::text::COLOR_GREEN;
-use crate::graphics::text::{COLOR_WHITE, FONT_SIZE};
+use crate::graphics::camera_2d::DestinationRect;
+use crate::graphics::text::{FONT_SIZE, Theme, set_font_size};
 
 pub struct Text {
     section: OwnedSection,
+    font_size: f32,
+    /// Whether a GIF is being recorded, which shows up next to the preset.
+    recording: bool,
+    theme: Theme,
 }
 
 pub enum PresetMode {
     Normal,
     Dirty,
+    /// Has staged edits that haven't been applied yet.
+    Pending,
     Selecting,
+    /// Waiting for F9 to be pressed again to delete the preset.
+    ConfirmingDelete,
 }
 
 impl PresetMode {
-    fn color(&self) -> [f32; 4] {
+    fn color(&self, theme: &Theme) -> [f32; 4] {
         match self {
-            PresetMode::Normal | PresetMode::Dirty => COLOR_WHITE,
-            PresetMode::Selecting => COLOR_GREEN,
+            PresetMode::Normal | PresetMode::Dirty => theme.text,
+            PresetMode::Pending => theme.fft_highlight,
+            PresetMode::Selecting => theme.highlight,
+            PresetMode::ConfirmingDelete => theme.fft_text,
         }
     }
 }
@@ -35,34 +44,60 @@ impl Text {
             section: Section::default()
                 .with_layout(Layout::default_wrap().h_align(HorizontalAlign::Right))
                 .to_owned(),
+            font_size: FONT_SIZE,
+            recording: false,
+            theme: Theme::default(),
         }
     }
 
+    /// Takes effect on the next `update()`.
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+    }
+
     pub fn section(&self) -> &OwnedSection {
         &self.section
     }
 
-    pub fn resize(&mut self, new_size: PhysicalSize<u32>) {
-        self.section.bounds = (PLAYBACK_WIDTH as f32, HEADER_HEIGHT as f32);
-        self.section.screen_position = ((new_size.width - FFT_WIDTH) as f32, 0.0);
+    pub fn resize(&mut self, frame: DestinationRect, scale: f32) {
+        self.section.bounds = (PLAYBACK_WIDTH as f32 * scale, HEADER_HEIGHT as f32 * scale);
+        self.section.screen_position = (
+            frame.x + (frame.width - FFT_WIDTH as f32 * scale).max(0.0),
+            frame.y,
+        );
+        self.font_size = FONT_SIZE * scale;
+        set_font_size(&mut self.section, self.font_size);
+    }
+
+    /// Takes effect on the next `update()`.
+    pub fn set_recording(&mut self, recording: bool) {
+        self.recording = recording;
     }
 
     pub fn update(&mut self, index: usize, mode: PresetMode) {
         let text = format!(
             "{}{}",
-            if matches!(mode, PresetMode::Dirty) {
-                "*"
-            } else {
-                ""
+            match mode {
+                PresetMode::Dirty | PresetMode::Pending => "*",
+                PresetMode::ConfirmingDelete => "F9 again to delete ",
+                PresetMode::Normal | PresetMode::Selecting => "",
             },
             index + 1
         );
         self.section.text.clear();
+        if self.recording {
+            self.section.text.push(
+                OwnedText::default()
+                    .with_text("REC ")
+                    .with_scale(self.font_size)
+                    .with_color(self.theme.fft_text),
+            );
+        }
         self.section.text.push(
             OwnedText::default()
                 .with_text(text)
-                .with_scale(FONT_SIZE)
-                .with_color(mode.color()),
+                .with_scale(self.font_size)
+                .with_color(mode.color(&self.theme)),
         );
     }
 }
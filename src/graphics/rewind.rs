@@ -0,0 +1,186 @@
+//! Keeps a ring of snapshots of the simulation, so it can be stepped backwards through past
+//! states. While rewound, the simulation is frozen on the snapshot being shown; resuming carries
+//! on from that snapshot, forgetting about everything that came after it.
+//!
+//! Each snapshot holds every particle as well as the trail, so they aren't small. That's why
+//! there's a cap on how many can be kept, and why none are kept unless asked for.
+
+use std::collections::VecDeque;
+
+use web_time::{Duration, Instant};
+
+use crate::constants::*;
+use crate::graphics::physarum::TRAIL_FORMAT;
+
+/// Upper limit on the number of snapshots, since each one is about 65MB of GPU memory: 8 bytes for
+/// every particle, then 16 bytes a pixel for the `Rgba32Float` trail & 4 for the display.
+pub const MAX_SNAPSHOTS: usize = 16;
+
+/// The parts of the simulation that need saving to be able to pick up from where it was.
+pub struct Live<'a> {
+    pub particles: &'a wgpu::Buffer,
+    pub trail: &'a wgpu::Texture,
+    pub display: &'a wgpu::Texture,
+}
+
+struct Snapshot {
+    particles: wgpu::Buffer,
+    trail: wgpu::Texture,
+    display: wgpu::Texture,
+}
+
+pub struct Rewind {
+    /// How many snapshots to keep. 0 turns rewinding off.
+    capacity: usize,
+    /// How long between snapshots.
+    interval: Duration,
+    /// When the most recent snapshot was taken.
+    last_snapshot: Instant,
+    /// Oldest first.
+    snapshots: VecDeque<Snapshot>,
+    /// Which snapshot is being shown, if we're rewound.
+    position: Option<usize>,
+    /// Whether the snapshot at `position` still needs to be copied into the simulation.
+    restore_pending: bool,
+}
+
+impl Rewind {
+    pub fn new(capacity: usize, interval: Duration) -> Self {
+        Self {
+            capacity: capacity.min(MAX_SNAPSHOTS),
+            interval,
+            last_snapshot: Instant::now(),
+            snapshots: VecDeque::new(),
+            position: None,
+            restore_pending: false,
+        }
+    }
+
+    /// Whether the simulation should be frozen on a past state.
+    pub fn is_rewound(&self) -> bool {
+        self.position.is_some()
+    }
+
+    /// Shows the snapshot before the current one. Returns whether the key was handled.
+    pub fn step_back(&mut self) -> bool {
+        if self.snapshots.is_empty() {
+            return false;
+        }
+        let position = match self.position {
+            Some(position) => position.saturating_sub(1),
+            None => self.snapshots.len() - 1,
+        };
+        self.position = Some(position);
+        self.restore_pending = true;
+        true
+    }
+
+    /// Shows the snapshot after the current one. Past the most recent snapshot, resumes the
+    /// simulation. Returns whether the key was handled.
+    pub fn step_forward(&mut self) -> bool {
+        let Some(position) = self.position else {
+            return false;
+        };
+        if position + 1 < self.snapshots.len() {
+            self.position = Some(position + 1);
+            self.restore_pending = true;
+        } else {
+            self.resume();
+        }
+        true
+    }
+
+    /// Carries on simulating from the snapshot being shown.
+    pub fn resume(&mut self) {
+        if let Some(position) = self.position.take() {
+            // Everything after this point didn't happen any more
+            self.snapshots.truncate(position + 1);
+            self.last_snapshot = Instant::now();
+        }
+    }
+
+    /// Takes a snapshot of the simulation if it's time, or copies the snapshot being shown into
+    /// the simulation if one was just picked.
+    pub fn update(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        live: Live,
+    ) {
+        if let Some(position) = self.position {
+            if self.restore_pending {
+                let snapshot = &self.snapshots[position];
+                encoder.copy_buffer_to_buffer(&snapshot.particles, 0, live.particles, 0, None);
+                encoder.copy_texture_to_texture(
+                    snapshot.trail.as_image_copy(),
+                    live.trail.as_image_copy(),
+                    snapshot.trail.size(),
+                );
+                encoder.copy_texture_to_texture(
+                    snapshot.display.as_image_copy(),
+                    live.display.as_image_copy(),
+                    snapshot.display.size(),
+                );
+                self.restore_pending = false;
+            }
+            return;
+        }
+
+        if self.capacity == 0 || self.last_snapshot.elapsed() < self.interval {
+            return;
+        }
+        self.last_snapshot = Instant::now();
+
+        // Once we're full, reuse the oldest snapshot instead of making a new one
+        let snapshot = if self.snapshots.len() >= self.capacity {
+            self.snapshots.pop_front().expect("capacity is non-zero")
+        } else {
+            Snapshot::new(device, live.particles.size())
+        };
+        encoder.copy_buffer_to_buffer(live.particles, 0, &snapshot.particles, 0, None);
+        encoder.copy_texture_to_texture(
+            live.trail.as_image_copy(),
+            snapshot.trail.as_image_copy(),
+            live.trail.size(),
+        );
+        encoder.copy_texture_to_texture(
+            live.display.as_image_copy(),
+            snapshot.display.as_image_copy(),
+            live.display.size(),
+        );
+        self.snapshots.push_back(snapshot);
+    }
+}
+
+impl Snapshot {
+    fn new(device: &wgpu::Device, particles_size: u64) -> Self {
+        let particles = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("snapshot_particles_buffer"),
+            size: particles_size,
+            usage: wgpu::BufferUsages::COPY_SRC | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let texture = |label: &str, format: wgpu::TextureFormat| {
+            device.create_texture(&wgpu::TextureDescriptor {
+                label: Some(&format!("snapshot_{label}_texture")),
+                size: wgpu::Extent3d {
+                    width: SIMULATION_WIDTH,
+                    height: SIMULATION_HEIGHT,
+                    depth_or_array_layers: 1,
+                },
+                format,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                usage: wgpu::TextureUsages::COPY_SRC | wgpu::TextureUsages::COPY_DST,
+                view_formats: &[],
+            })
+        };
+
+        Self {
+            particles,
+            trail: texture("trail", TRAIL_FORMAT),
+            display: texture("display", wgpu::TextureFormat::Rgba8Unorm),
+        }
+    }
+}
@@ -0,0 +1,48 @@
+//! Slowly morphs between randomly generated settings while nobody's around, so the simulation
+//! keeps changing when it's left running unattended. Only the base point settings get morphed,
+//! & into a copy of their own, so no preset is ever touched.
+
+use web_time::{Duration, Instant};
+
+use crate::fs::point_settings::PointSettings;
+use crate::fs::settings::{Randomizer, Settings};
+
+pub struct Screensaver {
+    /// What the morph currently under way started from.
+    from: PointSettings,
+    /// What it's heading towards.
+    to: PointSettings,
+    started: Instant,
+    /// How long each morph takes.
+    morph_time: Duration,
+}
+
+impl Screensaver {
+    /// Starts morphing away from `current` towards some random settings.
+    pub fn start(
+        current: PointSettings,
+        randomizer: &Randomizer,
+        morph_time: Duration,
+        now: Instant,
+    ) -> Self {
+        Self {
+            from: current,
+            to: Settings::random(randomizer).base.current,
+            started: now,
+            morph_time,
+        }
+    }
+
+    /// Where the morph has got to, picking new settings to head to whenever one finishes.
+    pub fn update(&mut self, randomizer: &Randomizer, now: Instant) -> PointSettings {
+        let mut t = now.duration_since(self.started).as_secs_f32() / self.morph_time.as_secs_f32();
+        if t >= 1.0 {
+            self.from = std::mem::replace(&mut self.to, Settings::random(randomizer).base.current);
+            self.started = now;
+            t = 0.0;
+        }
+        // Eases in & out, so it lingers a little on each set of settings instead of bouncing off
+        let t = t * t * (3.0 - 2.0 * t);
+        self.from.clone() * (1.0 - t) + self.to.clone() * t
+    }
+}
@@ -2,16 +2,18 @@ use wgpu_text::glyph_brush::Layout;
 use wgpu_text::glyph_brush::OwnedSection;
 use wgpu_text::glyph_brush::OwnedText;
 use wgpu_text::glyph_brush::Section;
-use winit::dpi::PhysicalSize;
 
+use crate::audio::{FrequencyRange, NUM_BINS};
 use crate::constants::{FFT_WIDTH, HEADER_HEIGHT, PLAYBACK_WIDTH};
-use crate::fs::{point_settings::PointSettings, settings::DisplaySettings, settings::Param};
+use crate::fs::point_settings::PointSettings;
+use crate::fs::settings::{DisplaySettings, Param, Settings};
 use crate::graphics::Mode;
-use crate::graphics::text::COLOR_GREEN;
-use crate::graphics::text::COLOR_RED;
-use crate::graphics::text::COLOR_WHITE;
-use crate::graphics::text::COLOR_YELLOW;
-use crate::graphics::text::FONT_SIZE;
+use crate::graphics::camera_2d::DestinationRect;
+use crate::graphics::text::{FONT_SIZE, Theme, set_font_size};
+
+/// Six columns have to fit beside the playback & FFT on a 1920 wide window, so the settings are a
+/// bit smaller than the rest of the header text.
+const SETTINGS_FONT_SCALE: f32 = 0.85;
 
 pub struct Text {
     section: OwnedSection,
@@ -19,6 +21,8 @@ pub struct Text {
     highlighted_index: Option<usize>,
     /// What overall "mode" we are in
     mode: TextMode,
+    font_size: f32,
+    theme: Theme,
 }
 
 #[derive(Copy, Clone)]
@@ -28,22 +32,27 @@ enum TextMode {
 }
 
 impl TextMode {
-    fn normal_color(&self) -> [f32; 4] {
+    fn normal_color(&self, theme: &Theme) -> [f32; 4] {
         match self {
-            Self::Base => COLOR_WHITE,
-            Self::Fft => COLOR_RED,
+            Self::Base => theme.text,
+            Self::Fft => theme.fft_text,
         }
     }
 
-    fn highlight_color(&self) -> [f32; 4] {
+    fn highlight_color(&self, theme: &Theme) -> [f32; 4] {
         match self {
-            Self::Base => COLOR_GREEN,
-            Self::Fft => COLOR_YELLOW,
+            Self::Base => theme.highlight,
+            Self::Fft => theme.fft_highlight,
         }
     }
 }
 
-fn format_display_settings(display_settings: &DisplaySettings) -> [String; 15] {
+/// Formats each setting as its value & how much a keypress changes it by, or as its value & what
+/// it resolves to once the music has been mixed in, if `resolved` is given.
+fn format_display_settings(
+    display_settings: &DisplaySettings,
+    resolved: Option<&PointSettings>,
+) -> [String; 15] {
     let PointSettings {
         sd0,
         sde,
@@ -81,52 +90,146 @@ fn format_display_settings(display_settings: &DisplaySettings) -> [String; 15] {
 
     const WIDTH: usize = 8;
     const PREC: usize = 3;
+    if let Some(PointSettings {
+        sd0: sd0_live,
+        sde: sde_live,
+        sda: sda_live,
+        sa0: sa0_live,
+        sae: sae_live,
+        saa: saa_live,
+        ra0: ra0_live,
+        rae: rae_live,
+        raa: raa_live,
+        md0: md0_live,
+        mde: mde_live,
+        mda: mda_live,
+        dsf: dsf_live,
+        sb1: sb1_live,
+        sb2: sb2_live,
+    }) = resolved
+    {
+        // Fits in the same space as the increment
+        return [
+            format!("SD0:{sd0:>WIDTH$.PREC$}→{sd0_live:<7.PREC$}  "),
+            format!("SA0:{sa0:>WIDTH$.PREC$}→{sa0_live:<7.PREC$}  "),
+            format!("RA0:{ra0:>WIDTH$.PREC$}→{ra0_live:<7.PREC$}  "),
+            format!("MD0:{md0:>WIDTH$.PREC$}→{md0_live:<7.PREC$}  "),
+            format!("DSF:{dsf:>WIDTH$.PREC$}→{dsf_live:<7.PREC$}  "),
+            format!("SDA:{sda:>WIDTH$.PREC$}→{sda_live:<7.PREC$}  "),
+            format!("SAA:{saa:>WIDTH$.PREC$}→{saa_live:<7.PREC$}  "),
+            format!("RAA:{raa:>WIDTH$.PREC$}→{raa_live:<7.PREC$}  "),
+            format!("MDA:{mda:>WIDTH$.PREC$}→{mda_live:<7.PREC$}  "),
+            format!("SB1:{sb1:>WIDTH$.PREC$}→{sb1_live:<7.PREC$}  "),
+            format!("SDE:{sde:>WIDTH$.PREC$}→{sde_live:<7.PREC$}  "),
+            format!("SAE:{sae:>WIDTH$.PREC$}→{sae_live:<7.PREC$}  "),
+            format!("RAE:{rae:>WIDTH$.PREC$}→{rae_live:<7.PREC$}  "),
+            format!("MDE:{mde:>WIDTH$.PREC$}→{mde_live:<7.PREC$}  "),
+            format!("SB2:{sb2:>WIDTH$.PREC$}→{sb2_live:<7.PREC$}  "),
+        ];
+    }
     [
         format!("SD0:{sd0:>WIDTH$.PREC$}({sd0_incr:+.PREC$})  "),
         format!("SA0:{sa0:>WIDTH$.PREC$}({sa0_incr:+.PREC$})  "),
         format!("RA0:{ra0:>WIDTH$.PREC$}({ra0_incr:+.PREC$})  "),
         format!("MD0:{md0:>WIDTH$.PREC$}({md0_incr:+.PREC$})  "),
-        format!("DSF:{dsf:>WIDTH$.PREC$}({dsf_incr:+.PREC$})\n"),
+        format!("DSF:{dsf:>WIDTH$.PREC$}({dsf_incr:+.PREC$})  "),
         format!("SDA:{sda:>WIDTH$.PREC$}({sda_incr:+.PREC$})  "),
         format!("SAA:{saa:>WIDTH$.PREC$}({saa_incr:+.PREC$})  "),
         format!("RAA:{raa:>WIDTH$.PREC$}({raa_incr:+.PREC$})  "),
         format!("MDA:{mda:>WIDTH$.PREC$}({mda_incr:+.PREC$})  "),
-        format!("SB1:{sb1:>WIDTH$.PREC$}({sb1_incr:+.PREC$})\n"),
+        format!("SB1:{sb1:>WIDTH$.PREC$}({sb1_incr:+.PREC$})  "),
         format!("SDE:{sde:>WIDTH$.PREC$}({sde_incr:+.PREC$})  "),
         format!("SAE:{sae:>WIDTH$.PREC$}({sae_incr:+.PREC$})  "),
         format!("RAE:{rae:>WIDTH$.PREC$}({rae_incr:+.PREC$})  "),
         format!("MDE:{mde:>WIDTH$.PREC$}({mde_incr:+.PREC$})  "),
-        format!("SB2:{sb2:>WIDTH$.PREC$}({sb2_incr:+.PREC$})\n"),
+        format!("SB2:{sb2:>WIDTH$.PREC$}({sb2_incr:+.PREC$})  "),
     ]
 }
 
-/// Calculate the highlighted_index given the current active param.
-fn param_to_index(param: Param) -> usize {
+/// Formats the settings that apply to the whole preset rather than to each param, one for the end
+/// of each row.
+fn format_preset_settings(settings: &Settings) -> [String; 3] {
+    const WIDTH: usize = 8;
+    const PREC: usize = 3;
+    [
+        format!("MOV:{:>WIDTH$.PREC$}\n", settings.move_speed),
+        format!("TRN:{:>WIDTH$.PREC$}\n", settings.turn_strength),
+        format!("DEP:{:>WIDTH$.4}\n", settings.deposit_factor),
+    ]
+}
+
+fn format_frequency_ranges(ranges: &[FrequencyRange; NUM_BINS]) -> Vec<String> {
+    // Labelled with the keys that select each one, two to a row
+    const KEYS: [char; NUM_BINS] = ['Y', 'U', 'I', 'O', 'P'];
+    ranges
+        .iter()
+        .zip(KEYS)
+        .enumerate()
+        .map(|(i, (FrequencyRange { lo, hi }, key))| {
+            let end = if i % 2 == 1 { "\n" } else { "  " };
+            format!("{key}:{lo:>7.1}-{hi:<7.1}Hz{end}")
+        })
+        .collect()
+}
+
+/// Every param, in the order they're shown: along the top row, then the next, then the last.
+const PARAM_ORDER: [Param; 15] = {
     use Param::*;
-    match param {
-        SDBase => 0,
-        SABase => 1,
-        RABase => 2,
-        MDBase => 3,
-        DefaultScalingFactor => 4,
-        SDAmplitude => 5,
-        SAAmplitude => 6,
-        RAAmplitude => 7,
-        MDAmplitude => 8,
-        SensorBias1 => 9,
-        SDExponent => 10,
-        SAExponent => 11,
-        RAExponent => 12,
-        MDExponent => 13,
-        SensorBias2 => 14,
-    }
+    [
+        SDBase,
+        SABase,
+        RABase,
+        MDBase,
+        DefaultScalingFactor,
+        SDAmplitude,
+        SAAmplitude,
+        RAAmplitude,
+        MDAmplitude,
+        SensorBias1,
+        SDExponent,
+        SAExponent,
+        RAExponent,
+        MDExponent,
+        SensorBias2,
+    ]
+};
+
+/// How many params are shown along each row, before the preset's own setting at the end of it.
+const ROW_LEN: usize = 5;
+
+fn param_to_index(param: Param) -> usize {
+    PARAM_ORDER
+        .iter()
+        .position(|&p| p == param)
+        .expect("every param is shown")
+}
+
+/// Calculate the highlighted_index given the current active param, skipping over the preset's
+/// settings at the end of each row before it.
+fn param_to_text_index(param: Param) -> usize {
+    let index = param_to_index(param);
+    index + index / ROW_LEN
+}
+
+/// The param shown after this one (or before, if not `forwards`), wrapping around at the ends.
+/// With no param, starts from whichever end it'd wrap around to.
+pub fn step_param(param: Option<Param>, forwards: bool) -> Param {
+    let len = PARAM_ORDER.len();
+    let index = match (param, forwards) {
+        (Some(param), true) => (param_to_index(param) + 1) % len,
+        (Some(param), false) => (param_to_index(param) + len - 1) % len,
+        (None, true) => 0,
+        (None, false) => len - 1,
+    };
+    PARAM_ORDER[index]
 }
 
 /// Calculate the highlighted_index given the current mode.
 fn mode_to_index(mode: Mode) -> Option<usize> {
     match mode {
-        Mode::Base(param) => Some(param_to_index(param)),
-        Mode::Fft { param, index: _ } => param.map(param_to_index),
+        Mode::Base(param) => Some(param_to_text_index(param)),
+        Mode::Fft { param, index: _ } => param.map(param_to_text_index),
+        Mode::Bands { index } => Some(index.0),
         _ => None,
     }
 }
@@ -139,39 +242,67 @@ impl Text {
                 .to_owned(),
             highlighted_index: None,
             mode: TextMode::Base,
+            font_size: FONT_SIZE * SETTINGS_FONT_SCALE,
+            theme: Theme::default(),
         }
     }
 
+    /// Takes effect the next time the text is set.
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+    }
+
     pub fn section(&self) -> &OwnedSection {
         &self.section
     }
 
-    pub fn resize(&mut self, new_size: PhysicalSize<u32>) {
+    pub fn resize(&mut self, frame: DestinationRect, scale: f32) {
         self.section.bounds = (
-            (new_size.width - PLAYBACK_WIDTH - FFT_WIDTH) as f32,
-            HEADER_HEIGHT as f32,
+            (frame.width - (PLAYBACK_WIDTH + FFT_WIDTH) as f32 * scale).max(0.0),
+            HEADER_HEIGHT as f32 * scale,
         );
-        self.section.screen_position = (0.0, 0.0);
+        self.section.screen_position = (frame.x, frame.y);
+        self.font_size = FONT_SIZE * SETTINGS_FONT_SCALE * scale;
+        set_font_size(&mut self.section, self.font_size);
+    }
+
+    /// Shows the settings, along with what they resolve to once the music is mixed in if
+    /// `resolved` is given, and the rest of the `preset`'s settings at the end of each row.
+    pub fn set_settings(
+        &mut self,
+        settings: &DisplaySettings,
+        resolved: Option<&PointSettings>,
+        preset: &Settings,
+    ) {
+        let params = format_display_settings(settings, resolved);
+        let texts = params
+            .chunks(ROW_LEN)
+            .zip(format_preset_settings(preset))
+            .flat_map(|(row, end)| row.iter().cloned().chain([end]))
+            .collect::<Vec<_>>();
+        self.set_texts(texts);
     }
 
-    pub fn set_settings(&mut self, settings: &DisplaySettings) {
+    /// Shows the edges of each band instead of any settings, for while they're being edited.
+    pub fn set_frequency_ranges(&mut self, ranges: &[FrequencyRange; NUM_BINS]) {
+        self.set_texts(format_frequency_ranges(ranges));
+    }
+
+    fn set_texts(&mut self, texts: impl IntoIterator<Item = String>) {
         let mode = self.mode;
         self.section.text.clear();
-        self.section.text.extend(
-            format_display_settings(settings)
-                .into_iter()
-                .enumerate()
-                .map(|(i, text)| {
-                    OwnedText::default()
-                        .with_text(text)
-                        .with_scale(FONT_SIZE)
-                        .with_color(if Some(i) == self.highlighted_index {
-                            mode.highlight_color()
-                        } else {
-                            mode.normal_color()
-                        })
-                }),
-        );
+        self.section
+            .text
+            .extend(texts.into_iter().enumerate().map(|(i, text)| {
+                OwnedText::default()
+                    .with_text(text)
+                    .with_scale(self.font_size)
+                    .with_color(if Some(i) == self.highlighted_index {
+                        mode.highlight_color(&self.theme)
+                    } else {
+                        mode.normal_color(&self.theme)
+                    })
+            }));
     }
 
     pub fn set_mode(&mut self, mode: Mode) {
@@ -180,15 +311,18 @@ impl Text {
 
         self.mode = mode.into();
 
-        if let Some(i) = prev_highlighted_index {
-            self.section.text[i] = self.section.text[i]
-                .clone()
-                .with_color(self.mode.normal_color());
+        // Switching between settings & band edges changes what's showing, so the indices may not
+        // line up until the text is set again
+        if let Some(text) = prev_highlighted_index.and_then(|i| self.section.text.get_mut(i)) {
+            *text = text.clone().with_color(self.mode.normal_color(&self.theme));
         }
-        if let Some(i) = self.highlighted_index {
-            self.section.text[i] = self.section.text[i]
+        if let Some(text) = self
+            .highlighted_index
+            .and_then(|i| self.section.text.get_mut(i))
+        {
+            *text = text
                 .clone()
-                .with_color(self.mode.highlight_color());
+                .with_color(self.mode.highlight_color(&self.theme));
         }
     }
 }
@@ -196,7 +330,9 @@ impl Text {
 impl From<Mode> for TextMode {
     fn from(mode: Mode) -> Self {
         match mode {
-            Mode::Normal | Mode::EnteringNumber(_) | Mode::Base(_) => Self::Base,
+            Mode::Normal | Mode::EnteringNumber(_) | Mode::Base(_) | Mode::Bands { .. } => {
+                Self::Base
+            }
             Mode::Fft { .. } => Self::Fft,
         }
     }
@@ -0,0 +1,78 @@
+//! Smooths out each FFT bin on its own, so different bands can react to the music at different
+//! speeds.
+
+use web_time::Instant;
+
+use crate::audio::NUM_BINS;
+use crate::fs::settings::BinSmoothing;
+
+/// Where each bin has got to, kept alongside the bins rather than in the settings since it's
+/// changing all the time.
+#[derive(Default)]
+pub struct Smoothing {
+    /// In the same order as the bins, & the presets' `fft` & `smoothing`.
+    levels: [f32; NUM_BINS],
+    /// `None` until we've seen some music.
+    last_update: Option<Instant>,
+}
+
+impl Smoothing {
+    /// Moves each bin towards its newest level at the pace its settings ask for, & says where
+    /// they've all got to.
+    pub fn update(
+        &mut self,
+        bins: &[f32; NUM_BINS],
+        settings: &[BinSmoothing; NUM_BINS],
+        now: Instant,
+    ) -> [f32; NUM_BINS] {
+        let Some(elapsed) = self.last_update.replace(now).map(|last| now - last) else {
+            self.levels = *bins;
+            return self.levels;
+        };
+        for ((level, bin), settings) in self.levels.iter_mut().zip(bins).zip(settings) {
+            let BinSmoothing {
+                attack_ms,
+                release_ms,
+            } = *settings;
+            let time_ms = if bin > level { attack_ms } else { release_ms };
+            // Exponential smoothing that doesn't depend on how often we're called
+            let alpha = if time_ms.is_finite() && time_ms > 0.0 {
+                1.0 - f32::exp(-elapsed.as_secs_f32() * 1000.0 / time_ms)
+            } else {
+                1.0
+            };
+            *level += (bin - *level) * alpha;
+        }
+        self.levels
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use web_time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn each_bin_follows_at_its_own_pace() {
+        let mut settings = [BinSmoothing::default(); NUM_BINS];
+        settings[1] = BinSmoothing {
+            attack_ms: 100.0,
+            release_ms: 0.0,
+        };
+        settings[2] = BinSmoothing {
+            attack_ms: 0.0,
+            release_ms: 100.0,
+        };
+        let start = Instant::now();
+        let mut smoothing = Smoothing::default();
+        smoothing.update(&[0.0, 0.0, 1.0, 0.0, 0.0], &settings, start);
+
+        let after = start + Duration::from_millis(100);
+        let levels = smoothing.update(&[1.0, 1.0, 0.0, 0.0, 0.0], &settings, after);
+        // Unsmoothed bins jump straight there, smoothed ones get about 63% of the way
+        assert_eq!(levels[0], 1.0);
+        assert!((levels[1] - 0.632).abs() < 0.01, "{levels:?}");
+        assert!((levels[2] - 0.368).abs() < 0.01, "{levels:?}");
+    }
+}
@@ -0,0 +1,201 @@
+//! This module displays a small graph of the parameter currently being changed, as the music
+//! actually moves it, over the last couple of seconds.
+
+use std::collections::VecDeque;
+
+use winit::dpi::PhysicalSize;
+
+use crate::constants::{FFT_WIDTH, HEADER_HEIGHT, PLAYBACK_WIDTH, SPARKLINE_WIDTH};
+use crate::graphics::camera_2d;
+use crate::graphics::geometry_2d::{
+    ToVertices, VertexBuffer, make_line, vertex_buffer_from_geometry,
+};
+use crate::shaders::{pipelines, tris_render_shader as render_shader};
+
+/// Number of frames of history to show.
+const HISTORY_LEN: usize = 120;
+/// Space to leave above & below the graph.
+const MARGIN: f32 = 6.0;
+
+pub struct Pipeline {
+    /// Whether to show the graph at all.
+    enabled: bool,
+    /// The most recent values, oldest first. Empty when there's no parameter to show.
+    history: VecDeque<f32>,
+
+    render_uniforms_buffer: wgpu::Buffer,
+
+    /// A flat line with `HISTORY_LEN` points, which get moved up & down by `offset_buffer`.
+    vertex_buffer: VertexBuffer,
+    /// The colors of each point. Contains things of type glam::Vec4, and has length HISTORY_LEN.
+    color_buffer: wgpu::Buffer,
+    /// The offsets of each point. Contains things of type glam::Vec2, and has length
+    /// HISTORY_LEN.
+    offset_buffer: wgpu::Buffer,
+
+    render_bind_group: render_shader::bind_groups::BindGroup0,
+}
+
+impl Pipeline {
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        surface_format: wgpu::TextureFormat,
+    ) -> Self {
+        pipelines::initialize(device, surface_format);
+
+        const STEP: f32 = SPARKLINE_WIDTH as f32 / (HISTORY_LEN - 1) as f32;
+        let vertex_buffer = vertex_buffer_from_geometry(
+            device,
+            queue,
+            "sparkline vertex buffer",
+            (1..HISTORY_LEN).flat_map(|i| {
+                let start = glam::vec2(STEP * (i - 1) as f32, 0.0);
+                let end = glam::vec2(STEP * i as f32, 0.0);
+                make_line(start, end, 2.0).to_vertices((i as u32 - 1, i as u32))
+            }),
+        );
+
+        let color_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("sparkline color buffer"),
+            size: (size_of::<glam::Vec4>() * HISTORY_LEN) as u64,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        // filled in during each set_color()
+
+        let offset_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("sparkline offset buffer"),
+            size: (size_of::<glam::Vec2>() * HISTORY_LEN) as u64,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        // filled in during each prepare()
+
+        let render_uniforms_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("sparkline render uniforms"),
+            size: size_of::<render_shader::Uniforms>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        // Filled in during each resize()
+
+        let render_bind_group = render_shader::bind_groups::BindGroup0::from_bindings(
+            device,
+            render_shader::bind_groups::BindGroupLayout0 {
+                colors: color_buffer.as_entire_buffer_binding(),
+                offsets: offset_buffer.as_entire_buffer_binding(),
+                uni: render_uniforms_buffer.as_entire_buffer_binding(),
+            },
+        );
+
+        Self {
+            enabled: true,
+            history: VecDeque::with_capacity(HISTORY_LEN),
+            render_uniforms_buffer,
+            vertex_buffer,
+            color_buffer,
+            offset_buffer,
+            render_bind_group,
+        }
+    }
+
+    pub fn resize(
+        &mut self,
+        queue: &wgpu::Queue,
+        new_size: PhysicalSize<u32>,
+        frame: camera_2d::DestinationRect,
+        scale: f32,
+    ) {
+        let render_uniforms: render_shader::Uniforms = camera_2d::Uniforms::source_to_screen(
+            new_size.into(),
+            camera_2d::SourceRect {
+                width: SPARKLINE_WIDTH as f32,
+                height: HEADER_HEIGHT as f32,
+            },
+            // just to the left of the playback indicators
+            camera_2d::DestinationRect {
+                x: frame.x
+                    + (frame.width - (FFT_WIDTH + PLAYBACK_WIDTH + SPARKLINE_WIDTH) as f32 * scale)
+                        .max(0.0),
+                y: frame.y,
+                width: SPARKLINE_WIDTH as f32 * scale,
+                height: HEADER_HEIGHT as f32 * scale,
+            },
+            camera_2d::Mode::Fit,
+        )
+        .into();
+        queue.write_buffer(
+            &self.render_uniforms_buffer,
+            0,
+            bytemuck::bytes_of(&render_uniforms),
+        );
+    }
+
+    pub fn toggle(&mut self) {
+        self.enabled = !self.enabled;
+    }
+
+    /// Changes which parameter is being shown, forgetting the history of the old one.
+    pub fn set_color(&mut self, queue: &wgpu::Queue, color: [f32; 4]) {
+        self.history.clear();
+        let color_data = [glam::Vec4::from(color); HISTORY_LEN];
+        queue.write_buffer(&self.color_buffer, 0, bytemuck::cast_slice(&color_data[..]));
+    }
+
+    /// Adds the parameter's value this frame to the graph, or hides it if there isn't one.
+    pub fn prepare(&mut self, queue: &wgpu::Queue, value: Option<f32>) {
+        let value = match value {
+            Some(value) => value,
+            None => {
+                self.history.clear();
+                return;
+            }
+        };
+        if self.history.len() == HISTORY_LEN {
+            self.history.pop_front();
+        }
+        self.history.push_back(value);
+
+        let (min, max) = self
+            .history
+            .iter()
+            .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), v| {
+                (min.min(*v), max.max(*v))
+            });
+        let range = max - min;
+        let height = HEADER_HEIGHT as f32 - 2.0 * MARGIN;
+        let y = |v: f32| {
+            if range > f32::EPSILON {
+                // Screen y goes down, so higher values are closer to 0
+                MARGIN + height * (max - v) / range
+            } else {
+                HEADER_HEIGHT as f32 / 2.0
+            }
+        };
+
+        // Until we have a full history, stretch the oldest value back to the start of the graph
+        let oldest = self.history[0];
+        let offset_data: Vec<glam::Vec2> =
+            std::iter::repeat_n(oldest, HISTORY_LEN - self.history.len())
+                .chain(self.history.iter().copied())
+                .map(|v| glam::vec2(0.0, y(v)))
+                .collect();
+        queue.write_buffer(
+            &self.offset_buffer,
+            0,
+            bytemuck::cast_slice(&offset_data[..]),
+        );
+    }
+
+    pub fn render_pass(&self, render_pass: &mut wgpu::RenderPass) {
+        if !self.enabled || self.history.is_empty() {
+            return;
+        }
+        pipelines::render_tris(render_pass);
+
+        render_shader::set_bind_groups(render_pass, &self.render_bind_group);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.buffer.slice(..));
+        render_pass.draw(0..self.vertex_buffer.num_vertices as u32, 0..1);
+    }
+}
@@ -0,0 +1,87 @@
+use std::time::Duration;
+
+use wgpu_text::glyph_brush::Layout;
+use wgpu_text::glyph_brush::OwnedSection;
+use wgpu_text::glyph_brush::OwnedText;
+use wgpu_text::glyph_brush::Section;
+
+use crate::graphics::camera_2d::DestinationRect;
+use crate::graphics::text::{FONT_SIZE, Theme, set_font_size};
+
+/// The stats are a single line tucked into the corner of the simulation, so they're smaller than
+/// the header text.
+const STATS_FONT_SCALE: f32 = 0.75;
+
+/// Frame pacing & particle stats, drawn over the top-left corner of the simulation.
+pub struct Text {
+    section: OwnedSection,
+    font_size: f32,
+    /// Whether they're being shown at all.
+    shown: bool,
+    theme: Theme,
+}
+
+impl Text {
+    pub fn new() -> Self {
+        Self {
+            section: Section::default()
+                .with_layout(Layout::default_single_line())
+                .to_owned(),
+            font_size: FONT_SIZE * STATS_FONT_SCALE,
+            shown: false,
+            theme: Theme::default(),
+        }
+    }
+
+    /// Takes effect on the next `update()`.
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+    }
+
+    pub fn is_shown(&self) -> bool {
+        self.shown
+    }
+
+    /// Takes effect on the next `update()`.
+    pub fn set_shown(&mut self, shown: bool) {
+        self.shown = shown;
+        if !shown {
+            self.section.text.clear();
+        }
+    }
+
+    pub fn section(&self) -> &OwnedSection {
+        &self.section
+    }
+
+    /// The stats go just under the header, which is `header_height` tall.
+    pub fn resize(&mut self, frame: DestinationRect, scale: f32, header_height: f32) {
+        self.section.screen_position = (frame.x, frame.y + header_height);
+        self.font_size = FONT_SIZE * STATS_FONT_SCALE * scale;
+        set_font_size(&mut self.section, self.font_size);
+    }
+
+    pub fn update(
+        &mut self,
+        fps: f32,
+        dropped: u32,
+        frames: u32,
+        longest: Duration,
+        particles: usize,
+    ) {
+        if !self.shown {
+            return;
+        }
+        let text = format!(
+            "FPS: {fps:.1}, dropped {dropped}/{frames}, longest {:.1}ms, {particles} particles",
+            longest.as_secs_f32() * 1000.0,
+        );
+        self.section.text.clear();
+        self.section.text.push(
+            OwnedText::default()
+                .with_text(text)
+                .with_scale(self.font_size)
+                .with_color(self.theme.text),
+        );
+    }
+}
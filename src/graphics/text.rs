@@ -1,41 +1,112 @@
 //! Common utilities shared among pipelines that need to render text
 
+use std::borrow::Cow;
 use std::sync::LazyLock;
 
-use font_kit::family_name::FamilyName;
-use font_kit::handle::Handle;
-use font_kit::properties::Properties;
-use font_kit::source::SystemSource;
 use wgpu_text::BrushBuilder;
 use wgpu_text::TextBrush;
+use wgpu_text::glyph_brush::OwnedSection;
 use wgpu_text::glyph_brush::Section;
 use wgpu_text::glyph_brush::ab_glyph::FontRef;
 use winit::dpi::PhysicalSize;
 
 use crate::constants::HEADER_HEIGHT;
+use crate::platform::{Current, Platform};
 
 pub static MONOSPACE_FONT: LazyLock<FontRef> = LazyLock::new(|| {
-    let font_handle = SystemSource::new()
-        .select_best_match(&[FamilyName::Monospace], &Properties::new())
-        .expect("Did not find system monospace font");
-    let font_vec = match font_handle {
-        Handle::Memory { bytes, .. } => Vec::clone(&bytes),
-        Handle::Path { path, .. } => std::fs::read(path).expect("failed to read font file"),
-    };
-    FontRef::try_from_slice(font_vec.leak()).expect("invalid font")
+    FontRef::try_from_slice(Current::monospace_font().leak()).expect("invalid font")
 });
 
 /// We display 3 rows of text, so fill out the header completely.
 pub const FONT_SIZE: f32 = HEADER_HEIGHT as f32 / 3.0;
 
+/// Sets the size of all the text in a section, for when the UI scale changes.
+pub fn set_font_size(section: &mut OwnedSection, font_size: f32) {
+    for text in &mut section.text {
+        text.scale = font_size.into();
+    }
+}
+
 pub const COLOR_WHITE: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
 pub const COLOR_RED: [f32; 4] = [1.0, 0.0, 0.0, 1.0];
 pub const COLOR_GREEN: [f32; 4] = [0.0, 1.0, 0.0, 1.0];
 pub const COLOR_YELLOW: [f32; 4] = [1.0, 1.0, 0.0, 1.0];
 
+/// The drop shadow drawn behind text, if it's turned on, so it stands out over bright parts of the
+/// simulation.
+const SHADOW_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.8];
+/// How far down & to the right the shadow is, relative to the size of the text.
+const SHADOW_OFFSET: f32 = 0.08;
+
+/// How different (as a distance between RGB colors) text & highlighted text need to be, so it's
+/// still clear what's highlighted.
+const MIN_HIGHLIGHT_DISTANCE: f32 = 0.3;
+
+/// The colors the header text is drawn in, as linear RGBA. Any left out of the settings file are
+/// the usual ones.
+#[derive(Debug, Clone, facet::Facet)]
+pub struct Theme {
+    /// Most of the text.
+    #[facet(default = COLOR_WHITE)]
+    pub text: [f32; 4],
+    /// The parameter being changed, & the preset number being typed in.
+    #[facet(default = COLOR_GREEN)]
+    pub highlight: [f32; 4],
+    /// The settings while a band is selected, & the recording indicator.
+    #[facet(default = COLOR_RED)]
+    pub fft_text: [f32; 4],
+    /// The parameter being changed for a band, & the preset number while edits are staged.
+    #[facet(default = COLOR_YELLOW)]
+    pub fft_highlight: [f32; 4],
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            text: COLOR_WHITE,
+            highlight: COLOR_GREEN,
+            fft_text: COLOR_RED,
+            fft_highlight: COLOR_YELLOW,
+        }
+    }
+}
+
+impl Theme {
+    /// Puts back the usual colors for any text & highlight too close to tell apart.
+    pub fn validated(self) -> Self {
+        let default = Self::default();
+        let distinct = |a: [f32; 4], b: [f32; 4]| {
+            glam::Vec3::from_slice(&a).distance(glam::Vec3::from_slice(&b))
+                >= MIN_HIGHLIGHT_DISTANCE
+        };
+        let (text, highlight) = if distinct(self.text, self.highlight) {
+            (self.text, self.highlight)
+        } else {
+            eprintln!("Theme's text & highlight colors are too close to tell apart");
+            eprintln!("Falling back to the default colors for them...");
+            (default.text, default.highlight)
+        };
+        let (fft_text, fft_highlight) = if distinct(self.fft_text, self.fft_highlight) {
+            (self.fft_text, self.fft_highlight)
+        } else {
+            eprintln!("Theme's FFT text & highlight colors are too close to tell apart");
+            eprintln!("Falling back to the default colors for them...");
+            (default.fft_text, default.fft_highlight)
+        };
+        Self {
+            text,
+            highlight,
+            fft_text,
+            fft_highlight,
+        }
+    }
+}
+
 /// Pipeline that helps render all the other text-including pipelines.
 pub struct Pipeline {
     brush: TextBrush<FontRef<'static>>,
+    /// Whether text gets a drop shadow.
+    shadow: bool,
 }
 
 impl Pipeline {
@@ -47,7 +118,15 @@ impl Pipeline {
         let brush_builder = BrushBuilder::using_font((*MONOSPACE_FONT).clone());
         let brush = brush_builder.build(device, size.width, size.height, surface_format);
 
-        Self { brush }
+        Self {
+            brush,
+            shadow: false,
+        }
+    }
+
+    /// Takes effect the next time text is prepared.
+    pub fn set_shadow(&mut self, shadow: bool) {
+        self.shadow = shadow;
     }
 
     pub fn resize(&mut self, queue: &wgpu::Queue, new_size: PhysicalSize<u32>) {
@@ -58,10 +137,24 @@ impl Pipeline {
     pub fn prepare<'a, I, S>(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, sections: I)
     where
         I: IntoIterator<Item = S>,
-        S: Into<std::borrow::Cow<'a, Section<'a>>>,
+        S: Into<Cow<'a, Section<'a>>>,
     {
+        let sections: Vec<Cow<Section>> = sections.into_iter().map(Into::into).collect();
+        // Queued first so they're drawn underneath
+        let shadows: Vec<OwnedSection> = if self.shadow {
+            sections.iter().map(|section| shadow_of(section)).collect()
+        } else {
+            Vec::new()
+        };
         self.brush
-            .queue(device, queue, sections)
+            .queue(
+                device,
+                queue,
+                shadows
+                    .iter()
+                    .map(|shadow| Cow::Owned(shadow.to_borrowed()))
+                    .chain(sections.iter().map(|section| Cow::Borrowed(&**section))),
+            )
             .expect("queueing brush");
     }
 
@@ -69,3 +162,18 @@ impl Pipeline {
         self.brush.draw(render_pass);
     }
 }
+
+/// A dark copy of a section, nudged down & to the right.
+fn shadow_of(section: &Section) -> OwnedSection {
+    let mut shadow = section.to_owned();
+    let offset = shadow
+        .text
+        .first()
+        .map_or(0.0, |text| text.scale.y * SHADOW_OFFSET);
+    shadow.screen_position.0 += offset;
+    shadow.screen_position.1 += offset;
+    for text in &mut shadow.text {
+        text.extra.color = SHADOW_COLOR;
+    }
+    shadow
+}
@@ -0,0 +1,205 @@
+//! Keeps an eye on the trail, so that if the simulation gets into a state it can't recover from
+//! (everything gone, everything solid, or NaNs everywhere) we can notice & reset it.
+//!
+//! Every `CHECK_INTERVAL`, a few rows of the trail are copied into a buffer and read back once
+//! the GPU gets around to it. We never wait on the GPU, so this doesn't stall rendering.
+
+use std::sync::mpsc;
+
+use web_time::{Duration, Instant};
+
+use crate::constants::*;
+use crate::graphics::physarum::TRAIL_TEXEL_SIZE;
+
+/// How often to sample the trail.
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How many rows of the trail to sample, spread evenly from top to bottom.
+const SAMPLE_ROWS: u32 = 8;
+
+/// Number of samples in a row that have to be bad before we reset. Keeps us from resetting
+/// something that would have sorted itself out, like right after a preset change.
+const STRIKES: u32 = 3;
+
+/// A trail value this high everywhere in our sample means the screen is solid. A single particle
+/// deposit is a lot smaller than this, so it takes far more than a normal clump to get here.
+const SATURATED_TRAIL: f32 = 1.0;
+
+/// What's wrong with the simulation.
+#[derive(Copy, Clone, Debug)]
+pub enum Problem {
+    NotANumber,
+    Empty,
+    Saturated,
+}
+
+impl std::fmt::Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::NotANumber => "trail has NaN/infinite values",
+            Self::Empty => "trail is empty",
+            Self::Saturated => "trail is saturated",
+        })
+    }
+}
+
+enum State {
+    /// Waiting until the next sample is due.
+    Idle { since: Instant },
+    /// The copy has been recorded, and needs to be mapped once it's submitted.
+    Copied,
+    /// Waiting for the GPU to finish the copy.
+    Mapping(mpsc::Receiver<Result<(), wgpu::BufferAsyncError>>),
+}
+
+pub struct Watchdog {
+    buffer: wgpu::Buffer,
+    /// Bytes between sampled rows in `buffer`, padded to what copies require.
+    row_stride: u32,
+    state: State,
+    /// How many samples in a row have had a problem.
+    strikes: u32,
+}
+
+impl Watchdog {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let row_stride = (SIMULATION_WIDTH * TRAIL_TEXEL_SIZE)
+            .next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("watchdog_buffer"),
+            size: u64::from(row_stride * SAMPLE_ROWS),
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            buffer,
+            row_stride,
+            state: State::Idle {
+                since: Instant::now(),
+            },
+            strikes: 0,
+        }
+    }
+
+    /// Forgets about any problems seen so far, e.g. because the simulation was just reset.
+    pub fn clear(&mut self) {
+        self.strikes = 0;
+    }
+
+    /// Records a copy of some of the trail, if it's time for another sample.
+    pub fn copy_sample(&mut self, encoder: &mut wgpu::CommandEncoder, trail: &wgpu::Texture) {
+        let State::Idle { since } = self.state else {
+            return;
+        };
+        if since.elapsed() < CHECK_INTERVAL {
+            return;
+        }
+
+        for i in 0..SAMPLE_ROWS {
+            let y = (SIMULATION_HEIGHT * (2 * i + 1)) / (2 * SAMPLE_ROWS);
+            encoder.copy_texture_to_buffer(
+                wgpu::TexelCopyTextureInfo {
+                    texture: trail,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d { x: 0, y, z: 0 },
+                    aspect: wgpu::TextureAspect::All,
+                },
+                wgpu::TexelCopyBufferInfo {
+                    buffer: &self.buffer,
+                    layout: wgpu::TexelCopyBufferLayout {
+                        offset: u64::from(self.row_stride * i),
+                        bytes_per_row: Some(self.row_stride),
+                        rows_per_image: Some(1),
+                    },
+                },
+                wgpu::Extent3d {
+                    width: SIMULATION_WIDTH,
+                    height: 1,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+        self.state = State::Copied;
+    }
+
+    /// Must be called after the encoder passed to `copy_sample()` has been submitted.
+    pub fn after_submit(&mut self) {
+        if !matches!(self.state, State::Copied) {
+            return;
+        }
+        let (tx, rx) = mpsc::channel();
+        self.buffer
+            .map_async(wgpu::MapMode::Read, .., move |result| {
+                // If we've gone away, nobody cares about the result
+                let _ = tx.send(result);
+            });
+        self.state = State::Mapping(rx);
+    }
+
+    /// Looks at the latest sample, if it's arrived. Returns a problem once it's been seen enough
+    /// times in a row that the simulation should be reset.
+    pub fn check(&mut self, device: &wgpu::Device) -> Option<Problem> {
+        let State::Mapping(rx) = &self.state else {
+            return None;
+        };
+        // Lets the GPU run the map callback, without waiting on anything
+        if let Err(e) = device.poll(wgpu::PollType::Poll) {
+            eprintln!("Error polling device: {e}");
+        }
+        let result = match rx.try_recv() {
+            Ok(result) => result,
+            Err(mpsc::TryRecvError::Empty) => return None,
+            Err(mpsc::TryRecvError::Disconnected) => Err(wgpu::BufferAsyncError),
+        };
+        self.state = State::Idle {
+            since: Instant::now(),
+        };
+        if let Err(e) = result {
+            eprintln!("Error reading back trail: {e}");
+            return None;
+        }
+
+        let problem = {
+            let view = self.buffer.slice(..).get_mapped_range();
+            // Only the trail particles sense matters, not how it's colored
+            let samples = view.chunks_exact(self.row_stride as usize).flat_map(|row| {
+                bytemuck::cast_slice::<u8, [f32; 4]>(row)[..SIMULATION_WIDTH as usize]
+                    .iter()
+                    .map(|texel| texel[0])
+            });
+            find_problem(samples)
+        };
+        self.buffer.unmap();
+
+        match problem {
+            Some(problem) => {
+                self.strikes += 1;
+                (self.strikes >= STRIKES).then_some(problem)
+            }
+            None => {
+                self.strikes = 0;
+                None
+            }
+        }
+    }
+}
+
+fn find_problem(samples: impl Iterator<Item = f32>) -> Option<Problem> {
+    let mut min = f32::INFINITY;
+    let mut max = 0.0f32;
+    for sample in samples {
+        if !sample.is_finite() {
+            return Some(Problem::NotANumber);
+        }
+        min = min.min(sample);
+        max = max.max(sample);
+    }
+    if max <= 0.0 {
+        Some(Problem::Empty)
+    } else if min >= SATURATED_TRAIL {
+        Some(Problem::Saturated)
+    } else {
+        None
+    }
+}
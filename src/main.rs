@@ -1,38 +1,158 @@
 #![allow(clippy::approx_constant)]
 
 use std::{
-    sync::{Arc, Mutex, mpsc},
-    time::Duration,
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::{Arc, Mutex},
 };
 
-use rodio::{DeviceTrait, Source, cpal::traits::HostTrait};
+use rodio::Source;
+use web_time::{Duration, Instant};
 use winit::{
     application::ApplicationHandler,
-    event::{ElementState, KeyEvent, WindowEvent},
-    event_loop::{ActiveEventLoop, ControlFlow, EventLoop},
-    keyboard::{Key, KeyCode, NamedKey, PhysicalKey},
+    event::{ElementState, KeyEvent, MouseButton, StartCause, WindowEvent},
+    event_loop::{ActiveEventLoop, ControlFlow, EventLoop, EventLoopProxy},
+    keyboard::{Key, KeyCode, ModifiersState, NamedKey, PhysicalKey},
+    monitor::MonitorHandle,
     window::{Fullscreen, Window, WindowId},
 };
 
+use crate::action::Action;
 use crate::audio::NUM_BINS;
+use crate::platform::{Current, Platform};
 
+mod action;
 mod audio;
+mod benchmark;
+mod check;
+mod clipboard;
 mod constants;
 mod fs;
+mod gif;
 mod graphics;
+mod osc;
+mod pacing;
+mod picker;
+mod platform;
+mod png;
+mod remote;
+mod screenshot;
 mod shaders;
+mod sweep;
+mod timelapse;
+mod trigger;
 
 struct State {
     window: Arc<Window>,
 
     device: wgpu::Device,
     queue: wgpu::Queue,
+    /// Which GPU & driver we're running on, kept for printing diagnostics.
+    adapter_info: wgpu::AdapterInfo,
     size: winit::dpi::PhysicalSize<u32>,
+    /// Whether the window has no area, e.g. while it's minimized. The surface can't be configured
+    /// that small on some backends, so nothing is drawn until it's given a size again.
+    minimized: bool,
     surface: wgpu::Surface<'static>,
     surface_format: wgpu::TextureFormat,
+    /// What the surface can be used for, including being read back for screenshots if possible.
+    surface_usage: wgpu::TextureUsages,
     pipeline: crate::graphics::Pipeline,
+    pacing: pacing::FramePacing,
+    /// How long to stay on each preset before automatically moving to the next one, if at all.
+    slideshow_interval: Option<Duration>,
+    /// When the preset was last changed by the slideshow, or the user last pressed a key.
+    slideshow_last_change: Instant,
+    /// How long nobody has to be around before the screensaver starts, if it should at all.
+    screensaver_after: Option<Duration>,
+    /// How long the screensaver takes to morph from one set of random settings to the next.
+    screensaver_morph_time: Duration,
+    /// Lets the user pick music & settings files while running.
+    picker: picker::Picker,
+    /// How long to wait between frames when nothing much is happening, if we should slow down at
+    /// all.
+    idle_frame_time: Option<Duration>,
+    /// When the user last pressed a key.
+    last_input: Instant,
+    /// When to render the next frame, if we're idling instead of rendering as fast as possible.
+    idle_until: Option<Instant>,
 
     audio: Option<Audio>,
+    /// How to play & analyze music, kept for when new music is picked.
+    audio_options: AudioOptions,
+    /// How much bigger to draw the header, if not whatever the window's scale factor is.
+    ui_scale: Option<f32>,
+    /// What decides when the simulation advances, if not the frame rate.
+    trigger: Option<trigger::Trigger>,
+    /// Where requests to control us from elsewhere come in, if anywhere.
+    remote: Option<remote::Remote>,
+    /// Where the music analysis gets sent to, if anywhere.
+    osc: Option<osc::Sender>,
+    /// Whether the next frame read back should be saved as a screenshot.
+    screenshot_requested: bool,
+    /// Where screenshots get copied to, or why they can't be.
+    clipboard: Result<clipboard::Clipboard, String>,
+    /// The GIF being recorded, if there is one.
+    recording: Option<gif::Recording>,
+    /// Where frames are being saved every so often, if anywhere.
+    timelapse: Option<timelapse::Timelapse>,
+    /// The parameter being swept while saving every frame, if one is.
+    sweep: Option<sweep::Sweep>,
+    /// Whether the next keypress should start everything off, having started paused.
+    resume_on_key: bool,
+}
+
+/// How long without any keypresses before we can start idling.
+const IDLE_AFTER: Duration = Duration::from_secs(10);
+/// How long to wait between frames while idling by default, for about 30 a second.
+const DEFAULT_IDLE_FRAME_TIME: Duration = Duration::from_millis(33);
+/// How long the screensaver takes to morph between random settings by default.
+const DEFAULT_SCREENSAVER_MORPH_TIME: Duration = Duration::from_secs(30);
+/// How long to wait between frames while minimized, since none of them get drawn.
+const MINIMIZED_FRAME_TIME: Duration = Duration::from_millis(100);
+
+/// What the addresses of OSC messages start with by default.
+const DEFAULT_OSC_PREFIX: &str = "/physarum";
+/// How many times a second the music analysis gets sent over OSC by default, & at most.
+const DEFAULT_OSC_RATE: f32 = 30.0;
+const MAX_OSC_RATE: f32 = 1000.0;
+
+/// How bright the trail needs to be to glow by default, from 0 to 1.
+const DEFAULT_GLOW_THRESHOLD: f32 = 0.6;
+
+/// How far F2 & F4 seek through the music by default.
+const DEFAULT_SEEK_STEP: Duration = Duration::from_secs(10);
+/// How many times further seeking goes with Shift held.
+const BIG_SEEK_MULTIPLIER: u32 = 6;
+/// How far the position indicator glides past the last position rodio gave us by default.
+const DEFAULT_POSITION_SMOOTHING: Duration = Duration::from_millis(250);
+
+/// Loudness tracks get normalized to by default, in LUFS. About where streaming services put
+/// music.
+const DEFAULT_LOUDNESS_TARGET: f32 = -14.0;
+
+struct AudioOptions {
+    /// Whether music should be analyzed without being played.
+    mute: bool,
+    /// Name of the device to play music on, if not the default.
+    device: Option<String>,
+    /// How to even out quiet & loud passages, if at all.
+    auto_gain: Option<audio::gain::Config>,
+    /// The loudness (in LUFS) to bring every track to, if normalizing.
+    loudness_target: Option<f32>,
+    /// How long music takes to be heard after it's played, on top of what rodio knows about.
+    latency: Duration,
+    /// Which frequencies go into each FFT bin, shared with the display so they can be edited.
+    frequency_ranges: audio::SharedRanges,
+    /// The only channel to analyze & play (counting from 0), or `None` for all of them. Carries
+    /// over to the next music file opened.
+    solo_channel: Option<usize>,
+    /// How far F2 & F4 seek through the music.
+    seek_step: Duration,
+    /// Whether music starts over as soon as it finishes.
+    looping: bool,
+    /// How far the position indicator can glide ahead between updates, or zero to not smooth it.
+    position_smoothing: Duration,
 }
 
 struct Audio {
@@ -40,22 +160,225 @@ struct Audio {
     _output_stream: rodio::OutputStream,
     sink: rodio::Sink,
     total_duration: Duration,
+    latency: Duration,
     // TODO: better naming
-    tx: mpsc::SyncSender<()>,
+    tx: audio::worker::Sender,
     bins: Arc<Mutex<Vec<f32>>>,
     last_bins: [f32; NUM_BINS],
+    /// The bins straight out of the FFT, before auto-gain.
+    raw_bins: audio::worker::SharedBins,
+    last_raw_bins: [f32; NUM_BINS],
+    /// How much louder the right channel is than the left, from -1 to 1.
+    balance: Arc<Mutex<f32>>,
+    collector: Arc<Mutex<audio::collector::Collector>>,
+    /// The music file to queue up again as each time through finishes, if looping.
+    loop_file: Option<PathBuf>,
+    /// How many times through the music have been queued up on the sink so far.
+    queued: u32,
+    /// Where the position indicator shows the music as being.
+    shown_position: audio::position::SmoothedPosition,
+}
+
+impl Audio {
+    /// Starts playing a music file & analyzing it in the background. If that's not possible, says
+    /// why and carries on without music.
+    fn open_or_report(file: &Path, options: &AudioOptions) -> Option<Self> {
+        match Self::open(file, options) {
+            Ok(audio) => Some(audio),
+            Err(e) => {
+                eprintln!("Error opening music file {}: {e}", file.display());
+                eprintln!("Continuing without music...");
+                None
+            }
+        }
+    }
+
+    fn open(file: &Path, options: &AudioOptions) -> Result<Self, String> {
+        let output_stream = Current::open_output_stream(options.device.as_deref())?;
+        let mixer = output_stream.mixer();
+        // TODO: some way to pause/otherwise control this sink with the keyboard
+        let sink = rodio::Sink::connect_new(mixer);
+        if options.mute {
+            // Still decode & analyze the music, we just don't want to hear it
+            sink.set_volume(0.0);
+        }
+
+        let source = decode(file)?;
+        let total_duration = source
+            .total_duration()
+            .ok_or("could not get the length of the music")?;
+        let (collector, source) = audio::collector::Collector::new(source);
+        collector.lock().unwrap().set_solo(options.solo_channel);
+        sink.append(source);
+
+        let (tx, bins, raw_bins, balance, worker) = audio::worker::Worker::new(
+            collector.clone(),
+            options.frequency_ranges.clone(),
+            options.auto_gain,
+            options.loudness_target,
+        );
+        Current::spawn(move || worker.work());
+
+        Ok(Audio {
+            _output_stream: output_stream,
+            sink,
+            total_duration,
+            latency: options.latency,
+            tx,
+            bins,
+            last_bins: [0.0; NUM_BINS],
+            raw_bins,
+            last_raw_bins: [0.0; NUM_BINS],
+            balance,
+            collector,
+            loop_file: options.looping.then(|| file.to_path_buf()),
+            queued: 1,
+            shown_position: audio::position::SmoothedPosition::new(options.position_smoothing),
+        })
+    }
+
+    /// Keeps the next time through the music queued up behind the one playing, if looping, so it
+    /// carries straight on without a gap when this one finishes. Stops looping if the music can't
+    /// be opened again.
+    fn keep_looping(&mut self) {
+        let Some(file) = &self.loop_file else {
+            return;
+        };
+        if self.sink.len() >= 2 {
+            return;
+        }
+        match decode(file) {
+            Ok(source) => {
+                let start = self.total_duration * self.queued;
+                self.sink.append(audio::collector::Collector::queue(
+                    &self.collector,
+                    source,
+                    start,
+                ));
+                self.queued += 1;
+            }
+            Err(e) => {
+                eprintln!("Error looping music file {}: {e}", file.display());
+                eprintln!("Continuing without looping...");
+                self.loop_file = None;
+            }
+        }
+    }
+
+    /// Where to show the music as being on the position indicator.
+    fn shown_position(&mut self) -> Duration {
+        self.shown_position.update(
+            self.sink.get_pos(),
+            !self.sink.is_paused() && !self.sink.empty(),
+            self.sink.speed(),
+            Instant::now(),
+        )
+    }
+
+    /// Where in the music is being played, counting every time through it if looping, so the
+    /// analysis carries on smoothly from one time to the next.
+    fn stream_position(&self) -> Duration {
+        let position = self.sink.get_pos();
+        match self.loop_file {
+            Some(_) => {
+                let finished = self.queued.saturating_sub(self.sink.len() as u32);
+                self.total_duration * finished + position
+            }
+            None => position,
+        }
+    }
+}
+
+/// The latest batch of bins the worker has made.
+fn read_bins(bins: &audio::worker::SharedBins) -> [f32; NUM_BINS] {
+    bins.lock()
+        .unwrap()
+        .iter()
+        .map(Clone::clone)
+        .collect::<Vec<_>>()
+        .try_into()
+        .expect("wrong number of bins")
+}
+
+/// Opens a music file to play.
+fn decode(file: &Path) -> Result<rodio::Decoder<std::io::BufReader<std::fs::File>>, String> {
+    let file = std::fs::File::open(file).map_err(|e| format!("could not open file: {e}"))?;
+    rodio::Decoder::try_from(file).map_err(|e| {
+        format!(
+            "could not decode file: {e} (supported formats are {})",
+            audio::FORMATS.join(", ")
+        )
+    })
+}
+
+/// Which wgpu backend a --backend name refers to. Warns & returns None if it isn't one.
+fn parse_backend(name: &str) -> Option<wgpu::Backends> {
+    match name.to_ascii_lowercase().as_str() {
+        "vulkan" => Some(wgpu::Backends::VULKAN),
+        "dx12" => Some(wgpu::Backends::DX12),
+        "metal" => Some(wgpu::Backends::METAL),
+        "gl" => Some(wgpu::Backends::GL),
+        _ => {
+            eprintln!("Unknown backend {name:?}, expected one of vulkan, dx12, metal, or gl");
+            eprintln!("Falling back to picking one automatically...");
+            None
+        }
+    }
+}
+
+/// Checks the adapter can handle everything the simulation needs, printing what it's missing if
+/// not.
+fn supports_required_limits(adapter: &wgpu::Adapter) -> bool {
+    let mut missing = Vec::new();
+    graphics::required_limits().check_limits_with_fail_fn(
+        &adapter.limits(),
+        false,
+        |name, needed, supported| missing.push((name, needed, supported)),
+    );
+    if missing.is_empty() {
+        return true;
+    }
+    eprintln!(
+        "{} can't run the simulation, its limits are too low:",
+        adapter.get_info().name
+    );
+    for (name, needed, supported) in missing {
+        eprintln!("  {name} needs to be {needed}, but is only {supported}");
+    }
+    false
+}
+
+/// How to open the device, once `supports_required_limits()` says the adapter is good enough.
+fn device_descriptor(adapter: &wgpu::Adapter) -> wgpu::DeviceDescriptor<'static> {
+    wgpu::DeviceDescriptor {
+        // Allow windows as big as the GPU can handle, even if the simulation doesn't need it
+        required_limits: graphics::required_limits().using_resolution(adapter.limits()),
+        ..Default::default()
+    }
 }
 
 /// Data that gets rendered on the screen every frame, if playing audio
 struct AudioDisplay {
     bins: [f32; NUM_BINS],
+    /// The same bins before auto-gain, for comparing against.
+    raw_bins: [f32; NUM_BINS],
+    /// How much louder the right channel is than the left, from -1 to 1.
+    balance: f32,
     position: Duration,
     total_duration: Duration,
 }
 
 impl State {
-    async fn new(flags: &flags::Main, window: Arc<Window>) -> State {
-        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
+    async fn new(flags: &flags::Run, settings_filename: PathBuf, window: Arc<Window>) -> State {
+        let backends = flags
+            .backend
+            .as_deref()
+            .and_then(parse_backend)
+            .unwrap_or(wgpu::Backends::all());
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
+            backends,
+            ..Default::default()
+        });
         let adapter = instance
             .request_adapter(&wgpu::RequestAdapterOptionsBase {
                 power_preference: wgpu::PowerPreference::HighPerformance,
@@ -63,86 +386,376 @@ impl State {
                 compatible_surface: None,
             })
             .await
-            .unwrap();
+            .expect("could not find a graphics adapter for the chosen backend");
+        let info = adapter.get_info();
+        println!(
+            "Using {} on {} ({} {})",
+            info.backend, info.name, info.driver, info.driver_info
+        );
+        if !supports_required_limits(&adapter) {
+            eprintln!("Try a different GPU, or a different backend with --backend");
+            std::process::exit(1);
+        }
         let (device, queue) = adapter
-            .request_device(&wgpu::DeviceDescriptor::default())
+            .request_device(&device_descriptor(&adapter))
             .await
-            .unwrap();
+            .expect("could not open the graphics adapter");
 
         let size = window.inner_size();
 
         let surface = instance.create_surface(window.clone()).unwrap();
         let cap = surface.get_capabilities(&adapter);
         let surface_format = cap.formats[0];
+        // Reading the screen back is only used for screenshots, so do without it if we have to
+        let surface_usage =
+            wgpu::TextureUsages::RENDER_ATTACHMENT | (cap.usages & wgpu::TextureUsages::COPY_SRC);
 
-        let settings_filename = flags.settings.clone().unwrap_or("settings.json".into());
-        let mut pipeline = graphics::Pipeline::new(&device, &queue, size, surface_format);
+        let edges = if flags.bounded {
+            graphics::Edges::Bounded
+        } else {
+            graphics::Edges::Wrap
+        };
+        let background = flags.background.as_ref().and_then(|path| {
+            png::read(path)
+                .inspect_err(|e| {
+                    eprintln!("Error loading background image {}: {e}", path.display());
+                    eprintln!("Continuing without one...");
+                })
+                .ok()
+        });
+        let visualizer = {
+            use graphics::VisualizerSizes;
+            let defaults = VisualizerSizes::default();
+            let size_or_default = |name: &str, size: Option<f32>, default: f32| match size {
+                Some(size) if size > 0.0 && size <= VisualizerSizes::MAX_RADIUS => size,
+                Some(size) => {
+                    eprintln!(
+                        "Visualizer {name} must be more than 0 & at most {}, got {size}",
+                        VisualizerSizes::MAX_RADIUS
+                    );
+                    eprintln!("Falling back to {default}...");
+                    default
+                }
+                None => default,
+            };
+            VisualizerSizes {
+                circle_radius: size_or_default(
+                    "radius",
+                    flags.visualizer_radius,
+                    defaults.circle_radius,
+                ),
+                ring_width: size_or_default(
+                    "ring width",
+                    flags.visualizer_ring_width,
+                    defaults.ring_width,
+                ),
+                line_width: size_or_default(
+                    "line width",
+                    flags.visualizer_line_width,
+                    defaults.line_width,
+                ),
+            }
+        };
+        let mut pipeline = graphics::Pipeline::new(
+            &device,
+            &queue,
+            size,
+            surface_format,
+            edges,
+            background.as_ref(),
+            (!flags.no_header).then_some(visualizer),
+        );
         pipeline.read_settings_file(&queue, settings_filename);
+        if let Some(number) = flags.preset {
+            let count = pipeline.settings().num_presets().max(1);
+            let clamped = number.clamp(1, count);
+            if clamped != number {
+                eprintln!("Preset must be between 1 & {count}, got {number}");
+                eprintln!("Falling back to preset {clamped}...");
+            }
+            pipeline.set_preset(clamped - 1);
+        }
+        if let Some(count_scale) = flags.count_scale {
+            pipeline.set_count_scale(&queue, count_scale.max(f32::EPSILON));
+        }
+        if let Some(steps) = flags.particle_lifetime {
+            if steps <= constants::MAX_PARTICLE_LIFETIME {
+                pipeline.set_particle_lifetime(&queue, steps);
+            } else {
+                eprintln!(
+                    "Particles can live for at most {} steps, got {steps}",
+                    constants::MAX_PARTICLE_LIFETIME
+                );
+                eprintln!(
+                    "Falling back to {}...",
+                    constants::DEFAULT_PARTICLE_LIFETIME
+                );
+            }
+        }
+        pipeline.set_pixelated(flags.pixelated);
+        pipeline.set_text_shadow(flags.text_shadow);
+        pipeline.set_frame_stats_shown(flags.frame_stats);
+        if let Some(name) = &flags.distribution {
+            match name.parse() {
+                Ok(distribution) => pipeline.set_distribution(&queue, distribution),
+                Err(e) => {
+                    eprintln!("Unknown distribution {name:?}, {e}");
+                    eprintln!("Falling back to uniform...");
+                }
+            }
+        }
+        if let Some(degrees) = flags.rotate {
+            if degrees % 90 != 0 {
+                eprintln!("Can only rotate by multiples of 90 degrees, rounding down...");
+            }
+            pipeline.set_rotation(&queue, degrees / 90);
+        }
+        if let Some(ratio) = &flags.aspect {
+            match ratio.parse() {
+                Ok(aspect) => pipeline.set_aspect(Some(aspect)),
+                Err(e) => {
+                    eprintln!("Can't letterbox to {ratio:?}, {e}");
+                    eprintln!("Falling back to filling the window...");
+                }
+            }
+        }
+        if let Some(feedback) = flags.feedback {
+            pipeline.set_feedback(feedback);
+        }
+        if let Some(intensity) = flags.glow {
+            let threshold = flags.glow_threshold.unwrap_or(DEFAULT_GLOW_THRESHOLD);
+            if intensity > 0.0 && (0.0..=1.0).contains(&threshold) {
+                pipeline.set_glow(Some(graphics::Glow {
+                    threshold,
+                    intensity,
+                }));
+            } else {
+                eprintln!(
+                    "Glow intensity must be more than 0 & its threshold between 0 & 1, got {intensity} & {threshold}"
+                );
+                eprintln!("Continuing without glow...");
+            }
+        }
+        if let Some(snapshots) = flags.rewind_snapshots {
+            let interval = Duration::from_millis(flags.rewind_interval_ms.unwrap_or(1000));
+            pipeline.set_rewind(snapshots, interval);
+        }
+        if let Some(count) = flags.beat_spawn {
+            if (1..=constants::SIMULATION_NUM_PARTICLES).contains(&count) {
+                pipeline.set_beat_spawn(count);
+            } else {
+                eprintln!(
+                    "Beats can respawn between 1 & {} particles, got {count}",
+                    constants::SIMULATION_NUM_PARTICLES
+                );
+                eprintln!("Continuing without respawning particles on beats...");
+            }
+        }
         let pipeline = pipeline;
+        let frequency_ranges = pipeline.frequency_ranges();
+
+        let refresh_period = pacing::FramePacing::period_from_millihertz(
+            window
+                .current_monitor()
+                .and_then(|monitor| monitor.refresh_rate_millihertz()),
+        );
 
         let mut state = State {
             window,
             device,
             queue,
+            adapter_info: info,
             size,
+            minimized: size.width == 0 || size.height == 0,
             surface,
             surface_format,
+            surface_usage,
             pipeline,
+            pacing: pacing::FramePacing::new(refresh_period),
+            slideshow_interval: flags.slideshow_secs.map(Duration::from_secs),
+            slideshow_last_change: Instant::now(),
+            screensaver_after: flags.screensaver_secs.map(Duration::from_secs),
+            screensaver_morph_time: flags.screensaver_morph_secs.map_or(
+                DEFAULT_SCREENSAVER_MORPH_TIME,
+                |secs| match Duration::try_from_secs_f32(secs) {
+                    Ok(time) if !time.is_zero() => time,
+                    _ => {
+                        eprintln!("Screensaver morphs must take more than 0 seconds, got {secs}");
+                        eprintln!("Falling back to {DEFAULT_SCREENSAVER_MORPH_TIME:?}...");
+                        DEFAULT_SCREENSAVER_MORPH_TIME
+                    }
+                },
+            ),
+            picker: picker::Picker::new(),
+            idle_frame_time: match flags.idle_frame_ms {
+                Some(0) => None,
+                Some(ms) => Some(Duration::from_millis(ms)),
+                None => Some(DEFAULT_IDLE_FRAME_TIME),
+            },
+            last_input: Instant::now(),
+            idle_until: None,
             audio: None,
+            audio_options: AudioOptions {
+                mute: flags.mute,
+                device: flags.audio_device.clone(),
+                auto_gain: flags.agc.then(|| {
+                    let default = audio::gain::Config::default();
+                    audio::gain::Config {
+                        attack: flags
+                            .agc_attack_ms
+                            .map_or(default.attack, Duration::from_millis),
+                        release: flags
+                            .agc_release_ms
+                            .map_or(default.release, Duration::from_millis),
+                        max_gain: flags.agc_max_gain.unwrap_or(default.max_gain),
+                    }
+                }),
+                loudness_target: flags
+                    .normalize_loudness
+                    .then(|| flags.loudness_target.unwrap_or(DEFAULT_LOUDNESS_TARGET)),
+                latency: flags
+                    .audio_latency_ms
+                    .map_or(Duration::ZERO, Duration::from_millis),
+                frequency_ranges,
+                solo_channel: flags.solo_channel.and_then(|channel| {
+                    if channel > 0 {
+                        Some(channel - 1)
+                    } else {
+                        eprintln!("Channels count from 1, got {channel}");
+                        eprintln!("Falling back to all of them...");
+                        None
+                    }
+                }),
+                looping: flags.loop_music,
+                position_smoothing: flags
+                    .position_smoothing_ms
+                    .map_or(DEFAULT_POSITION_SMOOTHING, Duration::from_millis),
+                seek_step: flags.seek_secs.map_or(DEFAULT_SEEK_STEP, |secs| {
+                    match Duration::try_from_secs_f32(secs) {
+                        Ok(step) if !step.is_zero() => step,
+                        _ => {
+                            eprintln!("Seeking must go more than 0 seconds, got {secs}");
+                            eprintln!("Falling back to {DEFAULT_SEEK_STEP:?}...");
+                            DEFAULT_SEEK_STEP
+                        }
+                    }
+                }),
+            },
+            ui_scale: flags.ui_scale.and_then(|scale| {
+                if scale > 0.0 {
+                    Some(scale)
+                } else {
+                    eprintln!("UI scale must be more than 0, got {scale}");
+                    eprintln!("Falling back to the window's scale factor...");
+                    None
+                }
+            }),
+            trigger: flags.trigger.as_ref().and_then(|source| {
+                match source
+                    .parse()
+                    .and_then(|source| trigger::Trigger::start(source).map_err(|e| e.to_string()))
+                {
+                    Ok(trigger) => Some(trigger),
+                    Err(e) => {
+                        eprintln!("Can't use {source:?} as a trigger, {e}");
+                        eprintln!("Falling back to one step per frame...");
+                        None
+                    }
+                }
+            }),
+            remote: flags.remote.as_ref().and_then(|address| {
+                match remote::address(address)
+                    .and_then(|address| remote::Remote::start(address).map_err(|e| e.to_string()))
+                {
+                    Ok(remote) => Some(remote),
+                    Err(e) => {
+                        eprintln!("Can't listen for remote control on {address:?}, {e}");
+                        eprintln!("Continuing without remote control...");
+                        None
+                    }
+                }
+            }),
+            osc: flags.osc_out.as_ref().and_then(|address| {
+                let prefix = match &flags.osc_prefix {
+                    Some(prefix) if prefix.starts_with('/') => prefix.trim_end_matches('/'),
+                    Some(prefix) => {
+                        eprintln!("OSC address prefixes must start with /, got {prefix:?}");
+                        eprintln!("Falling back to {DEFAULT_OSC_PREFIX}...");
+                        DEFAULT_OSC_PREFIX
+                    }
+                    None => DEFAULT_OSC_PREFIX,
+                };
+                let rate = match flags.osc_rate {
+                    Some(rate) if rate > 0.0 && rate <= MAX_OSC_RATE => rate,
+                    Some(rate) => {
+                        eprintln!(
+                            "OSC rate must be more than 0 & at most {MAX_OSC_RATE}, got {rate}"
+                        );
+                        eprintln!("Falling back to {DEFAULT_OSC_RATE} times a second...");
+                        DEFAULT_OSC_RATE
+                    }
+                    None => DEFAULT_OSC_RATE,
+                };
+                let bin_addresses = osc::bin_addresses(flags.osc_bin_addresses.as_deref(), prefix);
+                match osc::address(address).and_then(|address| {
+                    osc::Sender::start(address, prefix.to_string(), bin_addresses, rate)
+                        .map_err(|e| e.to_string())
+                }) {
+                    Ok(sender) => Some(sender),
+                    Err(e) => {
+                        eprintln!("Can't send OSC to {address:?}, {e}");
+                        eprintln!("Continuing without sending OSC...");
+                        None
+                    }
+                }
+            }),
+            screenshot_requested: false,
+            clipboard: clipboard::Clipboard::open(),
+            recording: None,
+            timelapse: flags.timelapse_interval.as_ref().and_then(|interval| {
+                let dir = flags
+                    .timelapse_dir
+                    .clone()
+                    .unwrap_or_else(|| PathBuf::from("."));
+                match interval.parse().and_then(|interval| {
+                    timelapse::Timelapse::start(dir.clone(), interval).map_err(|e| e.to_string())
+                }) {
+                    Ok(timelapse) => Some(timelapse),
+                    Err(e) => {
+                        eprintln!("Can't save a timelapse to {}, {e}", dir.display());
+                        eprintln!("Continuing without a timelapse...");
+                        None
+                    }
+                }
+            }),
+            sweep: flags.sweep.as_ref().and_then(|spec| {
+                let dir = flags
+                    .sweep_dir
+                    .clone()
+                    .unwrap_or_else(|| PathBuf::from("."));
+                match spec.parse().and_then(|spec| {
+                    sweep::Sweep::start(spec, dir.clone()).map_err(|e| e.to_string())
+                }) {
+                    Ok(sweep) => Some(sweep),
+                    Err(e) => {
+                        eprintln!("Can't sweep {spec} into {}, {e}", dir.display());
+                        eprintln!("Continuing without a sweep...");
+                        None
+                    }
+                }
+            }),
+            resume_on_key: flags.start_paused,
         };
 
         // Configure surface for the first time
         state.configure_surface();
 
         if let Some(file) = &flags.music {
-            /// Returns a PulseAudio device, if there is one.
-            /// cpal only supports ALSA on Linux, but fortunately that has a PulseAudio backend
-            /// which seems to be the thing we actually use on KDE for routing audio stuff.
-            fn find_pulse_device() -> Option<rodio::cpal::Device> {
-                #[cfg(target_os = "linux")]
-                for device in rodio::cpal::host_from_id(rodio::cpal::HostId::Alsa)
-                    .expect("could not open host")
-                    .output_devices()
-                    .expect("could not enumerate output devices")
-                {
-                    let name = device.name().expect("could not read device name");
-                    if name == "pulse" {
-                        return Some(device);
-                    }
-                }
-
-                None
-            }
-            let output_stream = match find_pulse_device() {
-                Some(device) => rodio::OutputStreamBuilder::from_device(device),
-                None => rodio::OutputStreamBuilder::from_default_device(),
-            }
-            .expect("could not build output stream from device")
-            .open_stream()
-            .expect("could not open output stream");
-            let mixer = output_stream.mixer();
-            // TODO: some way to pause/otherwise control this sink with the keyboard
-            let sink = rodio::Sink::connect_new(mixer);
-
-            let file = std::fs::File::open(file).expect("could not open music file");
-            let source = rodio::Decoder::try_from(file).expect("could not decode music file");
-            let total_duration = source
-                .total_duration()
-                .expect("could not get source duration");
-            let (collector, source) = audio::collector::Collector::new(source);
-            sink.append(source);
-
-            let (tx, bins, worker) = audio::worker::Worker::new(collector);
-            std::thread::spawn(move || worker.work());
-
-            state.audio = Some(Audio {
-                _output_stream: output_stream,
-                sink,
-                total_duration,
-                tx,
-                bins,
-                last_bins: [0.0; NUM_BINS],
-            });
+            state.audio = Audio::open_or_report(file, &state.audio_options);
+        }
+        if flags.start_paused {
+            println!("Starting paused, press any key to begin");
+            state.set_paused(true);
         }
 
         state
@@ -152,9 +765,9 @@ impl State {
         &self.window
     }
 
-    fn configure_surface(&mut self) {
-        let surface_config = wgpu::SurfaceConfiguration {
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+    fn surface_config(&self) -> wgpu::SurfaceConfiguration {
+        wgpu::SurfaceConfiguration {
+            usage: self.surface_usage,
             format: self.surface_format,
             // Request compatibility with the sRGB-format texture view we‘re going to create later.
             view_formats: vec![self.surface_format.add_srgb_suffix()],
@@ -163,12 +776,34 @@ impl State {
             height: self.size.height,
             desired_maximum_frame_latency: 1,
             present_mode: wgpu::PresentMode::AutoVsync,
-        };
-        self.surface.configure(&self.device, &surface_config);
-        self.pipeline.resize(&self.queue, self.size);
+        }
+    }
+
+    fn configure_surface(&mut self) {
+        if self.minimized {
+            return;
+        }
+        self.surface.configure(&self.device, &self.surface_config());
+        let ui_scale = self
+            .ui_scale
+            .unwrap_or_else(|| self.window.scale_factor() as f32);
+        self.pipeline.set_ui_scale(ui_scale);
+        self.pipeline.resize(&self.device, &self.queue, self.size);
     }
 
     fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        // Keeps the last real size while minimized, which is usually what it's restored to
+        let was_minimized = std::mem::replace(
+            &mut self.minimized,
+            new_size.width == 0 || new_size.height == 0,
+        );
+        if self.minimized {
+            return;
+        }
+        if was_minimized && self.idle_until.take().is_some() {
+            // Back to drawing at full speed straight away
+            self.get_window().request_redraw();
+        }
         self.size = new_size;
 
         // reconfigure the surface
@@ -176,15 +811,86 @@ impl State {
     }
 
     fn render(&mut self, data: Option<&AudioDisplay>) {
+        if self.minimized {
+            return;
+        }
         // Create texture view
         if let Ok(surface_texture) = self.surface.get_current_texture() {
-            self.pipeline.render(
+            if let Some(trigger) = &mut self.trigger {
+                self.pipeline.set_steps(trigger.take_steps());
+            }
+            let now = Instant::now();
+            let record = self
+                .recording
+                .as_ref()
+                .is_some_and(|recording| recording.wants_frame(now));
+            let timelapse = self
+                .timelapse
+                .as_mut()
+                .is_some_and(|timelapse| timelapse.wants_frame(now));
+            let sweep = match &self.sweep {
+                Some(sweep) => {
+                    let (param, value) = sweep.value();
+                    self.pipeline.set_base_param(param, value);
+                    true
+                }
+                None => false,
+            };
+            if record || timelapse || sweep {
+                self.pipeline.request_capture();
+            }
+            let capture = self.pipeline.render(
                 &self.device,
                 &self.queue,
                 &surface_texture.texture,
                 self.surface_format,
                 data,
             );
+            match capture {
+                Some(image) => {
+                    if record && let Some(recording) = &mut self.recording {
+                        recording.add_frame(&image, now);
+                    }
+                    if sweep && let Some(sweep) = &mut self.sweep {
+                        // Screenshots wait until it's done, so it doesn't miss any frames
+                        sweep.add_frame(image, now);
+                    } else if std::mem::take(&mut self.screenshot_requested) {
+                        match &self.clipboard {
+                            Ok(clipboard) => clipboard.copy(image),
+                            Err(e) => {
+                                eprintln!("Can't copy the screenshot to the clipboard, {e}");
+                                eprintln!("Saving it to a file instead...");
+                                screenshot::save(image);
+                            }
+                        }
+                        // If the timelapse wanted this frame too, it takes the next one instead
+                    } else if timelapse && let Some(timelapse) = &mut self.timelapse {
+                        timelapse.add_frame(image, now);
+                    }
+                }
+                None if record || timelapse || sweep => {
+                    // Whatever went wrong has already been reported, and will keep going wrong
+                    if record {
+                        self.stop_recording();
+                    }
+                    if timelapse {
+                        eprintln!("Stopping the timelapse...");
+                        self.timelapse = None;
+                    }
+                    if sweep {
+                        eprintln!("Stopping the sweep...");
+                        self.sweep = None;
+                    }
+                }
+                None => {}
+            }
+            if self
+                .recording
+                .as_ref()
+                .is_some_and(|recording| recording.is_finished(now))
+            {
+                self.stop_recording();
+            }
 
             self.window.pre_present_notify();
             surface_texture.present();
@@ -196,19 +902,348 @@ impl State {
 }
 
 impl State {
-    fn handle_music_key(&mut self, key: KeyCode, repeat: bool) -> bool {
+    /// Records that a frame was rendered, updating the frame stats shown & printing them if
+    /// requested.
+    fn check_pacing(&mut self, print_stats: bool) {
+        let report = match self.pacing.frame(Instant::now()) {
+            Some(report) => report,
+            None => return,
+        };
+        // The window may have moved to a different monitor since we last checked
+        self.pacing
+            .set_refresh_period(pacing::FramePacing::period_from_millihertz(
+                self.window
+                    .current_monitor()
+                    .and_then(|monitor| monitor.refresh_rate_millihertz()),
+            ));
+        self.pipeline
+            .set_frame_stats(report.fps, report.dropped, report.frames, report.longest);
+        if print_stats {
+            println!(
+                "FPS: {:.1}, dropped {}/{} frames, longest frame {:.1}ms",
+                report.fps,
+                report.dropped,
+                report.frames,
+                report.longest.as_secs_f32() * 1000.0,
+            );
+        }
+    }
+
+    /// Moves on to the next preset if the slideshow is enabled & it's been long enough.
+    fn check_slideshow(&mut self) {
+        let interval = match self.slideshow_interval {
+            Some(interval) => interval,
+            None => return,
+        };
+        let now = Instant::now();
+        // The screensaver is showing its own settings, so moving the preset on would be hidden
+        if now.duration_since(self.slideshow_last_change) < interval
+            || self.pipeline.is_screensaving()
+        {
+            return;
+        }
+        // If the user is busy editing, wait a full interval before trying again
+        self.pipeline.advance_slideshow();
+        self.slideshow_last_change = now;
+    }
+
+    /// Starts the screensaver if it's enabled & nobody's been around for long enough.
+    fn check_screensaver(&mut self) {
+        if let Some(after) = self.screensaver_after
+            && self.last_input.elapsed() >= after
+            && !self.pipeline.is_screensaving()
+        {
+            self.pipeline.start_screensaver(self.screensaver_morph_time);
+        }
+    }
+
+    /// Finishes the sweep once every frame of it has been saved, & says whether it did, so we can
+    /// close now there's nothing left to do.
+    fn check_sweep(&mut self) -> bool {
+        if self.sweep.as_ref().is_some_and(sweep::Sweep::is_finished)
+            && let Some(sweep) = self.sweep.take()
+        {
+            sweep.finish();
+            return true;
+        }
+        false
+    }
+
+    /// Whether nothing is going on that needs rendering at full speed: no music playing, and no
+    /// keys pressed in a while. While paused, nothing changes on screen until a key is pressed
+    /// (which wakes us up anyway), so there's no need to wait.
+    fn is_idle(&self) -> bool {
+        let playing = self
+            .audio
+            .as_ref()
+            .is_some_and(|audio| !audio.sink.is_paused());
+        self.idle_frame_time.is_some()
+            && !playing
+            && self.sweep.is_none()
+            && (self.pipeline.is_paused() || self.last_input.elapsed() >= IDLE_AFTER)
+    }
+
+    /// Requests the next frame, either right away or after a while if we're idle.
+    fn schedule_redraw(&mut self) {
+        match self.idle_frame_time {
+            // Nothing gets drawn, but the music still needs analyzing every so often
+            _ if self.minimized => {
+                self.idle_until = Some(Instant::now() + MINIMIZED_FRAME_TIME);
+            }
+            Some(frame_time) if self.is_idle() => {
+                self.idle_until = Some(Instant::now() + frame_time);
+            }
+            _ => self.get_window().request_redraw(),
+        }
+    }
+
+    /// Goes back to rendering at full speed & stops any screensaver, e.g. because a key was
+    /// pressed.
+    fn wake(&mut self) {
+        self.last_input = Instant::now();
+        self.pipeline.stop_screensaver();
+        if self.idle_until.take().is_some() {
+            self.get_window().request_redraw();
+        }
+    }
+
+    /// Loads any files the user picked since we last checked.
+    fn check_picker(&mut self) {
+        while let Some(picked) = self.picker.try_recv() {
+            match picked {
+                picker::Picked::Music(file) => {
+                    // Drop the previous music first, so the two don't play over each other
+                    self.audio = None;
+                    self.audio = Audio::open_or_report(&file, &self.audio_options);
+                    self.pipeline.set_playing(self.audio.is_some());
+                }
+                picker::Picked::Settings(file) => {
+                    // A different file has different presets, so start again from the first one
+                    self.pipeline.read_settings_file(&self.queue, file);
+                }
+            }
+        }
+    }
+
+    /// Carries out any remote control requests that arrived since we last checked.
+    fn check_remote(&mut self) {
+        while let Some(action) = self.remote.as_ref().and_then(remote::Remote::try_recv) {
+            // Counts as interaction just like a keypress does
+            self.slideshow_last_change = Instant::now();
+            self.wake();
+            self.perform(action);
+        }
+    }
+
+    /// Carries out an action, whether it came from a keypress or over remote control.
+    fn perform(&mut self, action: Action) {
+        if matches!(action, Action::Play | Action::Pause | Action::TogglePause) {
+            // Already decided whether we're paused, so keys go back to normal
+            self.resume_on_key = false;
+        }
+        match action {
+            Action::PreviousPreset => self.pipeline.previous_preset(),
+            Action::NextPreset => self.pipeline.next_preset(),
+            Action::Preset(index) => self.pipeline.set_preset(index),
+            Action::Param(param, value) => self.pipeline.set_base_param(param, value),
+            Action::Play => self.set_paused(false),
+            Action::Pause => self.set_paused(true),
+            Action::TogglePause => self.set_paused(!self.pipeline.is_paused()),
+        }
+    }
+
+    /// Solos the next channel of the music, going back to all of them after the last.
+    fn solo_next_channel(&mut self) {
+        let Some(audio) = &self.audio else {
+            println!("No music to solo a channel of");
+            return;
+        };
+        let mut collector = audio.collector.lock().unwrap();
+        let channels = collector.channels();
+        let solo = match self.audio_options.solo_channel {
+            _ if channels <= 1 => {
+                println!("The music only has one channel to play");
+                return;
+            }
+            None => Some(0),
+            Some(channel) => Some(channel + 1).filter(|&channel| channel < channels),
+        };
+        collector.set_solo(solo);
+        self.audio_options.solo_channel = solo;
+        match solo {
+            Some(channel) => println!("Soloing channel {} of {channels}", channel + 1),
+            None => println!("Playing all {channels} channels"),
+        }
+    }
+
+    /// Handles keys pressed along with Ctrl (or Cmd). Any that aren't shortcuts act like they
+    /// were pressed on their own.
+    fn handle_shortcut_key(&mut self, key: KeyCode, repeat: bool) -> bool {
+        match key {
+            // Stash settings to compare, & switch between them
+            KeyCode::KeyA if !repeat => self.pipeline.store_slot(fs::Slot::A),
+            KeyCode::KeyB if !repeat => self.pipeline.store_slot(fs::Slot::B),
+            KeyCode::Tab if !repeat => self.pipeline.switch_slot(),
+            KeyCode::KeyA | KeyCode::KeyB | KeyCode::Tab => {}
+            // Alongside J/L for move speed, since the two shape patterns together
+            KeyCode::KeyJ | KeyCode::KeyL => {
+                self.pipeline.nudge_turn_strength(key == KeyCode::KeyL);
+            }
+            // How much the music moves everything, whatever mode we're in
+            KeyCode::ArrowUp | KeyCode::ArrowDown => {
+                self.pipeline.nudge_reactivity(key == KeyCode::ArrowUp);
+            }
+            // How the trail's brightness is mapped, like Left/Right & ,/. nudge parameters
+            KeyCode::ArrowLeft | KeyCode::ArrowRight => {
+                self.pipeline.nudge_exposure(key == KeyCode::ArrowRight);
+            }
+            KeyCode::Comma | KeyCode::Period => {
+                self.pipeline.nudge_gamma(key == KeyCode::Period);
+            }
+            // Like -/= nudge the selected parameter, but for how much trail gets laid down
+            KeyCode::Minus | KeyCode::Equal => {
+                self.pipeline.nudge_deposit_factor(key == KeyCode::Equal);
+            }
+            // How strongly particles spread out from each other
+            KeyCode::KeyO | KeyCode::KeyP => {
+                self.pipeline.nudge_repulsion(key == KeyCode::KeyP);
+            }
+            // Move the preset itself, rather than going to the one next to it
+            KeyCode::BracketLeft | KeyCode::BracketRight => {
+                self.pipeline.move_preset(key == KeyCode::BracketRight);
+            }
+            KeyCode::KeyS if !repeat => self.solo_next_channel(),
+            KeyCode::KeyS => {}
+            KeyCode::KeyH if !repeat => self.pipeline.toggle_heatmap(&self.queue),
+            KeyCode::KeyH => {}
+            KeyCode::KeyM if !repeat => self.pipeline.toggle_resolved(),
+            KeyCode::KeyM => {}
+            KeyCode::KeyF if !repeat => self.pipeline.toggle_raw_bins(),
+            KeyCode::KeyF => {}
+            // Only lasts while it's held, see the key being released in `window_event()`
+            KeyCode::KeyI => self.pipeline.set_inverted(true),
+            KeyCode::KeyR if !repeat => self.pipeline.reload_settings_file(&self.queue),
+            KeyCode::KeyR => {}
+            KeyCode::KeyE if !repeat => {
+                let settings = &self.pipeline.settings().get_settings().base.current;
+                println!("{}", settings.to_rust_literal());
+            }
+            KeyCode::KeyE => {}
+            KeyCode::KeyT if !repeat => {
+                if self.pipeline.toggle_frame_stats() {
+                    println!("Showing frame stats");
+                } else {
+                    println!("Hiding frame stats");
+                }
+            }
+            KeyCode::KeyT => {}
+            _ => return false,
+        }
+        true
+    }
+
+    fn handle_file_key(&mut self, key: KeyCode, repeat: bool) -> bool {
+        match key {
+            KeyCode::F7 if !repeat => self.picker.pick_music(),
+            KeyCode::F8 if !repeat => self.picker.pick_settings(),
+            KeyCode::F12 if !repeat => {
+                self.screenshot_requested = true;
+                self.pipeline.request_capture();
+            }
+            KeyCode::End if !repeat => {
+                if self.recording.is_some() {
+                    self.stop_recording();
+                } else {
+                    println!(
+                        "Recording a GIF for the next {} seconds, press End to stop early",
+                        gif::DURATION.as_secs()
+                    );
+                    self.recording = Some(gif::Recording::start(Instant::now()));
+                    self.pipeline.set_recording(true);
+                }
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Prints everything that's useful to know for a bug report, all in one place.
+    fn print_diagnostics(&self) {
+        let info = &self.adapter_info;
+        let config = self.surface_config();
+        let settings = self.pipeline.settings();
+        println!("--- Diagnostics ---");
+        println!("Adapter: {} ({:?})", info.name, info.device_type);
+        println!("Backend: {}", info.backend);
+        println!("Driver: {} {}", info.driver, info.driver_info);
+        println!("Surface: {:?}, {:?}", config.format, config.present_mode);
+        println!("Window: {}x{}", self.size.width, self.size.height);
+        println!(
+            "Simulation: {}x{}, {} particles",
+            constants::SIMULATION_WIDTH,
+            constants::SIMULATION_HEIGHT,
+            constants::SIMULATION_NUM_PARTICLES
+        );
+        println!(
+            "Preset: {} of {}{}",
+            settings.get_index() + 1,
+            settings.num_presets(),
+            if settings.get_dirty() {
+                " (edited)"
+            } else {
+                ""
+            }
+        );
+        match &self.audio {
+            Some(audio) if audio.sink.is_paused() => println!("Audio: paused"),
+            Some(_) => println!("Audio: playing"),
+            None => println!("Audio: none"),
+        }
+        println!("Paused: {}", self.pipeline.is_paused());
+        println!("-------------------");
+    }
+
+    /// Pauses or resumes both the simulation & the music.
+    fn set_paused(&mut self, paused: bool) {
+        self.pipeline.set_paused(paused);
+        if let Some(audio) = &self.audio {
+            if paused {
+                audio.sink.pause();
+            } else {
+                audio.sink.play();
+            }
+        }
+        self.pipeline.set_playing(!paused);
+    }
+
+    /// Stops recording a GIF, if we were, and saves what was recorded.
+    fn stop_recording(&mut self) {
+        if let Some(recording) = self.recording.take() {
+            recording.save();
+            self.pipeline.set_recording(false);
+        }
+    }
+
+    /// Handles keys that control the music. Holding Shift seeks further.
+    fn handle_music_key(&mut self, key: KeyCode, repeat: bool, shift: bool) -> bool {
         let audio = match self.audio.as_mut() {
             Some(audio) => audio,
             None => return false,
         };
+        let seek_step = if shift {
+            self.audio_options.seek_step * BIG_SEEK_MULTIPLIER
+        } else {
+            self.audio_options.seek_step
+        };
         match key {
             KeyCode::F2 => {
                 let pos = audio.sink.get_pos();
-                let next_pos = pos.saturating_sub(Duration::from_secs(10));
+                let next_pos = pos.saturating_sub(seek_step);
                 match audio.sink.try_seek(next_pos) {
                     Ok(()) => {}
                     Err(err) => eprintln!("Error seeking backwards: {err}"),
                 };
+                audio.shown_position.snap();
                 true
             }
             KeyCode::F3 if !repeat => {
@@ -222,11 +1257,12 @@ impl State {
             }
             KeyCode::F4 => {
                 let pos = audio.sink.get_pos();
-                let next_pos = pos.saturating_add(Duration::from_secs(10));
+                let next_pos = pos.saturating_add(seek_step);
                 match audio.sink.try_seek(next_pos) {
                     Ok(()) => {}
                     Err(err) => eprintln!("Error seeking forwards: {err}"),
                 };
+                audio.shown_position.snap();
                 true
             }
             _ => false,
@@ -235,53 +1271,114 @@ impl State {
 }
 
 struct App {
-    flags: flags::Main,
+    /// Shared with setting everything up, which might not be done until later.
+    flags: Rc<flags::Run>,
+    settings_filename: PathBuf,
     close_requested: bool,
+    /// Which modifier keys are held down.
+    modifiers: ModifiersState,
+    /// Where to go fullscreen, if `--monitor` picked somewhere other than wherever the window is.
+    monitor: Option<MonitorHandle>,
+    /// Where everything gets sent once it's set up, if that isn't done straight away.
+    proxy: EventLoopProxy<State>,
+    /// Everything, once it's been set up.
     state: Option<State>,
 }
 
-impl ApplicationHandler for App {
+/// The monitor `--monitor` asked for, counting from 1 in the order the system lists them.
+fn pick_monitor(event_loop: &ActiveEventLoop, number: usize) -> Option<MonitorHandle> {
+    let monitors = event_loop.available_monitors().collect::<Vec<_>>();
+    match number.checked_sub(1).and_then(|index| monitors.get(index)) {
+        Some(monitor) => Some(monitor.clone()),
+        None => {
+            eprintln!(
+                "Monitor must be between 1 & {}, got {number}",
+                monitors.len()
+            );
+            eprintln!("Falling back to the primary monitor...");
+            event_loop.primary_monitor()
+        }
+    }
+}
+
+impl ApplicationHandler<State> for App {
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {
         // Create window object
-        let window_attributes = Window::default_attributes().with_title("physarum-36p-rs");
+        if let Some(number) = self.flags.monitor {
+            self.monitor = pick_monitor(event_loop, number);
+        }
+        let mut window_attributes =
+            Current::window_attributes(Window::default_attributes().with_title("physarum-36p-rs"));
+        if let Some(monitor) = &self.monitor {
+            window_attributes = window_attributes.with_position(monitor.position());
+        }
+        if self.flags.fullscreen {
+            // With no monitor, winit goes fullscreen on whichever one the window opens on
+            window_attributes = window_attributes
+                .with_fullscreen(Some(Fullscreen::Borderless(self.monitor.clone())));
+        }
         let window = Arc::new(event_loop.create_window(window_attributes).unwrap());
 
-        let state = pollster::block_on(State::new(&self.flags, window.clone()));
-        self.state = Some(state);
+        let flags = self.flags.clone();
+        let settings_filename = self.settings_filename.clone();
+        let setup = async move { State::new(&flags, settings_filename, window).await };
+        if let Some(state) = Current::start(setup, self.proxy.clone()) {
+            self.user_event(event_loop, state);
+        }
+    }
 
-        window.request_redraw();
+    /// Everything's been set up, so it can start being drawn.
+    fn user_event(&mut self, _event_loop: &ActiveEventLoop, state: State) {
+        state.get_window().request_redraw();
+        self.state = Some(state);
     }
 
     fn window_event(&mut self, _event_loop: &ActiveEventLoop, _id: WindowId, event: WindowEvent) {
-        let state = self.state.as_mut().unwrap();
+        // Nothing to do until everything's set up
+        let Some(state) = self.state.as_mut() else {
+            return;
+        };
         match event {
             WindowEvent::CloseRequested => {
                 println!("The close button was pressed; stopping");
                 self.close_requested = true;
             }
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.modifiers = modifiers.state();
+                // Alt isn't used for anything else, so holding it on its own can be the magnifier
+                state.pipeline.set_magnifying(self.modifiers.alt_key());
+            }
             WindowEvent::RedrawRequested => {
-                let data = state.audio.as_ref().map(|audio| AudioDisplay {
+                let data = state.audio.as_mut().map(|audio| AudioDisplay {
+                    position: audio.shown_position(),
                     bins: audio.last_bins,
-                    position: audio.sink.get_pos(),
+                    raw_bins: audio.last_raw_bins,
+                    balance: *audio.balance.lock().unwrap(),
                     total_duration: audio.total_duration,
                 });
                 state.render(data.as_ref());
+                state.check_pacing(self.flags.frame_stats);
+                state.check_slideshow();
+                state.check_screensaver();
+                state.check_picker();
+                state.check_remote();
+                if state.check_sweep() {
+                    self.close_requested = true;
+                }
 
                 // Request another redraw after this one so we keep a consistent framerate
-                state.get_window().request_redraw();
+                state.schedule_redraw();
 
                 if let Some(audio) = &mut state.audio {
-                    // Request another batch of fft work after this one
-                    audio::worker::submit_work(&audio.tx);
-                    audio.last_bins = audio
-                        .bins
-                        .lock()
-                        .unwrap()
-                        .iter()
-                        .map(Clone::clone)
-                        .collect::<Vec<_>>()
-                        .try_into()
-                        .expect("wrong number of bins");
+                    audio.keep_looping();
+                    // Request another batch of fft work after this one, for what's being heard now
+                    let position = audio.stream_position().checked_sub(audio.latency);
+                    audio::worker::submit_work(&audio.tx, position);
+                    audio.last_bins = read_bins(&audio.bins);
+                    audio.last_raw_bins = read_bins(&audio.raw_bins);
+                }
+                if let Some(osc) = &state.osc {
+                    osc.set_bins(state.audio.as_ref().map(|audio| audio.last_bins));
                 }
             }
             WindowEvent::Resized(size) => {
@@ -289,6 +1386,33 @@ impl ApplicationHandler for App {
                 // here as this event is always followed up by redraw request.
                 state.resize(size);
             }
+            WindowEvent::ScaleFactorChanged { .. } => {
+                // Moved to a screen with a different DPI, so the header needs laying out again.
+                // Any change in size comes separately as a resize.
+                state.configure_surface();
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                state.pipeline.set_cursor(Some(position));
+            }
+            WindowEvent::CursorLeft { .. } => {
+                state.pipeline.set_cursor(None);
+            }
+            WindowEvent::MouseInput {
+                state: button_state,
+                button,
+                ..
+            } => {
+                let stroke = match button {
+                    MouseButton::Left => graphics::Stroke::Paint,
+                    MouseButton::Right => graphics::Stroke::Erase,
+                    _ => return,
+                };
+                state.slideshow_last_change = Instant::now();
+                state.wake();
+                state
+                    .pipeline
+                    .set_stroke(&state.queue, button_state.is_pressed().then_some(stroke));
+            }
             WindowEvent::KeyboardInput {
                 event:
                     KeyEvent {
@@ -299,14 +1423,28 @@ impl ApplicationHandler for App {
                     },
                 ..
             } => {
+                state.pipeline.disarm_delete();
                 // Toggle fullscreen
                 let window = state.get_window();
                 if window.fullscreen().is_some() {
                     window.set_fullscreen(None);
                 } else {
-                    window.set_fullscreen(Some(Fullscreen::Borderless(window.current_monitor())));
+                    let monitor = self.monitor.clone().or_else(|| window.current_monitor());
+                    window.set_fullscreen(Some(Fullscreen::Borderless(monitor)));
                 }
             }
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        physical_key: PhysicalKey::Code(KeyCode::KeyI),
+                        state: ElementState::Released,
+                        ..
+                    },
+                ..
+            } => {
+                // Whether or not Ctrl is still held, so the flash can't get stuck on
+                state.pipeline.set_inverted(false);
+            }
             WindowEvent::KeyboardInput {
                 event:
                     KeyEvent {
@@ -317,7 +1455,59 @@ impl ApplicationHandler for App {
                     },
                 ..
             } => {
-                if state.handle_music_key(key, repeat) {
+                // Only pressing F9 again goes through with deleting a preset
+                if key != KeyCode::F9 {
+                    state.pipeline.disarm_delete();
+                }
+                // Ctrl (or Cmd) + Q/W, for when there's no close button to reach, like in
+                // fullscreen. Escape is already taken by leaving modes.
+                if (self.modifiers.control_key() || self.modifiers.super_key())
+                    && matches!(key, KeyCode::KeyQ | KeyCode::KeyW)
+                {
+                    println!("Quit was pressed; stopping");
+                    self.close_requested = true;
+                    return;
+                }
+                if (self.modifiers.control_key() || self.modifiers.super_key())
+                    && key == KeyCode::KeyD
+                {
+                    if !repeat {
+                        state.print_diagnostics();
+                    }
+                    return;
+                }
+                // Any interaction holds off the slideshow, and gets us rendering at full speed
+                state.slideshow_last_change = Instant::now();
+                let screensaving = state.pipeline.is_screensaving();
+                state.wake();
+                if screensaving {
+                    // Just brings the preset back, rather than doing whatever the key normally does
+                    return;
+                }
+                if std::mem::take(&mut state.resume_on_key) {
+                    // Just starts everything off, rather than doing whatever the key normally does
+                    state.set_paused(false);
+                    return;
+                }
+                if (self.modifiers.control_key() || self.modifiers.super_key())
+                    && state.handle_shortcut_key(key, repeat)
+                {
+                    return;
+                }
+                if let Some(action) = state.pipeline.key_action(key, repeat) {
+                    state.perform(action);
+                    return;
+                }
+                if key == KeyCode::Tab {
+                    state
+                        .pipeline
+                        .step_param(&state.queue, !self.modifiers.shift_key());
+                    return;
+                }
+                if state.handle_file_key(key, repeat) {
+                    return;
+                }
+                if state.handle_music_key(key, repeat, self.modifiers.shift_key()) {
                     return;
                 }
                 state.pipeline.handle_keypress(&state.queue, key);
@@ -326,10 +1516,24 @@ impl ApplicationHandler for App {
         }
     }
 
+    fn new_events(&mut self, _event_loop: &ActiveEventLoop, cause: StartCause) {
+        if let StartCause::ResumeTimeReached { .. } = cause
+            && let Some(state) = self.state.as_mut()
+            && state.idle_until.take().is_some()
+        {
+            state.get_window().request_redraw();
+        }
+    }
+
     fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
         if self.close_requested {
             event_loop.exit();
         }
+        // While idle, sleep until the next frame is due instead of spinning
+        match self.state.as_ref().and_then(|state| state.idle_until) {
+            Some(idle_until) => event_loop.set_control_flow(ControlFlow::WaitUntil(idle_until)),
+            None => event_loop.set_control_flow(ControlFlow::Poll),
+        }
     }
 }
 
@@ -338,12 +1542,106 @@ mod flags {
 
     xflags::xflags! {
         cmd main {
-            optional --music file: PathBuf
             optional --settings file: PathBuf
+            default cmd run {
+                optional --preset number: usize
+                optional --music file: PathBuf
+                optional --mute
+                optional --audio-device name: String
+                optional --list-audio-devices
+                optional --normalize-loudness
+                optional --loudness-target lufs: f32
+                optional --agc
+                optional --agc-attack-ms ms: u64
+                optional --agc-release-ms ms: u64
+                optional --agc-max-gain gain: f32
+                optional --audio-latency-ms ms: u64
+                optional --seek-secs secs: f32
+                optional --position-smoothing-ms ms: u64
+                optional --loop-music
+                optional --solo-channel channel: usize
+                optional --frame-stats
+                optional --backend name: String
+                optional --slideshow-secs secs: u64
+                optional --screensaver-secs secs: u64
+                optional --screensaver-morph-secs secs: f32
+                optional --count-scale count: f32
+                optional --bounded
+                optional --distribution name: String
+                optional --particle-lifetime steps: u32
+                optional --pixelated
+                optional --rotate degrees: u32
+                optional --ui-scale factor: f32
+                optional --no-header
+                optional --text-shadow
+                optional --visualizer-radius px: f32
+                optional --visualizer-ring-width px: f32
+                optional --visualizer-line-width px: f32
+                optional --aspect ratio: String
+                optional --background file: PathBuf
+                optional --feedback amount: f32
+                optional --glow intensity: f32
+                optional --glow-threshold level: f32
+                optional --idle-frame-ms ms: u64
+                optional --rewind-snapshots count: usize
+                optional --rewind-interval-ms ms: u64
+                optional --trigger source: String
+                optional --beat-spawn count: usize
+                optional --remote address: String
+                optional --osc-out address: String
+                optional --osc-prefix prefix: String
+                optional --osc-bin-addresses list: String
+                optional --osc-rate hz: f32
+                optional --timelapse-interval interval: String
+                optional --timelapse-dir path: PathBuf
+                optional --sweep spec: String
+                optional --sweep-dir path: PathBuf
+                optional --start-paused
+                optional --monitor number: usize
+                optional --fullscreen
+            }
+            cmd list {}
+            cmd check {}
+            cmd benchmark {
+                optional --target-fps fps: f32
+                optional --backend name: String
+            }
+        }
+    }
+
+    impl Main {
+        pub fn settings_filename(&self) -> PathBuf {
+            self.settings.clone().unwrap_or("settings.json".into())
         }
     }
 }
 
+/// Prints a summary of every preset in a settings file, without opening a window.
+fn list_presets(path: PathBuf) {
+    let settings = match fs::AllSettings::read(path.clone()) {
+        Ok(settings) => settings,
+        Err(e) => {
+            eprintln!("Error loading settings from {}: {e}", path.display());
+            std::process::exit(1);
+        }
+    };
+    for (index, preset) in settings.presets().iter().enumerate() {
+        let fs::point_settings::PointSettings {
+            sd0,
+            sa0,
+            ra0,
+            md0,
+            dsf,
+            ..
+        } = &preset.base.current;
+        println!(
+            "{:>3}: SD0:{sd0:.3} SA0:{sa0:.3} RA0:{ra0:.3} MD0:{md0:.3} DSF:{dsf:.3}",
+            index + 1
+        );
+    }
+    println!("{} presets in {}", settings.num_presets(), path.display());
+}
+
 fn main() {
     // wgpu uses `log` for all of our logging, so we initialize a logger with the `env_logger` crate.
     //
@@ -351,7 +1649,48 @@ fn main() {
     // documentation for more information.
     env_logger::init();
 
-    let event_loop = EventLoop::new().unwrap();
+    let flags = flags::Main::from_env_or_exit();
+    let settings_filename = flags.settings_filename();
+    let flags = match flags.subcommand {
+        flags::MainCmd::Run(run) => run,
+        flags::MainCmd::List(_) => {
+            list_presets(settings_filename);
+            return;
+        }
+        flags::MainCmd::Check(_) => {
+            if !check::run(settings_filename) {
+                std::process::exit(1);
+            }
+            return;
+        }
+        flags::MainCmd::Benchmark(benchmark) => {
+            let backends = benchmark
+                .backend
+                .as_deref()
+                .and_then(parse_backend)
+                .unwrap_or(wgpu::Backends::all());
+            let target_fps = match benchmark.target_fps {
+                Some(fps) if fps > 0.0 && fps.is_finite() => fps,
+                Some(fps) => {
+                    eprintln!("Target frame rate must be more than 0, got {fps}");
+                    eprintln!("Falling back to {}...", benchmark::DEFAULT_TARGET_FPS);
+                    benchmark::DEFAULT_TARGET_FPS
+                }
+                None => benchmark::DEFAULT_TARGET_FPS,
+            };
+            if !benchmark::run(settings_filename, backends, target_fps) {
+                std::process::exit(1);
+            }
+            return;
+        }
+    };
+
+    if flags.list_audio_devices {
+        Current::list_audio_devices();
+        return;
+    }
+
+    let event_loop = EventLoop::with_user_event().build().unwrap();
 
     // When the current loop iteration finishes, immediately begin a new
     // iteration regardless of whether or not new events are available to
@@ -365,10 +1704,14 @@ fn main() {
     // the background.
     // event_loop.set_control_flow(ControlFlow::Wait);
 
-    let mut app = App {
-        flags: flags::Main::from_env_or_exit(),
+    let app = App {
+        flags: Rc::new(flags),
+        settings_filename,
         state: None,
         close_requested: false,
+        modifiers: ModifiersState::empty(),
+        monitor: None,
+        proxy: event_loop.create_proxy(),
     };
-    event_loop.run_app(&mut app).unwrap();
+    Current::run(event_loop, app);
 }
@@ -0,0 +1,151 @@
+//! Sends the music analysis out over OSC, so lighting rigs or other visuals can react to the same
+//! thing the simulation does. Sending happens at its own steady rate on a separate thread, however
+//! fast frames are being drawn, so the network doesn't get flooded.
+//!
+//! Like triggers, this is written without any dependencies. Each time it sends these messages,
+//! under the address prefix (`/physarum` by default):
+//!
+//! - `<prefix>/bins` with the level of each FFT bin, lowest frequencies first
+//! - `<prefix>/0`, `<prefix>/1`, & so on with the level of just that bin, unless it's been given
+//!   an address of its own
+//! - `<prefix>/beat` with how strong the beat is right now, between 0 & 1, or 0 if there isn't one
+
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, ToSocketAddrs, UdpSocket};
+use std::sync::{Arc, Mutex};
+
+use web_time::{Duration, Instant};
+
+use crate::audio::NUM_BINS;
+use crate::graphics::Beats;
+
+/// Where messages get sent to. A port on its own sends to localhost.
+pub fn address(s: &str) -> Result<SocketAddr, String> {
+    if let Ok(port) = s.parse() {
+        return Ok(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), port));
+    }
+    s.to_socket_addrs()
+        .ok()
+        .and_then(|mut addresses| addresses.next())
+        .ok_or_else(|| format!("expected a port or an address like 127.0.0.1:9000, got {s}"))
+}
+
+/// The address to send each bin's level to on its own, from a comma separated `list` of them.
+/// Bins left out or left blank are sent to `<prefix>/N` instead.
+pub fn bin_addresses(list: Option<&str>, prefix: &str) -> [String; NUM_BINS] {
+    let mut given = list.into_iter().flat_map(|list| list.split(','));
+    let addresses = std::array::from_fn(|bin| {
+        let fallback = format!("{prefix}/{bin}");
+        match given.next().map(str::trim) {
+            Some(address) if address.starts_with('/') => address.to_string(),
+            Some("") | None => fallback,
+            Some(address) => {
+                eprintln!("OSC addresses must start with /, got {address:?}");
+                eprintln!("Falling back to {fallback}...");
+                fallback
+            }
+        }
+    });
+    if given.next().is_some() {
+        eprintln!("There are only {NUM_BINS} bins to give OSC addresses to");
+        eprintln!("Continuing without the rest...");
+    }
+    addresses
+}
+
+pub struct Sender {
+    /// The newest bins, if there's any music. The thread sends whatever's here when it's next due.
+    bins: Arc<Mutex<Option<[f32; NUM_BINS]>>>,
+}
+
+impl Sender {
+    /// Starts sending to `address` in the background, `rate` times a second.
+    pub fn start(
+        address: SocketAddr,
+        prefix: String,
+        bin_addresses: [String; NUM_BINS],
+        rate: f32,
+    ) -> std::io::Result<Self> {
+        let unspecified = match address {
+            SocketAddr::V4(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            SocketAddr::V6(_) => IpAddr::V6(std::net::Ipv6Addr::UNSPECIFIED),
+        };
+        let socket = UdpSocket::bind(SocketAddr::new(unspecified, 0))?;
+        socket.connect(address)?;
+        println!("Sending OSC to {address}");
+
+        let bins = Arc::new(Mutex::new(None));
+        let shared = bins.clone();
+        let interval = Duration::from_secs_f32(1.0 / rate);
+        std::thread::spawn(move || {
+            let mut beats = Beats::default();
+            let mut next = Instant::now();
+            // Only says so the first time, since it'll likely keep failing the same way
+            let mut reported = false;
+            loop {
+                next += interval;
+                std::thread::sleep(next.saturating_duration_since(Instant::now()));
+                let Some(bins) = *shared.lock().unwrap() else {
+                    continue;
+                };
+                let beat = beats.update(&bins, Instant::now()).unwrap_or(0.0);
+                let result = socket
+                    .send(&message(&format!("{prefix}/bins"), &bins))
+                    .and_then(|_| {
+                        bin_addresses
+                            .iter()
+                            .zip(bins)
+                            .try_for_each(|(address, level)| {
+                                socket.send(&message(address, &[level])).map(|_| ())
+                            })
+                    })
+                    .and_then(|_| socket.send(&message(&format!("{prefix}/beat"), &[beat])));
+                if let Err(e) = result
+                    && !std::mem::replace(&mut reported, true)
+                {
+                    eprintln!("Error sending OSC to {address}: {e}");
+                }
+            }
+        });
+        Ok(Self { bins })
+    }
+
+    /// Sets the bins to send from now on, or stops sending with `None` while there's no music.
+    pub fn set_bins(&self, bins: Option<[f32; NUM_BINS]>) {
+        *self.bins.lock().unwrap() = bins;
+    }
+}
+
+/// Encodes an OSC message with some float arguments.
+fn message(address: &str, values: &[f32]) -> Vec<u8> {
+    /// OSC strings end with at least one null, & are padded out to a multiple of 4 bytes.
+    fn push_string(out: &mut Vec<u8>, s: &str) {
+        out.extend_from_slice(s.as_bytes());
+        out.resize((out.len() / 4 + 1) * 4, 0);
+    }
+
+    let mut out = Vec::new();
+    push_string(&mut out, address);
+    push_string(&mut out, &format!(",{}", "f".repeat(values.len())));
+    for value in values {
+        out.extend_from_slice(&value.to_be_bytes());
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bins_without_addresses_fall_back_to_their_number() {
+        let addresses = bin_addresses(Some("/kick, ,nope"), "/physarum");
+        assert_eq!(addresses[0], "/kick");
+        assert_eq!(addresses[1], "/physarum/1");
+        assert_eq!(addresses[2], "/physarum/2");
+        assert_eq!(
+            addresses[NUM_BINS - 1],
+            format!("/physarum/{}", NUM_BINS - 1)
+        );
+        assert_eq!(bin_addresses(None, "/p")[0], "/p/0");
+    }
+}
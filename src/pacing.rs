@@ -0,0 +1,89 @@
+//! Tracks how long frames are taking, so we can tell when the simulation is too heavy for the
+//! display it's running on.
+
+use web_time::{Duration, Instant};
+
+/// How often we summarize the frames we've seen.
+const REPORT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A frame counts as "dropped" if it took this many refresh periods or more to arrive. Leaves a
+/// bit of slack for timer jitter.
+const DROPPED_FRAME_THRESHOLD: f32 = 1.5;
+
+pub struct FramePacing {
+    /// How long a single refresh of the display takes, if we know it.
+    refresh_period: Option<Duration>,
+    /// When the previous frame was rendered.
+    last_frame: Option<Instant>,
+    /// When the current report interval started.
+    interval_start: Instant,
+    /// Number of frames rendered in the current report interval.
+    frames: u32,
+    /// Number of frames in the current report interval that took longer than the refresh period.
+    dropped: u32,
+    /// The longest frame in the current report interval.
+    longest: Duration,
+}
+
+/// Summary of the frames rendered over the last `REPORT_INTERVAL`.
+pub struct Report {
+    pub fps: f32,
+    pub frames: u32,
+    pub dropped: u32,
+    pub longest: Duration,
+}
+
+impl FramePacing {
+    pub fn new(refresh_period: Option<Duration>) -> Self {
+        Self {
+            refresh_period,
+            last_frame: None,
+            interval_start: Instant::now(),
+            frames: 0,
+            dropped: 0,
+            longest: Duration::ZERO,
+        }
+    }
+
+    /// Converts a monitor's refresh rate, as reported by winit, into a refresh period.
+    pub fn period_from_millihertz(millihertz: Option<u32>) -> Option<Duration> {
+        millihertz
+            .filter(|&mhz| mhz > 0)
+            .map(|mhz| Duration::from_secs_f64(1000.0 / mhz as f64))
+    }
+
+    pub fn set_refresh_period(&mut self, refresh_period: Option<Duration>) {
+        self.refresh_period = refresh_period;
+    }
+
+    /// Records that a frame was just rendered. Returns a report once every `REPORT_INTERVAL`.
+    pub fn frame(&mut self, now: Instant) -> Option<Report> {
+        if let Some(last_frame) = self.last_frame.replace(now) {
+            let delta = now.duration_since(last_frame);
+            self.longest = self.longest.max(delta);
+            if let Some(period) = self.refresh_period
+                && delta.as_secs_f32() >= period.as_secs_f32() * DROPPED_FRAME_THRESHOLD
+            {
+                self.dropped += 1;
+            }
+        }
+        self.frames += 1;
+
+        let elapsed = now.duration_since(self.interval_start);
+        if elapsed < REPORT_INTERVAL {
+            return None;
+        }
+
+        let report = Report {
+            fps: self.frames as f32 / elapsed.as_secs_f32(),
+            frames: self.frames,
+            dropped: self.dropped,
+            longest: self.longest,
+        };
+        self.interval_start = now;
+        self.frames = 0;
+        self.dropped = 0;
+        self.longest = Duration::ZERO;
+        Some(report)
+    }
+}
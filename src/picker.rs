@@ -0,0 +1,71 @@
+//! File dialogs for loading music & settings files while running. The dialogs don't block the
+//! event loop, and send back whatever was picked.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+
+use crate::platform::{Current, Platform};
+
+/// A file the user picked, along with what they want to do with it.
+pub enum Picked {
+    Music(PathBuf),
+    Settings(PathBuf),
+}
+
+pub struct Picker {
+    tx: mpsc::Sender<Picked>,
+    rx: mpsc::Receiver<Picked>,
+    /// Whether there's a dialog open already, so we don't end up with a pile of them.
+    open: Arc<AtomicBool>,
+}
+
+impl Picker {
+    pub fn new() -> Self {
+        let (tx, rx) = mpsc::channel();
+        Self {
+            tx,
+            rx,
+            open: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub fn pick_music(&self) {
+        self.pick("Open music", "Music", crate::audio::FORMATS, Picked::Music);
+    }
+
+    pub fn pick_settings(&self) {
+        self.pick("Open settings", "Settings", &["json"], Picked::Settings);
+    }
+
+    fn pick(
+        &self,
+        title: &str,
+        filter: &str,
+        extensions: &[&str],
+        to_picked: fn(PathBuf) -> Picked,
+    ) {
+        if self.open.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        let tx = self.tx.clone();
+        let open = self.open.clone();
+        let opened = Current::pick_file(title, filter, extensions, move |path| {
+            if let Some(path) = path {
+                // The receiver only goes away when we're shutting down, so it's fine to drop this
+                let _ = tx.send(to_picked(path));
+            }
+            open.store(false, Ordering::Release);
+        });
+        if let Err(e) = opened {
+            eprintln!("Can't open a file dialog, {e}");
+            self.open.store(false, Ordering::Release);
+        }
+    }
+
+    /// Returns the next file that was picked, if any.
+    pub fn try_recv(&self) -> Option<Picked> {
+        self.rx.try_recv().ok()
+    }
+}
@@ -0,0 +1,75 @@
+//! The pieces that have to work differently in a browser than on a desktop: where files & the font
+//! come from, file dialogs, the clipboard, playing music, background threads, & getting everything
+//! set up. Everything else reaches
+//! these through `Current`, rather than using the desktop-only crates directly.
+//!
+//! Whatever a browser can't do (music, files, dialogs, the clipboard) fails gracefully there, the
+//! same way it would on a desktop missing it.
+
+use std::future::Future;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+
+use winit::application::ApplicationHandler;
+use winit::event_loop::{EventLoop, EventLoopProxy};
+use winit::window::WindowAttributes;
+
+use crate::graphics::Image;
+
+#[cfg(not(target_arch = "wasm32"))]
+mod native;
+#[cfg(target_arch = "wasm32")]
+mod web;
+
+/// The platform being built for.
+#[cfg(not(target_arch = "wasm32"))]
+pub type Current = native::Native;
+/// The platform being built for.
+#[cfg(target_arch = "wasm32")]
+pub type Current = web::Web;
+
+pub trait Platform {
+    /// Gets the window ready to be created, e.g. by giving it somewhere on the page to go.
+    fn window_attributes(attributes: WindowAttributes) -> WindowAttributes;
+
+    /// Runs `setup` to get everything ready, giving back what it made if that was done straight
+    /// away. Otherwise, it's sent through `proxy` once it's done.
+    fn start<T: 'static>(
+        setup: impl Future<Output = T> + 'static,
+        proxy: EventLoopProxy<T>,
+    ) -> Option<T>;
+
+    /// Hands control over to the event loop, for as long as the app is running.
+    fn run<T: 'static>(event_loop: EventLoop<T>, app: impl ApplicationHandler<T> + 'static);
+
+    /// A monospace font to draw all the text in, as the contents of a TTF or OTF file.
+    fn monospace_font() -> Vec<u8>;
+
+    /// Opens a file to read, like `std::fs::File::open()`.
+    fn open_file(path: &Path) -> io::Result<Box<dyn io::Read>>;
+
+    /// Creates a file to write, like `std::fs::File::create()`.
+    fn create_file(path: &Path) -> io::Result<Box<dyn io::Write>>;
+
+    /// Opens a dialog to pick a file of one of these `extensions`, without waiting for it to be
+    /// closed. `picked` is called with whatever was picked, if anything.
+    fn pick_file(
+        title: &str,
+        filter: &str,
+        extensions: &[&str],
+        picked: impl FnOnce(Option<PathBuf>) + Send + 'static,
+    ) -> Result<(), String>;
+
+    /// Connects to the system clipboard, giving back where to send images to copy to it.
+    fn open_clipboard() -> Result<mpsc::Sender<Image>, String>;
+
+    /// Opens somewhere to play music, on the output device with this name if there's one given.
+    fn open_output_stream(device: Option<&str>) -> Result<rodio::OutputStream, String>;
+
+    /// Prints the names of every output device, grouped by host, for use with `--audio-device`.
+    fn list_audio_devices();
+
+    /// Does `work` in the background where that's possible, otherwise straight away.
+    fn spawn(work: impl FnOnce() + Send + 'static);
+}
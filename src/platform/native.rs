@@ -0,0 +1,180 @@
+use std::borrow::Cow;
+use std::future::Future;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+
+use font_kit::family_name::FamilyName;
+use font_kit::handle::Handle;
+use font_kit::properties::Properties;
+use font_kit::source::SystemSource;
+use rodio::DeviceTrait;
+use rodio::cpal::traits::HostTrait;
+use winit::application::ApplicationHandler;
+use winit::event_loop::{EventLoop, EventLoopProxy};
+use winit::window::WindowAttributes;
+
+use crate::graphics::Image;
+use crate::platform::Platform;
+use crate::screenshot;
+
+pub struct Native;
+
+impl Platform for Native {
+    fn window_attributes(attributes: WindowAttributes) -> WindowAttributes {
+        attributes
+    }
+
+    fn start<T: 'static>(
+        setup: impl Future<Output = T> + 'static,
+        _proxy: EventLoopProxy<T>,
+    ) -> Option<T> {
+        Some(pollster::block_on(setup))
+    }
+
+    fn run<T: 'static>(event_loop: EventLoop<T>, mut app: impl ApplicationHandler<T> + 'static) {
+        event_loop.run_app(&mut app).unwrap();
+    }
+
+    fn monospace_font() -> Vec<u8> {
+        let font_handle = SystemSource::new()
+            .select_best_match(&[FamilyName::Monospace], &Properties::new())
+            .expect("Did not find system monospace font");
+        match font_handle {
+            Handle::Memory { bytes, .. } => Vec::clone(&bytes),
+            Handle::Path { path, .. } => std::fs::read(path).expect("failed to read font file"),
+        }
+    }
+
+    fn open_file(path: &Path) -> io::Result<Box<dyn io::Read>> {
+        Ok(Box::new(std::fs::File::open(path)?))
+    }
+
+    fn create_file(path: &Path) -> io::Result<Box<dyn io::Write>> {
+        Ok(Box::new(std::fs::File::create(path)?))
+    }
+
+    fn pick_file(
+        title: &str,
+        filter: &str,
+        extensions: &[&str],
+        picked: impl FnOnce(Option<PathBuf>) + Send + 'static,
+    ) -> Result<(), String> {
+        let dialog = rfd::AsyncFileDialog::new()
+            .set_title(title)
+            .add_filter(filter, extensions);
+        // Run on its own thread so it doesn't block the event loop
+        std::thread::spawn(move || {
+            picked(pollster::block_on(dialog.pick_file()).map(|file| file.path().to_owned()));
+        });
+        Ok(())
+    }
+
+    /// On some platforms (X11 especially) whatever was copied only stays on the clipboard for as
+    /// long as the program that copied it keeps it there, so the clipboard lives on its own thread
+    /// for as long as we're running.
+    fn open_clipboard() -> Result<mpsc::Sender<Image>, String> {
+        let (tx, rx) = mpsc::channel::<Image>();
+        let (opened_tx, opened_rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            let mut clipboard = match arboard::Clipboard::new() {
+                Ok(clipboard) => {
+                    let _ = opened_tx.send(Ok(()));
+                    clipboard
+                }
+                Err(e) => {
+                    let _ = opened_tx.send(Err(e.to_string()));
+                    return;
+                }
+            };
+            while let Ok(image) = rx.recv() {
+                let data = arboard::ImageData {
+                    width: image.width as usize,
+                    height: image.height as usize,
+                    bytes: Cow::Borrowed(&image.rgba),
+                };
+                match clipboard.set_image(data) {
+                    Ok(()) => println!("Copied screenshot to the clipboard"),
+                    Err(e) => {
+                        eprintln!("Error copying screenshot to the clipboard: {e}");
+                        eprintln!("Saving it to a file instead...");
+                        screenshot::save(image);
+                    }
+                }
+            }
+        });
+        opened_rx
+            .recv()
+            .unwrap_or_else(|_| Err("the clipboard stopped unexpectedly".to_string()))?;
+        Ok(tx)
+    }
+
+    fn open_output_stream(device: Option<&str>) -> Result<rodio::OutputStream, String> {
+        /// Returns a PulseAudio device, if there is one.
+        /// cpal only supports ALSA on Linux, but fortunately that has a PulseAudio backend
+        /// which seems to be the thing we actually use on KDE for routing audio stuff.
+        fn find_pulse_device() -> Option<rodio::cpal::Device> {
+            #[cfg(target_os = "linux")]
+            for device in rodio::cpal::host_from_id(rodio::cpal::HostId::Alsa)
+                .expect("could not open host")
+                .output_devices()
+                .expect("could not enumerate output devices")
+            {
+                let name = device.name().expect("could not read device name");
+                if name == "pulse" {
+                    return Some(device);
+                }
+            }
+
+            None
+        }
+        let device = device.and_then(|name| {
+            let device = output_devices().find(|device| device.name().is_ok_and(|n| n == name));
+            if device.is_none() {
+                eprintln!("Could not find audio device {name:?}, falling back to the default...");
+                eprintln!("Run with --list-audio-devices to see which ones there are");
+            }
+            device
+        });
+        match device.or_else(find_pulse_device) {
+            Some(device) => rodio::OutputStreamBuilder::from_device(device),
+            None => rodio::OutputStreamBuilder::from_default_device(),
+        }
+        .and_then(|builder| builder.open_stream())
+        .map_err(|e| format!("could not open output stream: {e}"))
+    }
+
+    fn list_audio_devices() {
+        for id in rodio::cpal::available_hosts() {
+            println!("{}:", id.name());
+            let devices = rodio::cpal::host_from_id(id)
+                .map_err(|e| e.to_string())
+                .and_then(|host| host.output_devices().map_err(|e| e.to_string()));
+            let devices = match devices {
+                Ok(devices) => devices,
+                Err(e) => {
+                    eprintln!("  Error listing devices: {e}");
+                    continue;
+                }
+            };
+            for device in devices {
+                match device.name() {
+                    Ok(name) => println!("  {name}"),
+                    Err(e) => eprintln!("  Error reading device name: {e}"),
+                }
+            }
+        }
+    }
+
+    fn spawn(work: impl FnOnce() + Send + 'static) {
+        std::thread::spawn(work);
+    }
+}
+
+/// Every output device we can find, on every audio host available here.
+fn output_devices() -> impl Iterator<Item = rodio::cpal::Device> {
+    rodio::cpal::available_hosts()
+        .into_iter()
+        .filter_map(|id| rodio::cpal::host_from_id(id).ok())
+        .flat_map(|host| host.output_devices().into_iter().flatten())
+}
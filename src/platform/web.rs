@@ -0,0 +1,89 @@
+use std::future::Future;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+
+use winit::application::ApplicationHandler;
+use winit::event_loop::{EventLoop, EventLoopProxy};
+use winit::platform::web::{EventLoopExtWebSys, WindowAttributesExtWebSys};
+use winit::window::WindowAttributes;
+
+use crate::graphics::Image;
+use crate::platform::Platform;
+
+/// There's no system font to find, so one comes along with the app.
+const MONOSPACE_FONT: &[u8] = include_bytes!("../../assets/DejaVuSansMono.ttf");
+
+pub struct Web;
+
+fn unsupported(what: &str) -> String {
+    format!("{what} isn't supported in the browser")
+}
+
+impl Platform for Web {
+    fn window_attributes(attributes: WindowAttributes) -> WindowAttributes {
+        // Gives the window a canvas on the page to draw in
+        attributes.with_append(true)
+    }
+
+    fn start<T: 'static>(
+        setup: impl Future<Output = T> + 'static,
+        proxy: EventLoopProxy<T>,
+    ) -> Option<T> {
+        // Nothing can be waited on without locking up the page
+        wasm_bindgen_futures::spawn_local(async move {
+            // Only fails once the page is going away anyway
+            let _ = proxy.send_event(setup.await);
+        });
+        None
+    }
+
+    fn run<T: 'static>(event_loop: EventLoop<T>, app: impl ApplicationHandler<T> + 'static) {
+        // Returns straight away, leaving the browser to run it
+        event_loop.spawn_app(app);
+    }
+
+    fn monospace_font() -> Vec<u8> {
+        MONOSPACE_FONT.to_vec()
+    }
+
+    fn open_file(_path: &Path) -> io::Result<Box<dyn io::Read>> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            unsupported("reading files"),
+        ))
+    }
+
+    fn create_file(_path: &Path) -> io::Result<Box<dyn io::Write>> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            unsupported("saving files"),
+        ))
+    }
+
+    fn pick_file(
+        _title: &str,
+        _filter: &str,
+        _extensions: &[&str],
+        _picked: impl FnOnce(Option<PathBuf>) + Send + 'static,
+    ) -> Result<(), String> {
+        Err(unsupported("opening files"))
+    }
+
+    fn open_clipboard() -> Result<mpsc::Sender<Image>, String> {
+        Err(unsupported("copying to the clipboard"))
+    }
+
+    fn open_output_stream(_device: Option<&str>) -> Result<rodio::OutputStream, String> {
+        Err(unsupported("playing music"))
+    }
+
+    fn list_audio_devices() {
+        eprintln!("{}", unsupported("listing audio devices"));
+    }
+
+    fn spawn(work: impl FnOnce() + Send + 'static) {
+        // There's only the one thread
+        work();
+    }
+}
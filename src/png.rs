@@ -0,0 +1,20 @@
+//! Reads PNG files, for showing an image behind the simulation.
+
+use std::io::{self, Read};
+use std::path::Path;
+
+use crate::graphics::Image;
+use crate::platform::{Current, Platform};
+
+pub fn read(path: &Path) -> io::Result<Image> {
+    let mut data = Vec::new();
+    Current::open_file(path)?.read_to_end(&mut data)?;
+    let image = image::load_from_memory_with_format(&data, image::ImageFormat::Png)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
+        .into_rgba8();
+    Ok(Image {
+        width: image.width(),
+        height: image.height(),
+        rgba: image.into_raw(),
+    })
+}
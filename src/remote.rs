@@ -0,0 +1,169 @@
+//! Lets other software control a running instance over HTTP, e.g. a show-control system or a phone
+//! on the same network. Each request is turned into an `Action` for the event loop, which carries
+//! it out the same way as the matching keypress.
+//!
+//! Like triggers, this is written without any dependencies, so it only understands just enough
+//! HTTP for simple `POST` requests:
+//!
+//! - `/preset/next`, `/preset/previous`, & `/preset/<number>`, counting from 1
+//! - `/param/<name>` with the new value as the body, e.g. `/param/sd0` with `12.5`
+//! - `/play`, `/pause`, & `/toggle-pause`
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::sync::mpsc;
+use std::time::Duration;
+
+use facet::Facet;
+
+use crate::action::Action;
+use crate::fs::settings::Param;
+
+/// How long to wait on a client that's stopped sending its request.
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+/// Bodies are at most a number, so anything bigger than this isn't meant for us.
+const MAX_BODY: usize = 1024;
+
+/// Parses where to listen. A port on its own listens on localhost only, so nothing else on the
+/// network can take control unless asked to.
+pub fn address(s: &str) -> Result<SocketAddr, String> {
+    if let Ok(port) = s.parse() {
+        return Ok(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), port));
+    }
+    s.parse()
+        .map_err(|_| format!("expected a port or an address like 127.0.0.1:8080, got {s}"))
+}
+
+pub struct Remote {
+    rx: mpsc::Receiver<Action>,
+}
+
+impl Remote {
+    /// Starts listening for requests in the background.
+    pub fn start(address: SocketAddr) -> std::io::Result<Self> {
+        let (tx, rx) = mpsc::channel();
+        let listener = TcpListener::bind(address)?;
+        println!("Listening for remote control on http://{address}");
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        let tx = tx.clone();
+                        std::thread::spawn(move || {
+                            if let Err(e) = handle_connection(&stream, &tx) {
+                                eprintln!("Error handling remote control request: {e}");
+                            }
+                        });
+                    }
+                    Err(e) => eprintln!("Error accepting remote control connection: {e}"),
+                }
+            }
+        });
+        Ok(Self { rx })
+    }
+
+    /// The next action that's arrived since we last checked, if any.
+    pub fn try_recv(&self) -> Option<Action> {
+        self.rx.try_recv().ok()
+    }
+}
+
+/// Reads one request, passes on what it asks for, & says how that went.
+fn handle_connection(stream: &TcpStream, tx: &mpsc::Sender<Action>) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut reader = BufReader::new(stream);
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut content_length = 0;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 || header.trim_end().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':')
+            && name.trim().eq_ignore_ascii_case("content-length")
+        {
+            content_length = value.trim().parse().unwrap_or(0);
+        }
+    }
+    let mut body = vec![0; content_length.min(MAX_BODY)];
+    reader.read_exact(&mut body)?;
+
+    let (status, message) = match parse_request(&request_line, &body) {
+        Ok(action) => {
+            if tx.send(action).is_err() {
+                // The event loop has stopped, so there's nobody left to answer for
+                return Ok(());
+            }
+            ("200 OK", "ok".to_string())
+        }
+        Err(error) => error,
+    };
+    let mut stream = stream;
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{message}\n",
+        message.len() + 1
+    )?;
+    stream.flush()
+}
+
+/// Works out which action a request is for, or the status & message to answer with if it isn't
+/// one we know.
+fn parse_request(request_line: &str, body: &[u8]) -> Result<Action, (&'static str, String)> {
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Err(("400 Bad Request", "malformed request".to_string()));
+    };
+    if method != "POST" {
+        return Err((
+            "405 Method Not Allowed",
+            "only POST is supported".to_string(),
+        ));
+    }
+    let path = target.split_once('?').map_or(target, |(path, _)| path);
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    let not_found = || ("404 Not Found", format!("nothing at {path}"));
+
+    match segments.as_slice() {
+        ["preset", "next"] => Ok(Action::NextPreset),
+        ["preset", "previous"] => Ok(Action::PreviousPreset),
+        ["preset", number] => match number.parse::<usize>() {
+            Ok(number) if number > 0 => Ok(Action::Preset(number - 1)),
+            _ => Err(not_found()),
+        },
+        ["param", name] => {
+            let param = Param::from_name(name).ok_or_else(not_found)?;
+            let value = parse_value(body).ok_or_else(|| {
+                (
+                    "400 Bad Request",
+                    "expected a number like 1.5 or {\"value\": 1.5}".to_string(),
+                )
+            })?;
+            Ok(Action::Param(param, value))
+        }
+        ["play"] => Ok(Action::Play),
+        ["pause"] => Ok(Action::Pause),
+        ["toggle-pause"] => Ok(Action::TogglePause),
+        _ => Err(not_found()),
+    }
+}
+
+#[derive(Facet)]
+struct Value {
+    value: f32,
+}
+
+/// Accepts either a bare number or a JSON object holding one, whichever is easier to send.
+fn parse_value(body: &[u8]) -> Option<f32> {
+    let value = std::str::from_utf8(body)
+        .ok()
+        .and_then(|text| text.trim().parse().ok())
+        .or_else(|| {
+            facet_json::from_slice::<Value>(body)
+                .ok()
+                .map(|value| value.value)
+        })?;
+    value.is_finite().then_some(value)
+}
@@ -0,0 +1,45 @@
+//! Saves frames read back from the screen as PNG files, named after when they were taken.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use image::ExtendedColorType;
+use image::ImageEncoder;
+use image::codecs::png::{CompressionType, FilterType, PngEncoder};
+use web_time::{SystemTime, UNIX_EPOCH};
+
+use crate::graphics::Image;
+use crate::platform::{Current, Platform};
+
+/// Saves the image into the current directory on its own thread, so the event loop isn't held up
+/// writing it out.
+pub fn save(image: Image) {
+    let path = timestamped_path("png");
+    Current::spawn(move || match write_png(&path, &image) {
+        Ok(()) => println!("Saved screenshot to {}", path.display()),
+        Err(e) => eprintln!("Error saving screenshot to {}: {e}", path.display()),
+    });
+}
+
+/// A file in the current directory named after the current time, with this extension.
+pub fn timestamped_path(extension: &str) -> PathBuf {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    PathBuf::from(format!("physarum-{timestamp}.{extension}"))
+}
+
+pub fn write_png(path: &Path, image: &Image) -> std::io::Result<()> {
+    let mut file = std::io::BufWriter::new(Current::create_file(path)?);
+    // Frames can come thick & fast for a timelapse, so spend less time squeezing them down
+    PngEncoder::new_with_quality(&mut file, CompressionType::Fast, FilterType::Adaptive)
+        .write_image(
+            &image.rgba,
+            image.width,
+            image.height,
+            ExtendedColorType::Rgba8,
+        )
+        .map_err(std::io::Error::other)?;
+    file.flush()
+}
@@ -8,6 +8,8 @@ pub mod bind_groups {
     pub struct BindGroupLayout0<'a> {
         pub constants: wgpu::BufferBinding<'a>,
         pub params: wgpu::BufferBinding<'a>,
+        pub brush_params: wgpu::BufferBinding<'a>,
+        pub brush: wgpu::BufferBinding<'a>,
     }
     const LAYOUT_DESCRIPTOR0: wgpu::BindGroupLayoutDescriptor = wgpu::BindGroupLayoutDescriptor {
         label: Some("LayoutDescriptor0"),
@@ -32,6 +34,26 @@ pub mod bind_groups {
                 },
                 count: None,
             },
+            wgpu::BindGroupLayoutEntry {
+                binding: 2,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 3,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
         ],
     };
     impl BindGroup0 {
@@ -51,6 +73,14 @@ pub mod bind_groups {
                         binding: 1,
                         resource: wgpu::BindingResource::Buffer(bindings.params),
                     },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: wgpu::BindingResource::Buffer(bindings.brush_params),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource: wgpu::BindingResource::Buffer(bindings.brush),
+                    },
                 ],
                 label: Some("BindGroup0"),
             });
@@ -67,6 +97,8 @@ pub mod bind_groups {
         pub particle_params: wgpu::BufferBinding<'a>,
         pub particle_counters: wgpu::BufferBinding<'a>,
         pub fbo_display: &'a wgpu::TextureView,
+        pub param_field: wgpu::BufferBinding<'a>,
+        pub heatmap_max: wgpu::BufferBinding<'a>,
     }
     const LAYOUT_DESCRIPTOR1: wgpu::BindGroupLayoutDescriptor = wgpu::BindGroupLayoutDescriptor {
         label: Some("LayoutDescriptor1"),
@@ -101,6 +133,26 @@ pub mod bind_groups {
                 },
                 count: None,
             },
+            wgpu::BindGroupLayoutEntry {
+                binding: 3,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Storage { read_only: false },
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 4,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Storage { read_only: false },
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
         ],
     };
     impl BindGroup1 {
@@ -124,6 +176,14 @@ pub mod bind_groups {
                         binding: 2,
                         resource: wgpu::BindingResource::TextureView(bindings.fbo_display),
                     },
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource: wgpu::BindingResource::Buffer(bindings.param_field),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 4,
+                        resource: wgpu::BindingResource::Buffer(bindings.heatmap_max),
+                    },
                 ],
                 label: Some("BindGroup1"),
             });
@@ -148,7 +208,7 @@ pub mod bind_groups {
                 visibility: wgpu::ShaderStages::COMPUTE,
                 ty: wgpu::BindingType::StorageTexture {
                     access: wgpu::StorageTextureAccess::ReadOnly,
-                    format: wgpu::TextureFormat::R32Float,
+                    format: wgpu::TextureFormat::Rgba32Float,
                     view_dimension: wgpu::TextureViewDimension::D2,
                 },
                 count: None,
@@ -158,7 +218,7 @@ pub mod bind_groups {
                 visibility: wgpu::ShaderStages::COMPUTE,
                 ty: wgpu::BindingType::StorageTexture {
                     access: wgpu::StorageTextureAccess::WriteOnly,
-                    format: wgpu::TextureFormat::R32Float,
+                    format: wgpu::TextureFormat::Rgba32Float,
                     view_dimension: wgpu::TextureViewDimension::D2,
                 },
                 count: None,
@@ -267,6 +327,19 @@ pub mod compute {
             cache: Default::default(),
         })
     }
+    pub const CS_BRUSH_WORKGROUP_SIZE: [u32; 3] = [16, 16, 1];
+    pub fn create_cs_brush_pipeline(device: &wgpu::Device) -> wgpu::ComputePipeline {
+        let module = super::create_shader_module(device);
+        let layout = super::create_pipeline_layout(device);
+        device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Compute Pipeline cs_brush"),
+            layout: Some(&layout),
+            module: &module,
+            entry_point: Some("cs_brush"),
+            compilation_options: Default::default(),
+            cache: Default::default(),
+        })
+    }
     pub const CS_MOVE_WORKGROUP_SIZE: [u32; 3] = [256, 1, 1];
     pub fn create_cs_move_pipeline(device: &wgpu::Device) -> wgpu::ComputePipeline {
         let module = super::create_shader_module(device);
@@ -293,6 +366,19 @@ pub mod compute {
             cache: Default::default(),
         })
     }
+    pub const CS_DISPLAY_WORKGROUP_SIZE: [u32; 3] = [16, 16, 1];
+    pub fn create_cs_display_pipeline(device: &wgpu::Device) -> wgpu::ComputePipeline {
+        let module = super::create_shader_module(device);
+        let layout = super::create_pipeline_layout(device);
+        device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Compute Pipeline cs_display"),
+            layout: Some(&layout),
+            module: &module,
+            entry_point: Some("cs_display"),
+            compilation_options: Default::default(),
+            cache: Default::default(),
+        })
+    }
     pub const CS_DIFFUSION_WORKGROUP_SIZE: [u32; 3] = [16, 16, 1];
     pub fn create_cs_diffusion_pipeline(device: &wgpu::Device) -> wgpu::ComputePipeline {
         let module = super::create_shader_module(device);
@@ -308,8 +394,10 @@ pub mod compute {
     }
 }
 pub const ENTRY_CS_SETTER: &str = "cs_setter";
+pub const ENTRY_CS_BRUSH: &str = "cs_brush";
 pub const ENTRY_CS_MOVE: &str = "cs_move";
 pub const ENTRY_CS_DEPOSIT: &str = "cs_deposit";
+pub const ENTRY_CS_DISPLAY: &str = "cs_display";
 pub const ENTRY_CS_DIFFUSION: &str = "cs_diffusion";
 pub const SOURCE: &str = include_str!("compute_shader.wgsl");
 pub fn create_shader_module(device: &wgpu::Device) -> wgpu::ShaderModule {
@@ -332,15 +420,52 @@ pub fn create_pipeline_layout(device: &wgpu::Device) -> wgpu::PipelineLayout {
 }
 #[repr(C)]
 #[derive(Debug, Copy, Clone, PartialEq, bytemuck :: Pod, bytemuck :: Zeroable)]
+pub struct Brush {
+    pub center: glam::Vec2,
+    pub radius: f32,
+    pub strength: f32,
+}
+const _: () = assert!(
+    std::mem::size_of::<Brush>() == 16,
+    "size of Brush does not match WGSL"
+);
+const _: () = assert!(
+    std::mem::offset_of!(Brush, center) == 0,
+    "offset of Brush.center does not match WGSL"
+);
+const _: () = assert!(
+    std::mem::offset_of!(Brush, radius) == 8,
+    "offset of Brush.radius does not match WGSL"
+);
+const _: () = assert!(
+    std::mem::offset_of!(Brush, strength) == 12,
+    "offset of Brush.strength does not match WGSL"
+);
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, bytemuck :: Pod, bytemuck :: Zeroable)]
 pub struct Constants {
     pub width: u32,
     pub height: u32,
     pub reset_value: u32,
     pub deposit_factor: f32,
     pub decay_factor: f32,
+    pub count_scale: f32,
+    pub count_blend: f32,
+    pub wrap_edges: u32,
+    pub sensor_count: u32,
+    pub sensor_layout: u32,
+    pub move_speed: f32,
+    pub trail_colors: u32,
+    pub diffusion_radius_x: u32,
+    pub diffusion_radius_y: u32,
+    pub turn_strength: f32,
+    pub stereo_bias: f32,
+    pub heatmap: u32,
+    pub respawn_rate: f32,
+    pub repulsion: f32,
 }
 const _: () = assert!(
-    std::mem::size_of::<Constants>() == 20,
+    std::mem::size_of::<Constants>() == 76,
     "size of Constants does not match WGSL"
 );
 const _: () = assert!(
@@ -363,7 +488,67 @@ const _: () = assert!(
     std::mem::offset_of!(Constants, decay_factor) == 16,
     "offset of Constants.decay_factor does not match WGSL"
 );
+const _: () = assert!(
+    std::mem::offset_of!(Constants, count_scale) == 20,
+    "offset of Constants.count_scale does not match WGSL"
+);
+const _: () = assert!(
+    std::mem::offset_of!(Constants, count_blend) == 24,
+    "offset of Constants.count_blend does not match WGSL"
+);
+const _: () = assert!(
+    std::mem::offset_of!(Constants, wrap_edges) == 28,
+    "offset of Constants.wrap_edges does not match WGSL"
+);
+const _: () = assert!(
+    std::mem::offset_of!(Constants, sensor_count) == 32,
+    "offset of Constants.sensor_count does not match WGSL"
+);
+const _: () = assert!(
+    std::mem::offset_of!(Constants, sensor_layout) == 36,
+    "offset of Constants.sensor_layout does not match WGSL"
+);
+const _: () = assert!(
+    std::mem::offset_of!(Constants, move_speed) == 40,
+    "offset of Constants.move_speed does not match WGSL"
+);
+const _: () = assert!(
+    std::mem::offset_of!(Constants, trail_colors) == 44,
+    "offset of Constants.trail_colors does not match WGSL"
+);
+const _: () = assert!(
+    std::mem::offset_of!(Constants, diffusion_radius_x) == 48,
+    "offset of Constants.diffusion_radius_x does not match WGSL"
+);
+const _: () = assert!(
+    std::mem::offset_of!(Constants, diffusion_radius_y) == 52,
+    "offset of Constants.diffusion_radius_y does not match WGSL"
+);
+const _: () = assert!(
+    std::mem::offset_of!(Constants, turn_strength) == 56,
+    "offset of Constants.turn_strength does not match WGSL"
+);
+const _: () = assert!(
+    std::mem::offset_of!(Constants, stereo_bias) == 60,
+    "offset of Constants.stereo_bias does not match WGSL"
+);
+const _: () = assert!(
+    std::mem::offset_of!(Constants, heatmap) == 64,
+    "offset of Constants.heatmap does not match WGSL"
+);
+const _: () = assert!(
+    std::mem::offset_of!(Constants, respawn_rate) == 68,
+    "offset of Constants.respawn_rate does not match WGSL"
+);
+const _: () = assert!(
+    std::mem::offset_of!(Constants, repulsion) == 72,
+    "offset of Constants.repulsion does not match WGSL"
+);
+pub const FIELD_CELL_SIZE: u32 = 8u32;
+pub const HEATMAP_MAX_DECAY: f32 = 0.99f32;
+pub const HUE_PRECISION: f32 = 64f32;
 pub const LIMIT: f32 = 100f32;
+pub const PARTICLE_WORK_GROUP_SIZE: u32 = 256u32;
 pub const PI: f32 = 3.1415927f32;
 #[repr(C)]
 #[derive(Debug, Copy, Clone, PartialEq, bytemuck :: Pod, bytemuck :: Zeroable)]
@@ -448,4 +633,5 @@ const _: () = assert!(
     std::mem::offset_of!(PointSettings, sensor_bias_2) == 56,
     "offset of PointSettings.sensor_bias_2 does not match WGSL"
 );
+pub const WORK_GROUP_SIZE: u32 = 16u32;
 
@@ -8,6 +8,40 @@ struct Constants {
     reset_value: u32,
     deposit_factor: f32,
     decay_factor: f32,
+    // How many particles on a pixel it takes to get close to full brightness
+    count_scale: f32,
+    // What the displayed image is made of: 0.0 is all trail, 1.0 is all particle counts
+    count_blend: f32,
+    // 1 if particles & trails wrap around the edges of the simulation (toroidal), 0 if they are
+    // bounded by them
+    wrap_edges: u32,
+    // How many sensors each particle has
+    sensor_count: u32,
+    // 0 spreads the sensors across the sensor angle, 1 spreads them all the way around
+    sensor_layout: u32,
+    // How far particles move each step, relative to what the point settings say
+    move_speed: f32,
+    // 0 makes the trail one color, 1 colors it by which way particles are heading, 2 by which
+    // particle it is, 3 by the hue of the way they're heading
+    trail_colors: u32,
+    // How many pixels either side the trail is blurred over during diffusion, horizontally &
+    // vertically. Different amounts stretch the trail along one axis.
+    diffusion_radius_x: u32,
+    diffusion_radius_y: u32,
+    // How strongly particles turn towards the trail they sense, relative to what the point
+    // settings say
+    turn_strength: f32,
+    // Leans the simulation with the music's stereo balance, from -1 to 1. Particles move up to
+    // this much faster on the right & slower on the left, or the other way around if negative.
+    stereo_bias: f32,
+    // 1 shows a heatmap of where particles are crowding together over the usual display, 0 doesn't
+    heatmap: u32,
+    // How far particles get towards respawning somewhere random each step, 1 over how many steps
+    // they live for. 0 keeps them around forever.
+    respawn_rate: f32,
+    // How strongly particles steer away from pixels that other particles are crowding onto. 0
+    // ignores them entirely.
+    repulsion: f32,
 }
 @group(0) @binding(0) var<uniform> constants: Constants;
 
@@ -29,24 +63,124 @@ struct PointSettings {
     sensor_bias_2: f32,
 };
 @group(0) @binding(1) var<uniform> params: PointSettings;
+// The settings used in regions painted with the brush, see cs_brush
+@group(0) @binding(2) var<uniform> brush_params: PointSettings;
+
+struct Brush {
+    // Where the brush is, in simulation pixels
+    center: vec2f,
+    radius: f32,
+    // How much each application adds to the painted amount; negative to erase
+    strength: f32,
+}
+@group(0) @binding(3) var<uniform> brush: Brush;
 
 @group(1) @binding(0) var<storage, read_write> particle_params: array<u32>;
+// The number of particles on each pixel, followed by the number going into each of the red,
+// green, & blue channels when the trail is colored. After those comes the number of particles on
+// each pixel as of the last step, for repulsion to read while this step's counts are still
+// changing.
 @group(1) @binding(1) var<storage, read_write> particle_counters: array<atomic<u32>>;
 @group(1) @binding(2) var fbo_display: texture_storage_2d<rgba8unorm, write>;
+// How much of brush_params to use instead of params, from 0 to 1, for each FIELD_CELL_SIZE square
+// of the simulation
+@group(1) @binding(3) var<storage, read_write> param_field: array<f32>;
+// The most particles on any one pixel since the heatmap last took it in, then the heatmap's running
+// max (as f32 bits). Kept apart from particle_counters, so nothing indexing pixels can touch them.
+@group(1) @binding(4) var<storage, read_write> heatmap_max: array<atomic<u32>, 2>;
 
-@group(2) @binding(0) var trail_read: texture_storage_2d<r32float, read>;
-@group(2) @binding(1) var trail_write: texture_storage_2d<r32float, write>;
+// x is the trail particles sense, yzw is how much of it is red, green, & blue when it's colored
+@group(2) @binding(0) var trail_read: texture_storage_2d<rgba32float, read>;
+@group(2) @binding(1) var trail_write: texture_storage_2d<rgba32float, write>;
 
 const PI: f32 = radians(180.0);
 const LIMIT: f32 = 100.0;
+// Width & height of each cell of param_field, in simulation pixels
+const FIELD_CELL_SIZE: u32 = 8u;
+// Each side of the workgroups that go over pixels. Particles go in workgroups of the same number
+// of invocations, in a line. Changing this is enough for the host's dispatches & limits to follow.
+const WORK_GROUP_SIZE: u32 = 16u;
+const PARTICLE_WORK_GROUP_SIZE: u32 = WORK_GROUP_SIZE * WORK_GROUP_SIZE;
+
+// Whether an invocation over pixels is past the edge of the simulation, for when its size isn't a
+// multiple of WORK_GROUP_SIZE & the last workgroups hang over
+fn outsideSimulation(id: vec3<u32>) -> bool {
+    return id.x >= constants.width || id.y >= constants.height;
+}
 
 // In practice, this shader is only used to reset the counts (of particles) to zero for all pixels (at each iteration)
 
-@compute @workgroup_size(16, 16, 1)
+@compute @workgroup_size(WORK_GROUP_SIZE, WORK_GROUP_SIZE, 1)
 fn cs_setter(
     @builtin(global_invocation_id) id: vec3<u32>
 ) {
-    atomicStore(&particle_counters[id.x * constants.height + id.y], constants.reset_value);
+    if outsideSimulation(id) {
+        return;
+    }
+    let pixel = id.x * constants.height + id.y;
+    if constants.repulsion > 0.0 {
+        let count = atomicLoad(&particle_counters[pixel]);
+        atomicStore(&particle_counters[previousCountIndex(pixel)], count);
+    }
+    atomicStore(&particle_counters[pixel], constants.reset_value);
+    let plane = constants.width * constants.height;
+    if constants.trail_colors != 0u {
+        for (var channel = 1u; channel <= 3u; channel++) {
+            atomicStore(&particle_counters[channel * plane + pixel], constants.reset_value);
+        }
+    }
+    // Takes in the busiest pixel from last frame, letting the max fall back slowly otherwise so
+    // the heatmap stays readable as particles spread out
+    if constants.heatmap != 0u && pixel == 0u {
+        let frameMax = f32(atomicExchange(&heatmap_max[0], 0u));
+        let runningMax = bitcast<f32>(atomicLoad(&heatmap_max[1]));
+        let newMax = max(frameMax, runningMax * HEATMAP_MAX_DECAY);
+        atomicStore(&heatmap_max[1], bitcast<u32>(newMax));
+    }
+}
+
+// How much of the heatmap's running max is kept each frame, when no pixel is as busy as it.
+const HEATMAP_MAX_DECAY: f32 = 0.99;
+
+// The Turbo colormap, from dark blue for 0 through green to dark red for 1. A polynomial fit by
+// Google (Apache 2.0): https://gist.github.com/mikhailov-work/0d177465a8151eb6ede1768d51d476c7
+fn turbo(t: f32) -> vec3f {
+    let r4 = vec4(0.13572138, 4.61539260, -42.66032258, 132.13108234);
+    let g4 = vec4(0.09140261, 2.19418839, 4.84296658, -14.18503333);
+    let b4 = vec4(0.10667330, 12.64194608, -60.58204836, 110.36276771);
+    let r2 = vec2(-152.94239396, 59.28637943);
+    let g2 = vec2(4.27729857, 2.82956604);
+    let b2 = vec2(-89.90310912, 27.34824973);
+    let x = clamp(t, 0.0, 1.0);
+    let v4 = vec4(1.0, x, x * x, x * x * x);
+    let v2 = v4.zw * v4.z;
+    return clamp(vec3(
+        dot(v4, r4) + dot(v2, r2),
+        dot(v4, g4) + dot(v2, g2),
+        dot(v4, b4) + dot(v2, b2),
+    ), vec3(0.0), vec3(1.0));
+}
+
+// How finely a particle's deposit gets split between channels when it's colored by hue. The
+// counters are whole numbers, so each channel counts this many times over.
+const HUE_PRECISION: f32 = 64.0;
+
+// How much of a particle's deposit goes into each of the red, green, & blue channels when it's
+// colored by the hue of the way it's heading. Always adds up to 1, so every hue is as bright.
+fn headingHue(heading: f32) -> vec3f {
+    // HSV to RGB at full saturation & value: each channel ramps up & down around its own third of
+    // the circle
+    let hue = fract(heading / (2.0 * PI)) * 6.0;
+    let rgb = clamp(abs((vec3f(hue) + vec3f(0.0, 4.0, 2.0)) % 6.0 - 3.0) - 1.0, vec3f(0.0), vec3f(1.0));
+    return rgb / (rgb.r + rgb.g + rgb.b);
+}
+
+// Which of the red, green, & blue channels of the trail a particle deposits into
+fn trailChannel(index: u32, heading: f32) -> u32 {
+    if constants.trail_colors == 1u {
+        return u32(floor(fract(heading / (2.0 * PI)) * 3.0)) % 3u;
+    }
+    return index % 3u;
 }
 
 ///////////////////////////////////////////////////
@@ -90,7 +224,77 @@ fn random01FromParticle(particlePos: vec2f) -> f32 {
 // End of randomness utils
 ///////////////////////////////////////////////////
 
+///////////////////////////////////////////////////
+// Spatially varying settings, painted with the brush
+
+fn fieldDim() -> vec2u {
+    return (vec2u(constants.width, constants.height) + FIELD_CELL_SIZE - 1u) / FIELD_CELL_SIZE;
+}
+
+fn fieldValue(cell: vec2i) -> f32 {
+    let dim = vec2i(fieldDim());
+    let c = clamp(cell, vec2i(0), dim - 1);
+    return param_field[u32(c.y * dim.x + c.x)];
+}
+
+// Blends between the centers of the nearest cells, so painted regions have soft edges
+fn fieldWeight(pos: vec2f) -> f32 {
+    let cellPos = pos / f32(FIELD_CELL_SIZE) - 0.5;
+    let base = vec2i(floor(cellPos));
+    let t = fract(cellPos);
+    let top = mix(fieldValue(base), fieldValue(base + vec2i(1, 0)), t.x);
+    let bottom = mix(fieldValue(base + vec2i(0, 1)), fieldValue(base + vec2i(1, 1)), t.x);
+    return mix(top, bottom, t.y);
+}
+
+// The settings to use at a given position in the simulation
+fn localParams(pos: vec2f) -> PointSettings {
+    let w = fieldWeight(pos);
+    if w <= 0.0 {
+        return params;
+    }
+    var p: PointSettings;
+    p.default_scaling_factor = mix(params.default_scaling_factor, brush_params.default_scaling_factor, w);
+    p.sd_base = mix(params.sd_base, brush_params.sd_base, w);
+    p.sd_exponent = mix(params.sd_exponent, brush_params.sd_exponent, w);
+    p.sd_amplitude = mix(params.sd_amplitude, brush_params.sd_amplitude, w);
+    p.sa_base = mix(params.sa_base, brush_params.sa_base, w);
+    p.sa_exponent = mix(params.sa_exponent, brush_params.sa_exponent, w);
+    p.sa_amplitude = mix(params.sa_amplitude, brush_params.sa_amplitude, w);
+    p.ra_base = mix(params.ra_base, brush_params.ra_base, w);
+    p.ra_exponent = mix(params.ra_exponent, brush_params.ra_exponent, w);
+    p.ra_amplitude = mix(params.ra_amplitude, brush_params.ra_amplitude, w);
+    p.md_base = mix(params.md_base, brush_params.md_base, w);
+    p.md_exponent = mix(params.md_exponent, brush_params.md_exponent, w);
+    p.md_amplitude = mix(params.md_amplitude, brush_params.md_amplitude, w);
+    p.sensor_bias_1 = mix(params.sensor_bias_1, brush_params.sensor_bias_1, w);
+    p.sensor_bias_2 = mix(params.sensor_bias_2, brush_params.sensor_bias_2, w);
+    return p;
+}
+
+// Adds to (or takes away from) the painted amount around the brush, fading out towards its edge
+
+@compute @workgroup_size(WORK_GROUP_SIZE, WORK_GROUP_SIZE, 1)
+fn cs_brush(
+    @builtin(global_invocation_id) id: vec3<u32>
+) {
+    let dim = fieldDim();
+    if id.x >= dim.x || id.y >= dim.y {
+        return;
+    }
+    let cellCenter = (vec2f(id.xy) + 0.5) * f32(FIELD_CELL_SIZE);
+    let falloff = 1.0 - smoothstep(0.5 * brush.radius, brush.radius, distance(cellCenter, brush.center));
+    let i = id.y * dim.x + id.x;
+    param_field[i] = clamp(param_field[i] + brush.strength * falloff, 0.0, 1.0);
+}
+
+///////////////////////////////////////////////////
+
 fn getGridValue(pos: vec2f) -> f32 {
+    if constants.wrap_edges == 0u {
+        let dim = vec2i(i32(constants.width), i32(constants.height));
+        return textureLoad(trail_read, clamp(vec2i(pos + 0.5), vec2i(0), dim - 1)).x;
+    }
     return textureLoad(trail_read, vec2i(
         i32((pos.x + 0.5 + f32(constants.width)) % f32(constants.width)),
         i32((pos.y + 0.5 + f32(constants.height)) % f32(constants.height)),
@@ -98,10 +302,56 @@ fn getGridValue(pos: vec2f) -> f32 {
 }
 
 fn senseFromAngle(angle: f32, pos: vec2f, heading: f32, sense_offset: f32) -> f32 {
-    return getGridValue(vec2f(
+    let sensePos = vec2f(
         pos.x + sense_offset * cos(heading + angle),
         pos.y + sense_offset * sin(heading + angle),
-    ));
+    );
+    let sensed = getGridValue(sensePos);
+    if constants.repulsion > 0.0 {
+        // Crowded pixels seem to have less trail, so particles turn away from them
+        return sensed / (1.0 + constants.repulsion * crowdingAt(sensePos));
+    }
+    return sensed;
+}
+
+// Where in particle_counters the number of particles on a pixel as of the last step is kept.
+fn previousCountIndex(pixel: u32) -> u32 {
+    return 4u * constants.width * constants.height + pixel;
+}
+
+// Which pixel pos is on, as an index into one plane of particle_counters. Positions round to the
+// nearest pixel, so ones just short of the far edge wrap around (or clamp) rather than landing
+// past the end of the plane.
+fn counterPixel(pos: vec2f) -> u32 {
+    let dim = vec2i(i32(constants.width), i32(constants.height));
+    var pixel = vec2i(pos + 0.5);
+    if constants.wrap_edges == 0u {
+        pixel = clamp(pixel, vec2i(0), dim - 1);
+    } else {
+        pixel = (pixel % dim + dim) % dim;
+    }
+    return u32(pixel.x) * constants.height + u32(pixel.y);
+}
+
+// How many particles were on the pixel at pos as of the last step, relative to how many it takes
+// to look fully bright. This step's counts can't be used, since they change while particles move.
+fn crowdingAt(pos: vec2f) -> f32 {
+    let count = atomicLoad(&particle_counters[previousCountIndex(counterPixel(pos))]);
+    return f32(count) / constants.count_scale;
+}
+
+// The angle from the particle's heading of sensor number i
+fn sensorLayoutAngle(i: u32, sensorAngle: f32) -> f32 {
+    let count = constants.sensor_count;
+    if constants.sensor_layout == 1u {
+        // All the way around, kept between -PI and PI so sensors on the left turn left
+        let angle = 2.0 * PI * f32(i) / f32(count);
+        return select(angle, angle - 2.0 * PI, angle > PI);
+    }
+    if count <= 1u {
+        return 0.0;
+    }
+    return sensorAngle * (2.0 * f32(i) / f32(count - 1u) - 1.0);
 }
 
 // This is the main shader.
@@ -109,73 +359,128 @@ fn senseFromAngle(angle: f32, pos: vec2f, heading: f32, sense_offset: f32) -> f3
 // It also increases a counter on the pixel of the particle's new position, which will be used to add deposit in the deposit shader.
 // Counter increased with atomicAdd function to be able to do it with many particles in parallel.
 
-@compute @workgroup_size(256, 1, 1)
+@compute @workgroup_size(PARTICLE_WORK_GROUP_SIZE, 1, 1)
 fn cs_move(
     @builtin(global_invocation_id) id: vec3<u32>
 ) {
+    // The last workgroup hangs over the end if there aren't a multiple of its size
+    if id.x >= arrayLength(&particle_params) / 2u {
+        return;
+    }
     let dim = vec2f(f32(constants.width), f32(constants.height));
     let particlePos = unpack2x16unorm(particle_params[2u * id.x]) * dim;
     let curProgressAndHeading = unpack2x16unorm(particle_params[2u * id.x + 1u]) * vec2f(1.0, 2.0 * PI);
 
     let heading = curProgressAndHeading.y;
     let direction = vec2(cos(heading), sin(heading));
+    let p = localParams(particlePos);
 
     ///////////////////////////////////////////////////////////////////////////////////
     // Techniques/formulas from Sage Jenson (mxsage)
     // Sensing a value at particle pos or next to it...
-    var currentSensedValue = getGridValue(particlePos + params.sensor_bias_2 * direction + vec2(0., params.sensor_bias_1));
-    currentSensedValue *= params.default_scaling_factor;
+    var currentSensedValue = getGridValue(particlePos + p.sensor_bias_2 * direction + vec2(0., p.sensor_bias_1));
+    currentSensedValue *= p.default_scaling_factor;
     currentSensedValue = clamp(currentSensedValue, 0.000000001, 1.0);
     // For a current sensed value S,
     // physarum param = A + B * (S ^ C)
     // These A,B,C parameters are part of the data of a "Point"
-    let sensorDistance = params.sd_base + params.sd_amplitude * pow(currentSensedValue, params.sd_exponent) * 250.0;
-    let moveDistance = params.md_base + params.md_amplitude * pow(currentSensedValue, params.md_exponent) * 250.0;
-    let sensorAngle = params.sa_base + params.sa_amplitude * pow(currentSensedValue, params.sa_exponent);
-    let rotationAngle = params.ra_base + params.ra_amplitude * pow(currentSensedValue, params.ra_exponent);
+    let sensorDistance = p.sd_base + p.sd_amplitude * pow(currentSensedValue, p.sd_exponent) * 250.0;
+    // -1 at the left edge to 1 at the right, for leaning with the music's stereo balance
+    let side = particlePos.x / f32(constants.width) * 2.0 - 1.0;
+    let stereoSpeed = 1.0 + constants.stereo_bias * side;
+    let moveDistance = (p.md_base + p.md_amplitude * pow(currentSensedValue, p.md_exponent) * 250.0) * constants.move_speed * stereoSpeed;
+    let sensorAngle = p.sa_base + p.sa_amplitude * pow(currentSensedValue, p.sa_exponent);
+    let baseRotationAngle = p.ra_base + p.ra_amplitude * pow(currentSensedValue, p.ra_exponent);
     // 3 * 4 = 12 parameters + 2 with sensor bias
     ///////////////////////////////////////////////////////////////////////////////////
-
-    // sensing at 3 positions, as in the classic physarum algorithm
-    let sensedLeft = senseFromAngle(-sensorAngle, particlePos, heading, sensorDistance);
-    let sensedMiddle = senseFromAngle(0.0, particlePos, heading, sensorDistance);
-    let sensedRight = senseFromAngle(sensorAngle, particlePos, heading, sensorDistance);
+    let rotationAngle = baseRotationAngle * constants.turn_strength;
 
     var newHeading = heading;
-    // heading update, as in the classic physarum algorithm
-    if sensedMiddle > sensedLeft && sensedMiddle > sensedRight {
-    } else if sensedMiddle < sensedLeft && sensedMiddle < sensedRight {
-        if random01FromParticle(particlePos) < 0.5 {
+    if constants.sensor_count == 3u && constants.sensor_layout == 0u {
+        // sensing at 3 positions, as in the classic physarum algorithm
+        let sensedLeft = senseFromAngle(-sensorAngle, particlePos, heading, sensorDistance);
+        let sensedMiddle = senseFromAngle(0.0, particlePos, heading, sensorDistance);
+        let sensedRight = senseFromAngle(sensorAngle, particlePos, heading, sensorDistance);
+
+        // heading update, as in the classic physarum algorithm
+        if sensedMiddle > sensedLeft && sensedMiddle > sensedRight {
+        } else if sensedMiddle < sensedLeft && sensedMiddle < sensedRight {
+            if random01FromParticle(particlePos) < 0.5 {
+                newHeading = heading - rotationAngle;
+            } else {
+                newHeading = heading + rotationAngle;
+            }
+        } else if sensedRight < sensedLeft {
             newHeading = heading - rotationAngle;
-        } else {
+        } else if sensedLeft < sensedRight {
             newHeading = heading + rotationAngle;
         }
-    } else if sensedRight < sensedLeft {
-        newHeading = heading - rotationAngle;
-    } else if sensedLeft < sensedRight {
-        newHeading = heading + rotationAngle;
+    } else {
+        // Any other number of sensors: turn towards whichever one senses the most, by more the
+        // further off to the side it is. With 3 sensors in a fan, this turns the same amount as
+        // the classic algorithm does.
+        var bestAngle = 0.0;
+        var bestSensed = -1.0;
+        for (var i = 0u; i < constants.sensor_count; i++) {
+            let angle = sensorLayoutAngle(i, sensorAngle);
+            let sensed = senseFromAngle(angle, particlePos, heading, sensorDistance);
+            if sensed > bestSensed {
+                bestSensed = sensed;
+                bestAngle = angle;
+            }
+        }
+        let widestAngle = select(sensorAngle, PI, constants.sensor_layout == 1u);
+        if abs(widestAngle) > 0.0 {
+            newHeading = heading + rotationAngle * bestAngle / widestAngle;
+        }
     }
 
     // position update of the classic physarum algorithm
-    let px = particlePos.x + moveDistance * cos(newHeading);
-    let py = particlePos.y + moveDistance * sin(newHeading);
-
-    // position loop to keep pixel positions of the simulation canvas
-    var nextPos = vec2f(
-        (px + f32(constants.width)) % f32(constants.width),
-        (py + f32(constants.height)) % f32(constants.height),
-    );
+    var px = particlePos.x + moveDistance * cos(newHeading);
+    var py = particlePos.y + moveDistance * sin(newHeading);
+
+    var nextPos: vec2f;
+    if constants.wrap_edges != 0u {
+        // position loop to keep pixel positions of the simulation canvas
+        nextPos = vec2f(
+            (px + f32(constants.width)) % f32(constants.width),
+            (py + f32(constants.height)) % f32(constants.height),
+        );
+    } else {
+        // bounce off the edges of the simulation canvas instead
+        let maxPos = dim - 1.0;
+        if px < 0.0 || px > maxPos.x {
+            px = select(2.0 * maxPos.x - px, -px, px < 0.0);
+            newHeading = PI - newHeading;
+        }
+        if py < 0.0 || py > maxPos.y {
+            py = select(2.0 * maxPos.y - py, -py, py < 0.0);
+            newHeading = -newHeading;
+        }
+        nextPos = clamp(vec2f(px, py), vec2f(0.0), maxPos);
+    }
 
     let depositAmount: u32 = 1u; // all particles add 1 on pixel count, could be more complex one day maybe
     // atomicAdd for increasing counter at pixel, in parallel computation
-    atomicAdd(&particle_counters[u32(round(nextPos.x)) * constants.height + u32(round(nextPos.y))], depositAmount);
+    let pixel = counterPixel(nextPos);
+    atomicAdd(&particle_counters[pixel], depositAmount);
+    if constants.trail_colors == 3u {
+        let plane = constants.width * constants.height;
+        let hue = vec3u(round(headingHue(newHeading) * HUE_PRECISION * f32(depositAmount)));
+        for (var channel = 0u; channel < 3u; channel++) {
+            atomicAdd(&particle_counters[(channel + 1u) * plane + pixel], hue[channel]);
+        }
+    } else if constants.trail_colors != 0u {
+        let plane = constants.width * constants.height;
+        atomicAdd(&particle_counters[(trailChannel(id.x, newHeading) + 1u) * plane + pixel], depositAmount);
+    }
 
     ///////////////////////////////////////////////////////////////////////////////////
     // Technique/formula from Sage Jenson (mxsage)
     // particles are regularly respawning, their progression is stored in particle data
-    let reinitSegment = 0.0010; // respawn every 1/reinitSegment iterations
+    let reinitSegment = constants.respawn_rate; // respawn every 1/reinitSegment iterations
     let curProgress = curProgressAndHeading.x;
-    if curProgress < reinitSegment {
+    if reinitSegment > 0.0 && curProgress < reinitSegment {
         nextPos = randomPosFromParticle(particlePos);
     }
     let nextA = fract(curProgress + reinitSegment);
@@ -195,59 +500,130 @@ fn cs_move(
 // It adds deposit to the trail map from the number of particles on this pixel.
 // It also sets the color of the pixel in the displayed image.
 
-@compute @workgroup_size(16, 16, 1)
+@compute @workgroup_size(WORK_GROUP_SIZE, WORK_GROUP_SIZE, 1)
 fn cs_deposit(
     @builtin(global_invocation_id) id: vec3<u32>,
 ) {
+    if outsideSimulation(id) {
+        return;
+    }
     let pix = vec2i(id.xy);
 
-    let prevColor = textureLoad(trail_read, pix).xy; // Getting the trail map color on current pixel
+    let prevColor = textureLoad(trail_read, pix); // Getting the trail map color on current pixel
 
-    let count = f32(atomicLoad(&particle_counters[id.x * constants.height + id.y])); // number of particles on the pixel
+    let counts = countsAt(id.xy);
 
     // The following 3 lines of code are Bleuje's own innovation (looks like with the license, attribution is required if you use this :) ),
     // A way to define an amount of added trail in function of the number of particles on the pixel:
-    let limitedCount = min(count, LIMIT);
+    let limitedCount = min(counts.x, LIMIT);
     let addedDeposit = sqrt(limitedCount) * constants.deposit_factor;
 
     // Trail map update
-    let val = prevColor.x + addedDeposit;
-    textureStore(trail_write, pix, vec4(val, 0.0, 0.0, 0.0));
+    var trail = vec4(prevColor.x + addedDeposit, 0.0, 0.0, 0.0);
+    if constants.trail_colors != 0u {
+        trail = vec4(trail.x, prevColor.yzw + sqrt(min(counts.yzw, vec3(LIMIT))) * constants.deposit_factor);
+    }
+    textureStore(trail_write, pix, trail);
+
+    textureStore(fbo_display, pix, displayColor(pix, trail, counts));
+}
 
-    // Mapping the count on pixel to color intensity
-    let countColorValue = tanh(pow(count / 10.0, 1.7));
-    let col = clamp(vec3(countColorValue), vec3(0.0), vec3(1.0));
-    let outputColor = vec4(col, 1.0);
+// Sets the color of the pixel in the displayed image, for the steps that don't deposit any trail.
+// The trail is left as it is, so it's shown as it stands.
 
-    textureStore(fbo_display, pix, outputColor);
+@compute @workgroup_size(WORK_GROUP_SIZE, WORK_GROUP_SIZE, 1)
+fn cs_display(
+    @builtin(global_invocation_id) id: vec3<u32>,
+) {
+    if outsideSimulation(id) {
+        return;
+    }
+    let pix = vec2i(id.xy);
+    textureStore(fbo_display, pix, displayColor(pix, textureLoad(trail_read, pix), countsAt(id.xy)));
+}
+
+// The number of particles on a pixel, and then on each of its color channels if trails are colored.
+fn countsAt(xy: vec2u) -> vec4f {
+    let pixel = xy.x * constants.height + xy.y;
+    let count = f32(atomicLoad(&particle_counters[pixel])); // number of particles on the pixel
+    if constants.trail_colors == 0u {
+        return vec4(count, 0.0, 0.0, 0.0);
+    }
+    // Each channel gets about a third of the particles, so count them three times over to look as
+    // bright as a single color does when they're evenly mixed.
+    let plane = constants.width * constants.height;
+    // Split by hue, each channel counts many times over
+    let splitInto = select(1.0, HUE_PRECISION, constants.trail_colors == 3u);
+    return vec4(count, 3.0 / splitInto * vec3f(
+        f32(atomicLoad(&particle_counters[plane + pixel])),
+        f32(atomicLoad(&particle_counters[2u * plane + pixel])),
+        f32(atomicLoad(&particle_counters[3u * plane + pixel])),
+    ));
+}
+
+// The color a pixel is displayed as, given the trail on it & the particles on it from `countsAt()`.
+fn displayColor(pix: vec2i, trail: vec4f, counts: vec4f) -> vec4f {
+    let count = counts.x;
+    let scalingFactor = localParams(vec2f(pix)).default_scaling_factor;
+
+    var col: vec3f;
+    if constants.trail_colors == 0u {
+        // Mapping the count on pixel to color intensity
+        let countColorValue = tanh(pow(count / constants.count_scale, 1.7));
+        // Mapping the trail on pixel to color intensity, scaled the same as when it gets sensed
+        let trailColorValue = trail.x * scalingFactor;
+        let colorValue = mix(trailColorValue, countColorValue, constants.count_blend);
+        col = clamp(vec3(colorValue), vec3(0.0), vec3(1.0));
+    } else {
+        // The same again for each channel
+        let countColor = tanh(pow(counts.yzw / constants.count_scale, vec3(1.7)));
+        let trailColor = trail.yzw * scalingFactor;
+        col = clamp(mix(trailColor, countColor, constants.count_blend), vec3(0.0), vec3(1.0));
+    }
+    if constants.heatmap != 0u {
+        atomicMax(&heatmap_max[0], u32(count));
+        // Log scaled, since the busiest pixels have far more particles than most
+        let runningMax = max(bitcast<f32>(atomicLoad(&heatmap_max[1])), 1.0);
+        let heat = log(1.0 + count) / log(1.0 + runningMax);
+        // Pixels without any particles show the usual display dimmed underneath
+        col = select(col * 0.25, turbo(heat), count > 0.0);
+    }
+    return vec4(col, 1.0);
 }
 
 fn LoopedPosition(pos: vec2i) -> vec2i {
     let dim = vec2i(i32(constants.width), i32(constants.height));
+    if constants.wrap_edges == 0u {
+        return clamp(pos, vec2i(0), dim - 1);
+    }
     return (pos + dim) % dim;
 }
 
 /////////////////////////////////////////////////////
 // Shader for trail map diffusion and decay
 
-@compute @workgroup_size(16, 16, 1)
+@compute @workgroup_size(WORK_GROUP_SIZE, WORK_GROUP_SIZE, 1)
 fn cs_diffusion(
     @builtin(global_invocation_id) id: vec3<u32>,
 ) {
+    if outsideSimulation(id) {
+        return;
+    }
     let pos = vec2i(id.xy);
 
-    var colorSum = vec2f(0.0);
-    let kernelSize = 1.0;
-    for (var i = -kernelSize; i < kernelSize + 0.5; i += 1.0) {
-        for (var j = -kernelSize; j < kernelSize + 0.5; j += 1.0) {
-            colorSum += textureLoad(trail_read, LoopedPosition(pos - vec2i(i32(i), i32(j)))).xy;
+    var colorSum = vec4f(0.0);
+    let radius = vec2i(i32(constants.diffusion_radius_x), i32(constants.diffusion_radius_y));
+    for (var i = -radius.x; i <= radius.x; i++) {
+        for (var j = -radius.y; j <= radius.y; j++) {
+            colorSum += textureLoad(trail_read, LoopedPosition(pos - vec2i(i, j)));
         }
     }
 
-    let c = colorSum / pow(2.0 * kernelSize + 1.0, 2.0);
+    let taps = vec2f(2 * radius + 1);
+    let c = colorSum / (taps.x * taps.y);
 
-    let decayed = c.x * constants.decay_factor;
-    let cOutput = vec4(decayed, 0.0, 0.0, 0.0);
+    // The color channels spread & fade the same way as the trail they're part of
+    let cOutput = c * constants.decay_factor;
 
     textureStore(trail_write, pos, cOutput);
 }
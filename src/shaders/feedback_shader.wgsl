@@ -0,0 +1,30 @@
+// Copies one texture onto a whole render target. Used to fade the previous frames into the
+// current one, and to show the result on screen.
+struct VertexShaderOutput {
+    @builtin(position) position: vec4f,
+    @location(0) texcoord: vec2f,
+}
+
+@vertex fn vs(
+    @builtin(vertex_index) vertexIndex: u32,
+) -> VertexShaderOutput {
+    // A single triangle big enough to cover the whole target
+    let pos = array(
+        vec2f(-1.0, -1.0), // left, bottom
+        vec2f(3.0, -1.0), // past the right, bottom
+        vec2f(-1.0, 3.0), // left, past the top
+    );
+
+    var vsOutput: VertexShaderOutput;
+    let xy = pos[vertexIndex];
+    vsOutput.position = vec4f(xy, 0.0, 1.0);
+    vsOutput.texcoord = vec2f(xy.x + 1.0, 1.0 - xy.y) * 0.5;
+    return vsOutput;
+}
+
+@group(0) @binding(0) var ourSampler: sampler;
+@group(0) @binding(1) var ourTexture: texture_2d<f32>;
+
+@fragment fn fs(fsInput: VertexShaderOutput) -> @location(0) vec4f {
+    return textureSample(ourTexture, ourSampler, fsInput.texcoord);
+}
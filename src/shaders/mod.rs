@@ -2,5 +2,6 @@
 // Changes made to this file will not be saved.
 pub mod pipelines;
 pub mod compute_shader;
+pub mod feedback_shader;
 pub mod rect_render_shader;
 pub mod tris_render_shader;
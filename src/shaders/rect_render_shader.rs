@@ -7,8 +7,11 @@ pub mod bind_groups {
     #[derive(Debug)]
     pub struct BindGroupLayout0<'a> {
         pub uni: wgpu::BufferBinding<'a>,
+        pub render_settings: wgpu::BufferBinding<'a>,
         pub ourTexture: &'a wgpu::TextureView,
         pub ourSampler: &'a wgpu::Sampler,
+        pub backgroundImage: &'a wgpu::TextureView,
+        pub imageSampler: &'a wgpu::Sampler,
     }
     const LAYOUT_DESCRIPTOR0: wgpu::BindGroupLayoutDescriptor = wgpu::BindGroupLayoutDescriptor {
         label: Some("LayoutDescriptor0"),
@@ -23,6 +26,16 @@ pub mod bind_groups {
                 },
                 count: None,
             },
+            wgpu::BindGroupLayoutEntry {
+                binding: 2,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
             wgpu::BindGroupLayoutEntry {
                 binding: 1,
                 visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
@@ -39,6 +52,22 @@ pub mod bind_groups {
                 ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
                 count: None,
             },
+            wgpu::BindGroupLayoutEntry {
+                binding: 4,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 5,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            },
         ],
     };
     impl BindGroup0 {
@@ -54,6 +83,10 @@ pub mod bind_groups {
                         binding: 3,
                         resource: wgpu::BindingResource::Buffer(bindings.uni),
                     },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: wgpu::BindingResource::Buffer(bindings.render_settings),
+                    },
                     wgpu::BindGroupEntry {
                         binding: 1,
                         resource: wgpu::BindingResource::TextureView(bindings.ourTexture),
@@ -62,6 +95,14 @@ pub mod bind_groups {
                         binding: 0,
                         resource: wgpu::BindingResource::Sampler(bindings.ourSampler),
                     },
+                    wgpu::BindGroupEntry {
+                        binding: 4,
+                        resource: wgpu::BindingResource::TextureView(bindings.backgroundImage),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 5,
+                        resource: wgpu::BindingResource::Sampler(bindings.imageSampler),
+                    },
                 ],
                 label: Some("BindGroup0"),
             });
@@ -127,6 +168,7 @@ pub fn set_bind_groups<P: bind_groups::SetBindGroup>(
 }
 pub const ENTRY_VS: &str = "vs";
 pub const ENTRY_FS: &str = "fs";
+pub const ENTRY_FS_GLOW: &str = "fs_glow";
 #[derive(Debug)]
 pub struct VertexEntry<const N: usize> {
     pub entry_point: &'static str,
@@ -181,6 +223,13 @@ pub fn fs_entry(targets: [Option<wgpu::ColorTargetState>; 1]) -> FragmentEntry<1
         constants: Default::default(),
     }
 }
+pub fn fs_glow_entry(targets: [Option<wgpu::ColorTargetState>; 1]) -> FragmentEntry<1> {
+    FragmentEntry {
+        entry_point: ENTRY_FS_GLOW,
+        targets,
+        constants: Default::default(),
+    }
+}
 pub const SOURCE: &str = include_str!("rect_render_shader.wgsl");
 pub fn create_shader_module(device: &wgpu::Device) -> wgpu::ShaderModule {
     let source = std::borrow::Cow::Borrowed(SOURCE);
@@ -196,6 +245,67 @@ pub fn create_pipeline_layout(device: &wgpu::Device) -> wgpu::PipelineLayout {
         push_constant_ranges: &[],
     })
 }
+pub const GLOW_RADIUS: f32 = 12f32;
+pub const GLOW_RINGS: u32 = 3u32;
+pub const GLOW_RING_SAMPLES: u32 = 8u32;
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, bytemuck :: Pod, bytemuck :: Zeroable)]
+pub struct RenderSettings {
+    pub tint: glam::Vec4,
+    pub background: glam::Vec4,
+    pub glow_threshold: f32,
+    pub glow_intensity: f32,
+    pub exposure: f32,
+    pub gamma: f32,
+    pub invert: u32,
+    pub _padding1: u32,
+    pub _padding2: u32,
+    pub _padding3: u32,
+}
+const _: () = assert!(
+    std::mem::size_of::<RenderSettings>() == 64,
+    "size of RenderSettings does not match WGSL"
+);
+const _: () = assert!(
+    std::mem::offset_of!(RenderSettings, tint) == 0,
+    "offset of RenderSettings.tint does not match WGSL"
+);
+const _: () = assert!(
+    std::mem::offset_of!(RenderSettings, background) == 16,
+    "offset of RenderSettings.background does not match WGSL"
+);
+const _: () = assert!(
+    std::mem::offset_of!(RenderSettings, glow_threshold) == 32,
+    "offset of RenderSettings.glow_threshold does not match WGSL"
+);
+const _: () = assert!(
+    std::mem::offset_of!(RenderSettings, glow_intensity) == 36,
+    "offset of RenderSettings.glow_intensity does not match WGSL"
+);
+const _: () = assert!(
+    std::mem::offset_of!(RenderSettings, exposure) == 40,
+    "offset of RenderSettings.exposure does not match WGSL"
+);
+const _: () = assert!(
+    std::mem::offset_of!(RenderSettings, gamma) == 44,
+    "offset of RenderSettings.gamma does not match WGSL"
+);
+const _: () = assert!(
+    std::mem::offset_of!(RenderSettings, invert) == 48,
+    "offset of RenderSettings.invert does not match WGSL"
+);
+const _: () = assert!(
+    std::mem::offset_of!(RenderSettings, _padding1) == 52,
+    "offset of RenderSettings._padding1 does not match WGSL"
+);
+const _: () = assert!(
+    std::mem::offset_of!(RenderSettings, _padding2) == 56,
+    "offset of RenderSettings._padding2 does not match WGSL"
+);
+const _: () = assert!(
+    std::mem::offset_of!(RenderSettings, _padding3) == 60,
+    "offset of RenderSettings._padding3 does not match WGSL"
+);
 #[repr(C)]
 #[derive(Debug, Copy, Clone, PartialEq, bytemuck :: Pod, bytemuck :: Zeroable)]
 pub struct Uniforms {
@@ -203,9 +313,13 @@ pub struct Uniforms {
     pub offset: glam::Vec2,
     pub lower_bound: glam::Vec2,
     pub upper_bound: glam::Vec2,
+    pub quarter_turns: u32,
+    pub _padding: u32,
+    pub image_scale: glam::Vec2,
+    pub image_offset: glam::Vec2,
 }
 const _: () = assert!(
-    std::mem::size_of::<Uniforms>() == 32,
+    std::mem::size_of::<Uniforms>() == 56,
     "size of Uniforms does not match WGSL"
 );
 const _: () = assert!(
@@ -224,4 +338,20 @@ const _: () = assert!(
     std::mem::offset_of!(Uniforms, upper_bound) == 24,
     "offset of Uniforms.upper_bound does not match WGSL"
 );
+const _: () = assert!(
+    std::mem::offset_of!(Uniforms, quarter_turns) == 32,
+    "offset of Uniforms.quarter_turns does not match WGSL"
+);
+const _: () = assert!(
+    std::mem::offset_of!(Uniforms, _padding) == 36,
+    "offset of Uniforms._padding does not match WGSL"
+);
+const _: () = assert!(
+    std::mem::offset_of!(Uniforms, image_scale) == 40,
+    "offset of Uniforms.image_scale does not match WGSL"
+);
+const _: () = assert!(
+    std::mem::offset_of!(Uniforms, image_offset) == 48,
+    "offset of Uniforms.image_offset does not match WGSL"
+);
 
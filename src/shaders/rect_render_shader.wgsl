@@ -8,9 +8,41 @@ struct Uniforms {
   offset: vec2f,
   lower_bound: vec2f,
   upper_bound: vec2f,
+  // How many times to turn the texture clockwise by 90 degrees.
+  quarter_turns: u32,
+  // Uniforms are 8-byte aligned because of the vec2fs
+  _padding: u32,
+  // Turns screen pixels into texture coordinates for the background image, which is fitted to
+  // the screen separately from the simulation.
+  image_scale: vec2f,
+  image_offset: vec2f,
 }
 @group(0) @binding(3) var<uniform> uni: Uniforms;
 
+// How the trail texture gets turned into colors. Only expected to change when settings do.
+struct RenderSettings {
+  // Multiplied with the trail intensity. Linear color, the sRGB conversion happens on output.
+  tint: vec4f,
+  // Linear color that shows through where the texture is dark, premultiplied by how strongly
+  background: vec4f,
+  // How bright the trail needs to be before it starts to glow
+  glow_threshold: f32,
+  // How much of the glow gets added on top of the trail
+  glow_intensity: f32,
+  // Lifts faint parts of the trail above 1, or darkens them below it, with full brightness staying
+  // where it is
+  exposure: f32,
+  // Above 1 brightens the middle of the range, below 1 darkens it
+  gamma: f32,
+  // 1 flips every color to its opposite, for a flash, 0 shows them as usual
+  invert: u32,
+  // Uniforms are 16-byte aligned because of the vec4fs
+  _padding1: u32,
+  _padding2: u32,
+  _padding3: u32,
+}
+@group(0) @binding(2) var<uniform> render_settings: RenderSettings;
+
 @vertex fn vs(
     @builtin(vertex_index) vertexIndex: u32,
 ) -> VertexShaderOutput {
@@ -28,19 +60,90 @@ struct Uniforms {
 
     var vsOutput: VertexShaderOutput;
     let xy = pos[vertexIndex];
-    let dims = textureDimensions(ourTexture);
-    vsOutput.position = vec4f(xy * vec2f(dims) * uni.scale + uni.offset, 0.0, 1.0);
-    vsOutput.texcoord = xy;
+    let dims = vec2f(textureDimensions(ourTexture));
+    // Turning by 90 or 270 degrees swaps which way is wide
+    let turned_dims = select(dims, dims.yx, uni.quarter_turns % 2u == 1u);
+    vsOutput.position = vec4f(xy * turned_dims * uni.scale + uni.offset, 0.0, 1.0);
+    switch uni.quarter_turns % 4u {
+        case 1u: { vsOutput.texcoord = vec2f(xy.y, 1.0 - xy.x); }
+        case 2u: { vsOutput.texcoord = vec2f(1.0 - xy.x, 1.0 - xy.y); }
+        case 3u: { vsOutput.texcoord = vec2f(1.0 - xy.y, xy.x); }
+        default: { vsOutput.texcoord = xy; }
+    }
     return vsOutput;
 }
 
 @group(0) @binding(0) var ourSampler: sampler;
 @group(0) @binding(1) var ourTexture: texture_2d<f32>;
+// Shows through behind the trail, in front of the background color. Transparent if there's no
+// image.
+@group(0) @binding(4) var backgroundImage: texture_2d<f32>;
+@group(0) @binding(5) var imageSampler: sampler;
+
+// Compresses the range of the trail, so faint filaments & bright clumps can both be seen. A
+// Reinhard-style curve that keeps 0 & 1 in place, then gamma.
+fn tone_map(color: vec3f) -> vec3f {
+    let exposure = render_settings.exposure;
+    let exposed = color * exposure / (vec3f(1.0) + color * (exposure - 1.0));
+    return pow(exposed, vec3f(1.0 / render_settings.gamma));
+}
 
 @fragment fn fs(fsInput: VertexShaderOutput) -> @location(0) vec4f {
     let xy = fsInput.position.xy;
     if (all(uni.lower_bound <= xy) && all(xy <= uni.upper_bound)) {
-        return textureSample(ourTexture, ourSampler, fsInput.texcoord);
+        let sampled = textureSample(ourTexture, ourSampler, fsInput.texcoord);
+        let color = vec4f(tone_map(sampled.rgb), sampled.a);
+        // The texture is brighter where there's more going on, so use that as how much it covers
+        // up the background
+        let coverage = max(color.r, max(color.g, color.b));
+        let image = textureSampleLevel(
+            backgroundImage, imageSampler, xy * uni.image_scale + uni.image_offset, 0.0
+        );
+        // The background color shows through wherever the image is transparent
+        let behind = image.rgb * image.a + render_settings.background.rgb * (1.0 - image.a);
+        let background = behind * (1.0 - coverage);
+        let shown = color.rgb * render_settings.tint.rgb + background;
+        if render_settings.invert != 0u {
+            return vec4f(vec3f(1.0) - clamp(shown, vec3f(0.0), vec3f(1.0)), color.a);
+        }
+        return vec4f(shown, color.a);
     }
     discard;
 }
+
+// How far the glow spreads out from bright parts of the trail, in texture pixels.
+const GLOW_RADIUS: f32 = 12.0;
+// The glow is blurred from rings of samples at increasing distances from each pixel.
+const GLOW_RINGS: u32 = 3u;
+const GLOW_RING_SAMPLES: u32 = 8u;
+
+// The part of the trail bright enough to glow, as it'd be shown on screen.
+fn glowing(texcoord: vec2f) -> vec3f {
+    let color = tone_map(textureSampleLevel(ourTexture, ourSampler, texcoord, 0.0).rgb) * render_settings.tint.rgb;
+    return max(color - vec3f(render_settings.glow_threshold), vec3f(0.0));
+}
+
+// Blurs the bright parts of the trail, to be added on top of what fs drew.
+@fragment fn fs_glow(fsInput: VertexShaderOutput) -> @location(0) vec4f {
+    let xy = fsInput.position.xy;
+    // Adding light onto an inverted picture would only wash it back out
+    if (any(xy < uni.lower_bound) || any(uni.upper_bound < xy) || render_settings.invert != 0u) {
+        discard;
+    }
+    let texel = 1.0 / vec2f(textureDimensions(ourTexture));
+    var sum = glowing(fsInput.texcoord);
+    var total_weight = 1.0;
+    for (var ring = 1u; ring <= GLOW_RINGS; ring++) {
+        let distance = f32(ring) / f32(GLOW_RINGS);
+        // Further samples count for less, so the glow fades out smoothly
+        let weight = exp(-2.0 * distance * distance);
+        for (var i = 0u; i < GLOW_RING_SAMPLES; i++) {
+            // Every other ring is turned by half a step, so the samples don't line up into spokes
+            let angle = (f32(i) + 0.5 * f32(ring % 2u)) * 6.2831853 / f32(GLOW_RING_SAMPLES);
+            let offset = vec2f(cos(angle), sin(angle)) * distance * GLOW_RADIUS * texel;
+            sum += glowing(fsInput.texcoord + offset) * weight;
+            total_weight += weight;
+        }
+    }
+    return vec4f(sum / total_weight * render_settings.glow_intensity, 0.0);
+}
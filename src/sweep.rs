@@ -0,0 +1,109 @@
+//! Sweeps one parameter from one value to another while saving every frame, for showing off what
+//! it does. It goes by frames rather than by the clock, so the same sweep always comes out the
+//! same however fast it renders.
+
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use web_time::Instant;
+
+use crate::fs::settings::Param;
+use crate::graphics::Image;
+use crate::timelapse::{Interval, Timelapse};
+
+/// How many frames make up a second of the sweep, once they're turned into a video.
+pub const FRAME_RATE: f32 = 30.0;
+
+/// What to sweep, written like `sd0:0.0:2.0:10`.
+pub struct Spec {
+    name: String,
+    param: Param,
+    from: f32,
+    to: f32,
+    seconds: f32,
+}
+
+impl FromStr for Spec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let expected = || format!("expected param:from:to:seconds like sd0:0.0:2.0:10, got {s}");
+        let [name, from, to, seconds] = s.split(':').collect::<Vec<_>>()[..] else {
+            return Err(expected());
+        };
+        let param = Param::from_name(name).ok_or_else(|| format!("no parameter named {name}"))?;
+        let (Ok(from), Ok(to), Ok(seconds)) = (from.parse(), to.parse(), seconds.parse()) else {
+            return Err(expected());
+        };
+        if !f32::is_finite(seconds) || seconds <= 0.0 {
+            return Err(format!(
+                "the sweep has to take some time, got {seconds} seconds"
+            ));
+        }
+        Ok(Self {
+            name: name.to_string(),
+            param,
+            from,
+            to,
+            seconds,
+        })
+    }
+}
+
+pub struct Sweep {
+    spec: Spec,
+    /// Every frame rendered gets saved, until there are this many.
+    frames: u64,
+    /// Frames saved so far.
+    saved: u64,
+    timelapse: Timelapse,
+}
+
+impl Sweep {
+    /// Starts saving frames into `dir`, creating it if needed. Numbering carries on after any frames
+    /// already in there, like a timelapse.
+    pub fn start(spec: Spec, dir: PathBuf) -> std::io::Result<Self> {
+        let timelapse = Timelapse::start(dir, Interval::Frames(1))?;
+        let frames = ((spec.seconds * FRAME_RATE).round() as u64).max(1);
+        println!(
+            "Sweeping {} from {} to {} over {frames} frames",
+            spec.name, spec.from, spec.to
+        );
+        Ok(Self {
+            spec,
+            frames,
+            saved: 0,
+            timelapse,
+        })
+    }
+
+    /// What to set the parameter to for the next frame. Only moves on once a frame's been saved,
+    /// so none are skipped.
+    pub fn value(&self) -> (Param, f32) {
+        let t = match self.frames {
+            1 => 1.0,
+            frames => self.saved as f32 / (frames - 1) as f32,
+        };
+        let Spec {
+            param, from, to, ..
+        } = self.spec;
+        (param, from + (to - from) * t.min(1.0))
+    }
+
+    pub fn add_frame(&mut self, image: Image, now: Instant) {
+        self.timelapse.add_frame(image, now);
+        self.saved += 1;
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.saved >= self.frames
+    }
+
+    /// Says how it went. The last few frames finish being written when it's dropped.
+    pub fn finish(self) {
+        println!(
+            "Finished sweeping {}, saved {} frames",
+            self.spec.name, self.saved
+        );
+    }
+}
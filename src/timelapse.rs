@@ -0,0 +1,122 @@
+//! Saves a numbered PNG of the screen every so often, for turning a long run into a timelapse
+//! video of the pattern developing. Each frame waits on the GPU to be read back, so only the ones
+//! that get saved are.
+
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::thread::JoinHandle;
+
+use web_time::{Duration, Instant};
+
+use crate::graphics::Image;
+use crate::screenshot;
+
+/// How often to save a frame.
+#[derive(Clone, Copy)]
+pub enum Interval {
+    /// Every this many rendered frames.
+    Frames(u64),
+    /// Every this long, however many frames that is.
+    Time(Duration),
+}
+
+impl FromStr for Interval {
+    type Err = String;
+
+    /// A number of frames like `60`, or of seconds like `2.5s`.
+    fn from_str(s: &str) -> Result<Self, String> {
+        let interval = match s.strip_suffix('s') {
+            Some(secs) => secs
+                .parse()
+                .ok()
+                .and_then(|secs| Duration::try_from_secs_f32(secs).ok())
+                .filter(|secs| !secs.is_zero())
+                .map(Self::Time),
+            None => s
+                .parse()
+                .ok()
+                .filter(|&frames| frames > 0)
+                .map(Self::Frames),
+        };
+        interval.ok_or_else(|| {
+            format!("expected a number of frames like 60 or of seconds like 2.5s, got {s}")
+        })
+    }
+}
+
+pub struct Timelapse {
+    dir: PathBuf,
+    interval: Interval,
+    /// Frames rendered since the last one was saved.
+    frames: u64,
+    /// When the last one was saved, if any have been.
+    last_saved: Option<Instant>,
+    /// The number the next file gets.
+    next_number: u32,
+    /// Frames that might still be being written out.
+    writing: Vec<JoinHandle<()>>,
+}
+
+impl Timelapse {
+    /// Starts saving frames into `dir`, creating it if needed. Numbering carries on after any
+    /// frames already in there, so running again doesn't overwrite an earlier timelapse.
+    pub fn start(dir: PathBuf, interval: Interval) -> std::io::Result<Self> {
+        std::fs::create_dir_all(&dir)?;
+        let next_number = std::fs::read_dir(&dir)?
+            .filter_map(|entry| frame_number(&entry.ok()?.path()))
+            .max()
+            .map_or(0, |last| last + 1);
+        println!("Saving timelapse frames to {}", dir.display());
+        Ok(Self {
+            dir,
+            interval,
+            frames: 0,
+            last_saved: None,
+            next_number,
+            writing: Vec::new(),
+        })
+    }
+
+    /// Counts another frame being rendered, & says whether it should be read back & saved.
+    pub fn wants_frame(&mut self, now: Instant) -> bool {
+        self.frames += 1;
+        self.last_saved.is_none_or(|last| match self.interval {
+            Interval::Frames(frames) => self.frames >= frames,
+            Interval::Time(interval) => now.duration_since(last) >= interval,
+        })
+    }
+
+    /// Saves the frame as the next in the sequence, on its own thread so the event loop isn't held
+    /// up writing it out.
+    pub fn add_frame(&mut self, image: Image, now: Instant) {
+        self.frames = 0;
+        self.last_saved = Some(now);
+        let path = self.dir.join(format!("frame-{:06}.png", self.next_number));
+        self.next_number += 1;
+        self.writing.retain(|handle| !handle.is_finished());
+        self.writing.push(std::thread::spawn(move || {
+            if let Err(e) = screenshot::write_png(&path, &image) {
+                eprintln!("Error saving timelapse frame to {}: {e}", path.display());
+            }
+        }));
+    }
+}
+
+impl Drop for Timelapse {
+    /// Waits for the last few frames to be written, so quitting doesn't cut them off.
+    fn drop(&mut self) {
+        for handle in self.writing.drain(..) {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Which frame of a timelapse a file is, if it's one.
+fn frame_number(path: &Path) -> Option<u32> {
+    path.file_name()?
+        .to_str()?
+        .strip_prefix("frame-")?
+        .strip_suffix(".png")?
+        .parse()
+        .ok()
+}
@@ -0,0 +1,104 @@
+//! Lets something outside the program decide when the simulation advances, e.g. to keep it in step
+//! with other software or hardware in an installation. Every line that arrives, whether on stdin or
+//! over TCP, advances the simulation by one step.
+
+use std::io::BufRead;
+use std::net::{SocketAddr, TcpListener};
+use std::str::FromStr;
+use std::sync::mpsc;
+
+/// The most steps taken in one frame, so a burst of triggers doesn't stall rendering. Any more
+/// wait for the frames after.
+const MAX_STEPS_PER_FRAME: u32 = 4;
+/// The most triggers kept waiting for a frame. Past this, they're coming in faster than we can keep
+/// up with, so the extras are dropped.
+const MAX_BACKLOG: u32 = 64;
+
+/// Where triggers come from.
+pub enum Source {
+    Stdin,
+    /// Listens for connections on this address, so any of them can send triggers.
+    Tcp(SocketAddr),
+}
+
+impl FromStr for Source {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "stdin" {
+            return Ok(Self::Stdin);
+        }
+        s.parse()
+            .map(Self::Tcp)
+            .map_err(|_| format!("expected stdin or an address like 127.0.0.1:9000, got {s}"))
+    }
+}
+
+pub struct Trigger {
+    rx: mpsc::Receiver<()>,
+    /// Triggers that have arrived but haven't been stepped through yet.
+    backlog: u32,
+    /// Whether triggers are being dropped, so we only say so once each time it starts.
+    dropping: bool,
+}
+
+impl Trigger {
+    /// Starts listening for triggers in the background.
+    pub fn start(source: Source) -> std::io::Result<Self> {
+        let (tx, rx) = mpsc::channel();
+        match source {
+            Source::Stdin => {
+                std::thread::spawn(move || send_lines(std::io::stdin().lock(), &tx));
+            }
+            Source::Tcp(address) => {
+                let listener = TcpListener::bind(address)?;
+                println!("Listening for triggers on {address}");
+                std::thread::spawn(move || {
+                    for stream in listener.incoming() {
+                        match stream {
+                            Ok(stream) => {
+                                let tx = tx.clone();
+                                std::thread::spawn(move || {
+                                    send_lines(std::io::BufReader::new(stream), &tx)
+                                });
+                            }
+                            Err(e) => eprintln!("Error accepting trigger connection: {e}"),
+                        }
+                    }
+                });
+            }
+        }
+        Ok(Self {
+            rx,
+            backlog: 0,
+            dropping: false,
+        })
+    }
+
+    /// How many steps the simulation should take this frame, one for each trigger that's arrived
+    /// since the last frame.
+    pub fn take_steps(&mut self) -> u32 {
+        let arrived = self.rx.try_iter().count() as u32;
+        let backlog = self.backlog.saturating_add(arrived);
+        if backlog > MAX_BACKLOG {
+            if !self.dropping {
+                eprintln!("Triggers are arriving faster than frames can keep up, dropping some");
+            }
+            self.dropping = true;
+        } else {
+            self.dropping = false;
+        }
+        let steps = backlog.min(MAX_STEPS_PER_FRAME);
+        self.backlog = (backlog - steps).min(MAX_BACKLOG);
+        steps
+    }
+}
+
+/// Sends a trigger for each line read, until the input closes or nobody's listening anymore.
+fn send_lines(reader: impl BufRead, tx: &mpsc::Sender<()>) {
+    for line in reader.lines() {
+        if line.is_err() || tx.send(()).is_err() {
+            return;
+        }
+    }
+}